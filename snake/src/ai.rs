@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{config::WallMode, Direction, Game, Position};
+
+/// the direction to move the snake next,
+/// along the shortest path to the nearest food
+///
+/// when no food can be reached, or the path leads into a pocket
+/// too small for the snake, the direction leading to the most open cells
+/// is taken instead
+pub fn next_direction(game: &Game) -> Direction {
+    let head = game.snake.body[0];
+    let blocked: HashSet<Position> = game
+        .snake
+        .body
+        .iter()
+        .chain(&game.obstacles)
+        .copied()
+        .collect();
+
+    shortest_path_to_food(game, head, &blocked)
+        // not into a pocket too small for the snake
+        .filter(|direction| {
+            neighbors(game, head)
+                .into_iter()
+                .find(|(d, _)| d == direction)
+                .is_some_and(|(_, pos)| open_cells(game, pos, &blocked) >= game.snake.body.len())
+        })
+        .or_else(|| most_open_direction(game, head, &blocked))
+        .unwrap_or(game.snake.direction)
+}
+
+/// the positions the snake can move to from a position,
+/// going through the portals,
+/// not going through the wall when it kills
+fn neighbors(game: &Game, pos: Position) -> Vec<(Direction, Position)> {
+    pos.neighbors(game.width, game.height)
+        .into_iter()
+        .filter(|(direction, _)| {
+            game.config.wall_mode == WallMode::Wrap
+                || !pos.step(*direction).is_on_boundary(game.width, game.height)
+        })
+        .map(|(direction, next)| {
+            // the portals are entered before wrapping, as the snake moves
+            let next = game.portal_exit(pos.step(direction)).unwrap_or(next);
+            (direction, next)
+        })
+        .collect()
+}
+
+/// breadth first search from the head to the nearest food,
+/// returning the first step of the path
+fn shortest_path_to_food(
+    game: &Game,
+    head: Position,
+    blocked: &HashSet<Position>,
+) -> Option<Direction> {
+    // the first step taken to reach each visited cell
+    let mut first_step: HashMap<Position, Direction> = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    for (direction, pos) in neighbors(game, head) {
+        if blocked.contains(&pos) || first_step.contains_key(&pos) {
+            continue;
+        }
+        first_step.insert(pos, direction);
+        queue.push_back(pos);
+    }
+
+    while let Some(pos) = queue.pop_front() {
+        let direction = first_step[&pos];
+        if game.food_at(pos).is_some() {
+            return Some(direction);
+        }
+
+        for (_, next) in neighbors(game, pos) {
+            if blocked.contains(&next) || first_step.contains_key(&next) {
+                continue;
+            }
+            first_step.insert(next, direction);
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+/// the direction with the most cells reachable from the next position
+fn most_open_direction(
+    game: &Game,
+    head: Position,
+    blocked: &HashSet<Position>,
+) -> Option<Direction> {
+    neighbors(game, head)
+        .into_iter()
+        .filter(|(_, pos)| !blocked.contains(pos))
+        .max_by_key(|(_, pos)| open_cells(game, *pos, blocked))
+        .map(|(direction, _)| direction)
+}
+
+/// flood fill from a position, counting the cells reached
+fn open_cells(game: &Game, start: Position, blocked: &HashSet<Position>) -> usize {
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(pos) = queue.pop_front() {
+        for (_, next) in neighbors(game, pos) {
+            if !blocked.contains(&next) && visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    visited.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::GameConfig,
+        food::{Food, FoodKind},
+    };
+
+    fn food() -> Food {
+        Food {
+            score: 1,
+            kind: FoodKind::Normal,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn autopilot_does_not_bite_itself() {
+        for seed in 0..5 {
+            let config = GameConfig {
+                seed: Some(seed),
+                ..GameConfig::default()
+            };
+            let mut game = Game::new(20, 10, config);
+
+            for _ in 0..50 {
+                let direction = next_direction(&game);
+                assert_eq!(
+                    game.apply_direction_single(direction),
+                    Ok(()),
+                    "seed {seed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn autopilot_takes_the_portal_to_the_food() {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 0,
+            portal_pairs: vec![((4, 5).into(), (17, 5).into())],
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(20, 10, config);
+        game.place_food((18, 5).into(), food());
+
+        assert_eq!(next_direction(&game), Direction::Right);
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+        assert_eq!(game.snake.body[0], (17, 5).into());
+    }
+
+    #[test]
+    fn autopilot_goes_around_the_wall_when_it_kills() {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 0,
+            wall_mode: WallMode::Die,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(20, 10, config);
+        game.place_food((19, 5).into(), food());
+
+        assert_eq!(next_direction(&game), Direction::Right);
+    }
+}
@@ -0,0 +1,615 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{Direction, Position, INIT_SPEED_CPS};
+
+/// The configuration of a snake game.
+///
+/// any field missing from the config file keeps its default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// stretch the arena to fill the whole terminal
+    pub fit: bool,
+    /// render the game with characters only,
+    /// never emitting any color escape code
+    pub no_color: bool,
+    /// which keys move the snake
+    pub input_mode: InputMode,
+    /// tint the arena by how often the snake passed over each cell
+    pub heatmap: bool,
+    /// how long an expiring food stays in the arena,
+    /// 0 to never generate expiring food
+    pub food_expiry_secs: u64,
+    /// the chance of a new food to be expiring, from 0 to 1
+    pub expiring_food_chance: f64,
+    /// the chance of a new food to be a score multiplier, from 0 to 1
+    pub multiplier_food_chance: f64,
+    /// let the computer play the game
+    pub ai: bool,
+    /// the starting speed in blocks per second,
+    /// growing with the score
+    pub speed: f32,
+    /// how many food are kept in the arena
+    pub food_num: usize,
+    /// the highest score of a food, the lowest is 1
+    pub food_max_score: u16,
+    /// the starting length of the snake
+    pub init_length: u16,
+    /// what happens when the snake hits the wall
+    pub wall_mode: WallMode,
+    /// the seed of the food placement,
+    /// random when not set
+    pub seed: Option<u64>,
+    /// two players on the same keyboard,
+    /// player 1 with the arrow keys and player 2 with wasd
+    pub multiplayer: bool,
+    /// pairs of linked cells,
+    /// the snake entering one of them comes out of the other
+    pub portal_pairs: Vec<(Position, Position)>,
+    /// shrink the arena by one row and column every shrink interval
+    pub shrink_mode: bool,
+    #[serde(with = "duration_secs")]
+    pub shrink_interval: Duration,
+    /// the built-in level to play, from 1,
+    /// an open arena when not set
+    pub level: Option<usize>,
+    /// write the config to the config file and exit,
+    /// only given on the command line
+    #[serde(skip)]
+    pub write_config: bool,
+}
+
+/// a duration written as whole seconds in the config file
+mod duration_secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+/// What happens when the snake hits the wall.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallMode {
+    /// come out from the other side
+    #[default]
+    Wrap,
+    /// the game is over
+    Die,
+}
+
+impl FromStr for WallMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(WallMode::Wrap),
+            "die" => Ok(WallMode::Die),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            fit: false,
+            no_color: false,
+            input_mode: InputMode::default(),
+            heatmap: false,
+            food_expiry_secs: 10,
+            expiring_food_chance: 0.2,
+            multiplier_food_chance: 0.05,
+            ai: false,
+            speed: INIT_SPEED_CPS as f32,
+            food_num: 5,
+            food_max_score: 5,
+            init_length: 3,
+            wall_mode: WallMode::default(),
+            seed: None,
+            multiplayer: false,
+            portal_pairs: Vec::new(),
+            shrink_mode: false,
+            shrink_interval: Duration::from_secs(10),
+            level: None,
+            write_config: false,
+        }
+    }
+}
+
+/// parse a portal pair written as `x1,y1,x2,y2`
+fn parse_portal(s: &str) -> Option<(Position, Position)> {
+    let values: Vec<u16> = s
+        .split(',')
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [x1, y1, x2, y2] => Some((Position { x: x1, y: y1 }, Position { x: x2, y: y2 })),
+        _ => None,
+    }
+}
+
+/// The keys used to move the snake.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// arrow keys only
+    #[serde(rename = "arrows")]
+    ArrowKeys,
+    /// w, a, s, d only
+    WASD,
+    /// both arrow keys and w, a, s, d
+    #[default]
+    Both,
+}
+
+impl InputMode {
+    /// get the direction a key stands for in this input mode
+    ///
+    /// none if the key does not move the snake
+    pub fn direction_from_keycode(&self, code: KeyCode) -> Option<Direction> {
+        let arrow = match code {
+            KeyCode::Up => Some(Direction::Up),
+            KeyCode::Down => Some(Direction::Down),
+            KeyCode::Left => Some(Direction::Left),
+            KeyCode::Right => Some(Direction::Right),
+            _ => None,
+        };
+        let wasd = match code {
+            KeyCode::Char('w') => Some(Direction::Up),
+            KeyCode::Char('s') => Some(Direction::Down),
+            KeyCode::Char('a') => Some(Direction::Left),
+            KeyCode::Char('d') => Some(Direction::Right),
+            _ => None,
+        };
+
+        match self {
+            InputMode::ArrowKeys => arrow,
+            InputMode::WASD => wasd,
+            InputMode::Both => arrow.or(wasd),
+        }
+    }
+
+    /// the help text for the movement keys
+    pub fn help(&self) -> &'static str {
+        match self {
+            InputMode::ArrowKeys => "←↑→↓",
+            InputMode::WASD => "wasd",
+            InputMode::Both => "←↑→↓/wasd",
+        }
+    }
+}
+
+/// read and parse an environment variable,
+/// none if not set or not valid
+fn env_var<T: FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+impl GameConfig {
+    /// build the config from the `RGAMES_*` environment variables,
+    /// the default is kept for any variable not set or not valid
+    pub fn from_env() -> Self {
+        let mut config = GameConfig::default();
+        config.apply_env();
+
+        config
+    }
+
+    /// override the config with the `RGAMES_*` environment variables
+    /// that are set and valid
+    fn apply_env(&mut self) {
+        if let Some(speed) = env_var::<f32>("RGAMES_SPEED").filter(|v| *v > 0.0) {
+            self.speed = speed;
+        }
+        if let Some(food_num) = env_var("RGAMES_FOOD_NUM") {
+            self.food_num = food_num;
+        }
+        if let Some(init_length) = env_var::<u16>("RGAMES_INIT_LENGTH").filter(|v| *v > 0) {
+            self.init_length = init_length;
+        }
+        if let Some(wall_mode) = env_var("RGAMES_WALL_MODE") {
+            self.wall_mode = wall_mode;
+        }
+        if let Some(seed) = env_var("RGAMES_SEED") {
+            self.seed = Some(seed);
+        }
+    }
+
+    /// `~/.rgames/snake.toml`,
+    /// none if the home directory is unknown
+    pub fn config_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+
+        Some(Path::new(&home).join(".rgames").join("snake.toml"))
+    }
+
+    /// the config from the config file,
+    /// or the default one when there is no file
+    ///
+    /// a file that can't be parsed is reported and ignored
+    pub fn from_file_or_default() -> Self {
+        let content = match Self::config_path().and_then(|p| std::fs::read_to_string(p).ok()) {
+            Some(content) => content,
+            None => return Self::default(),
+        };
+
+        Self::from_toml(&content)
+    }
+
+    /// the config from the content of a config file
+    ///
+    /// a content that can't be parsed is reported and ignored,
+    /// and so are the values the game can't run with
+    pub fn from_toml(content: &str) -> Self {
+        match toml::from_str::<Self>(content) {
+            Ok(mut config) => {
+                config.reset_invalid_values();
+                config
+            }
+            Err(err) => {
+                eprintln!("warning: ignoring the invalid config file: {}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// put back the default of the values the game can't run with,
+    /// as the environment variables are checked
+    fn reset_invalid_values(&mut self) {
+        let default = Self::default();
+        if !(self.speed > 0.0 && self.speed.is_finite()) {
+            eprintln!(
+                "warning: ignoring the invalid speed {}, using {}",
+                self.speed, default.speed
+            );
+            self.speed = default.speed;
+        }
+        if self.init_length == 0 {
+            eprintln!(
+                "warning: ignoring the invalid init_length 0, using {}",
+                default.init_length
+            );
+            self.init_length = default.init_length;
+        }
+    }
+
+    /// the config as the content of a config file
+    ///
+    /// fails if the seed is too large for a toml integer
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// write the config to the config file
+    pub fn write_config_file(&self) -> std::io::Result<PathBuf> {
+        let path =
+            Self::config_path().ok_or_else(|| std::io::Error::other("unknown home directory"))?;
+        let content = self.to_toml_string().map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, content)?;
+
+        Ok(path)
+    }
+
+    /// the config from the environment,
+    /// or the default one when no `RGAMES_*` variable is set
+    pub fn from_env_or_default() -> Self {
+        let is_set = std::env::vars().any(|(name, _)| name.starts_with("RGAMES_"));
+        if is_set {
+            Self::from_env()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// build the config from the command line arguments,
+    /// the environment and the config file, in this order of precedence
+    pub fn from_args() -> Self {
+        let mut config = GameConfig::from_file_or_default();
+        config.apply_env();
+
+        // https://no-color.org
+        if let Ok(no_color) = std::env::var("NO_COLOR") {
+            if !no_color.is_empty() {
+                config.no_color = true;
+            }
+        }
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fit" => config.fit = true,
+                "--write-config" => config.write_config = true,
+                "--no-color" => config.no_color = true,
+                "--heatmap" => config.heatmap = true,
+                "--ai" => config.ai = true,
+                "--multiplayer" => config.multiplayer = true,
+                "--input" => match args.next().as_deref() {
+                    Some("arrows") => config.input_mode = InputMode::ArrowKeys,
+                    Some("wasd") => config.input_mode = InputMode::WASD,
+                    Some("both") => config.input_mode = InputMode::Both,
+                    _ => {}
+                },
+                "--portal" => {
+                    if let Some(pair) = args.next().as_deref().and_then(parse_portal) {
+                        config.portal_pairs.push(pair);
+                    }
+                }
+                "--shrink" => config.shrink_mode = true,
+                "--shrink-interval" => {
+                    if let Some(secs) = args.next().and_then(|v| v.parse().ok()) {
+                        config.shrink_interval = Duration::from_secs(secs);
+                    }
+                }
+                "--level" => {
+                    if let Some(level) = args.next().and_then(|v| v.parse().ok()) {
+                        config.level = Some(level);
+                    }
+                }
+                "--food-expiry" => {
+                    if let Some(secs) = args.next().and_then(|v| v.parse().ok()) {
+                        config.food_expiry_secs = secs;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARROWS: [(KeyCode, Direction); 4] = [
+        (KeyCode::Up, Direction::Up),
+        (KeyCode::Down, Direction::Down),
+        (KeyCode::Left, Direction::Left),
+        (KeyCode::Right, Direction::Right),
+    ];
+    const WASD: [(KeyCode, Direction); 4] = [
+        (KeyCode::Char('w'), Direction::Up),
+        (KeyCode::Char('s'), Direction::Down),
+        (KeyCode::Char('a'), Direction::Left),
+        (KeyCode::Char('d'), Direction::Right),
+    ];
+
+    #[test]
+    fn arrow_keys_mode_only_takes_the_arrows() {
+        for (code, direction) in ARROWS {
+            assert_eq!(
+                InputMode::ArrowKeys.direction_from_keycode(code),
+                Some(direction)
+            );
+        }
+        for (code, _) in WASD {
+            assert_eq!(InputMode::ArrowKeys.direction_from_keycode(code), None);
+        }
+    }
+
+    #[test]
+    fn wasd_mode_only_takes_wasd() {
+        for (code, direction) in WASD {
+            assert_eq!(
+                InputMode::WASD.direction_from_keycode(code),
+                Some(direction)
+            );
+        }
+        for (code, _) in ARROWS {
+            assert_eq!(InputMode::WASD.direction_from_keycode(code), None);
+        }
+    }
+
+    #[test]
+    fn both_mode_takes_both() {
+        for (code, direction) in ARROWS.into_iter().chain(WASD) {
+            assert_eq!(
+                InputMode::Both.direction_from_keycode(code),
+                Some(direction)
+            );
+        }
+    }
+
+    #[test]
+    fn other_keys_do_not_move() {
+        for mode in [InputMode::ArrowKeys, InputMode::WASD, InputMode::Both] {
+            for code in [KeyCode::Char('q'), KeyCode::Esc, KeyCode::Char('W')] {
+                assert_eq!(mode.direction_from_keycode(code), None);
+            }
+        }
+    }
+
+    /// a config with every field away from its default
+    fn custom() -> GameConfig {
+        GameConfig {
+            fit: true,
+            no_color: true,
+            input_mode: InputMode::WASD,
+            heatmap: true,
+            food_expiry_secs: 9,
+            expiring_food_chance: 0.4,
+            multiplier_food_chance: 0.125,
+            ai: true,
+            speed: 7.5,
+            food_num: 4,
+            food_max_score: 9,
+            init_length: 6,
+            wall_mode: WallMode::Die,
+            seed: Some(77),
+            multiplayer: true,
+            portal_pairs: vec![((2, 3).into(), (8, 9).into())],
+            shrink_mode: true,
+            shrink_interval: Duration::from_secs(25),
+            level: Some(2),
+            write_config: true,
+        }
+    }
+
+    #[test]
+    fn toml_round_trip_keeps_every_field() {
+        let config = custom();
+        let content = config.to_toml_string().unwrap();
+
+        let parsed: GameConfig = toml::from_str(&content).unwrap();
+
+        assert_eq!(parsed.to_toml_string().unwrap(), content);
+        assert!(parsed.fit && parsed.no_color && parsed.heatmap && parsed.ai);
+        assert_eq!(parsed.input_mode, InputMode::WASD);
+        assert_eq!(parsed.food_expiry_secs, 9);
+        assert_eq!(parsed.expiring_food_chance, 0.4);
+        assert_eq!(parsed.multiplier_food_chance, 0.125);
+        assert_eq!(parsed.speed, 7.5);
+        assert_eq!(parsed.food_num, 4);
+        assert_eq!(parsed.food_max_score, 9);
+        assert_eq!(parsed.init_length, 6);
+        assert_eq!(parsed.wall_mode, WallMode::Die);
+        assert_eq!(parsed.seed, Some(77));
+        assert!(parsed.multiplayer && parsed.shrink_mode);
+        assert_eq!(parsed.portal_pairs, config.portal_pairs);
+        assert_eq!(parsed.shrink_interval, Duration::from_secs(25));
+        assert_eq!(parsed.level, Some(2));
+        // only given on the command line
+        assert!(!parsed.write_config);
+    }
+
+    #[test]
+    fn wall_mode_is_written_in_lower_case() {
+        let content = custom().to_toml_string().unwrap();
+
+        assert!(content.contains("wall_mode = \"die\""));
+        assert!(content.contains("shrink_interval = 25"));
+        for wall_mode in [WallMode::Wrap, WallMode::Die] {
+            let config = GameConfig {
+                wall_mode,
+                ..GameConfig::default()
+            };
+            let parsed: GameConfig = toml::from_str(&config.to_toml_string().unwrap()).unwrap();
+            assert_eq!(parsed.wall_mode, wall_mode);
+        }
+    }
+
+    #[test]
+    fn missing_fields_keep_the_default() {
+        let parsed: GameConfig = toml::from_str("wall_mode = \"die\"\nfood_num = 2\n").unwrap();
+        let default = GameConfig::default();
+
+        assert_eq!(parsed.wall_mode, WallMode::Die);
+        assert_eq!(parsed.food_num, 2);
+        assert_eq!(parsed.speed, default.speed);
+        assert_eq!(parsed.shrink_interval, default.shrink_interval);
+        assert!(toml::from_str::<GameConfig>("wall_mode = \"bounce\"").is_err());
+    }
+
+    #[test]
+    fn speed_zero_in_the_file_keeps_the_default() {
+        let config = GameConfig::from_toml("speed = 0\nfood_num = 2\n");
+
+        assert_eq!(config.speed, GameConfig::default().speed);
+        assert_eq!(config.food_num, 2);
+    }
+
+    #[test]
+    fn invalid_values_in_the_file_keep_the_default() {
+        let default = GameConfig::default();
+
+        assert_eq!(GameConfig::from_toml("speed = -3.0").speed, default.speed);
+        assert_eq!(GameConfig::from_toml("speed = inf").speed, default.speed);
+        assert_eq!(GameConfig::from_toml("speed = nan").speed, default.speed);
+        assert_eq!(
+            GameConfig::from_toml("init_length = 0").init_length,
+            default.init_length
+        );
+        assert_eq!(GameConfig::from_toml("speed = 4.5").speed, 4.5);
+    }
+
+    /// the environment is shared by the tests running in parallel
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    const ENV_VARS: [&str; 5] = [
+        "RGAMES_SPEED",
+        "RGAMES_FOOD_NUM",
+        "RGAMES_INIT_LENGTH",
+        "RGAMES_WALL_MODE",
+        "RGAMES_SEED",
+    ];
+
+    /// the config read from the environment with the given variables set
+    fn config_with_env(vars: &[(&str, &str)]) -> GameConfig {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        for (name, value) in vars {
+            std::env::set_var(name, value);
+        }
+
+        let config = GameConfig::from_env();
+
+        for name in ENV_VARS {
+            std::env::remove_var(name);
+        }
+        config
+    }
+
+    #[test]
+    fn env_vars_set_the_config() {
+        let config = config_with_env(&[
+            ("RGAMES_SPEED", "12.5"),
+            ("RGAMES_FOOD_NUM", "7"),
+            ("RGAMES_INIT_LENGTH", "5"),
+            ("RGAMES_WALL_MODE", "die"),
+            ("RGAMES_SEED", "99"),
+        ]);
+
+        assert_eq!(config.speed, 12.5);
+        assert_eq!(config.food_num, 7);
+        assert_eq!(config.init_length, 5);
+        assert_eq!(config.wall_mode, WallMode::Die);
+        assert_eq!(config.seed, Some(99));
+    }
+
+    #[test]
+    fn invalid_env_vars_keep_the_default() {
+        let config = config_with_env(&[
+            ("RGAMES_SPEED", "-1"),
+            ("RGAMES_FOOD_NUM", "many"),
+            ("RGAMES_INIT_LENGTH", "0"),
+            ("RGAMES_WALL_MODE", "bounce"),
+            ("RGAMES_SEED", "-3"),
+        ]);
+        let default = GameConfig::default();
+
+        assert_eq!(config.speed, default.speed);
+        assert_eq!(config.food_num, default.food_num);
+        assert_eq!(config.init_length, default.init_length);
+        assert_eq!(config.wall_mode, default.wall_mode);
+        assert_eq!(config.seed, None);
+    }
+
+    #[test]
+    fn unset_env_vars_keep_the_default() {
+        let config = config_with_env(&[("RGAMES_WALL_MODE", "wrap")]);
+        let default = GameConfig::default();
+
+        assert_eq!(config.speed, default.speed);
+        assert_eq!(config.food_num, default.food_num);
+        assert_eq!(config.wall_mode, WallMode::Wrap);
+        assert_eq!(config.seed, None);
+    }
+}
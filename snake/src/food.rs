@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::{config::GameConfig, Position};
+
+// the score multiplier given by a multiplier food
+const MULTIPLIER_FACTOR: u16 = 2;
+// how long the score multiplier lasts
+const MULTIPLIER_DURATION: Duration = Duration::from_secs(10);
+
+/// a chance from the config that `gen_bool` accepts,
+/// cut to between 0 and 1
+fn valid_chance(chance: f64) -> f64 {
+    if chance.is_nan() {
+        0.0
+    } else {
+        chance.clamp(0.0, 1.0)
+    }
+}
+
+/// The food in the arena by position.
+pub type FoodMap = HashMap<Position, Food>;
+
+/// The kind of a food.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoodKind {
+    /// stays until eaten
+    Normal,
+    /// disappears if not eaten in time
+    Expiring,
+    /// multiplies the score gained for a while once eaten
+    Multiplier { factor: u16, duration: Duration },
+}
+
+/// A food in the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Food {
+    /// the score gained, and the length grown, when eaten
+    pub score: u16,
+    pub kind: FoodKind,
+    /// when the food disappears,
+    /// none if it never does
+    pub expires_at: Option<Instant>,
+}
+
+impl Food {
+    /// a new food with random score and kind,
+    /// expiring after the configured time if it is an expiring one
+    pub fn new_random(rng: &mut impl Rng, config: &GameConfig) -> Self {
+        let score = Food::random_score(rng, config);
+        let kind = Food::random_kind(rng, config);
+        let expires_at = match kind {
+            FoodKind::Expiring => {
+                Some(Instant::now() + Duration::from_secs(config.food_expiry_secs))
+            }
+            _ => None,
+        };
+
+        Food {
+            score,
+            kind,
+            expires_at,
+        }
+    }
+
+    /// a random score, from 1 to the configured highest score
+    pub fn random_score(rng: &mut impl Rng, config: &GameConfig) -> u16 {
+        rng.gen_range(1..=config.food_max_score.max(1))
+    }
+
+    /// a random kind, with the chances of the config,
+    /// expiring only if the config gives them a time
+    pub fn random_kind(rng: &mut impl Rng, config: &GameConfig) -> FoodKind {
+        if rng.gen_bool(valid_chance(config.multiplier_food_chance)) {
+            FoodKind::Multiplier {
+                factor: MULTIPLIER_FACTOR,
+                duration: MULTIPLIER_DURATION,
+            }
+        } else if config.food_expiry_secs > 0
+            && rng.gen_bool(valid_chance(config.expiring_food_chance))
+        {
+            FoodKind::Expiring
+        } else {
+            FoodKind::Normal
+        }
+    }
+
+    /// test if the food has disappeared at the given time
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|e| e <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const DRAWS: usize = 10_000;
+
+    /// the number of each kind in many draws
+    fn kind_counts(config: &GameConfig) -> (usize, usize, usize) {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (mut normal, mut expiring, mut multiplier) = (0, 0, 0);
+        for _ in 0..DRAWS {
+            match Food::random_kind(&mut rng, config) {
+                FoodKind::Normal => normal += 1,
+                FoodKind::Expiring => expiring += 1,
+                FoodKind::Multiplier { .. } => multiplier += 1,
+            }
+        }
+
+        (normal, expiring, multiplier)
+    }
+
+    /// test if a count is within 2 percent of the draws from the expected share
+    fn close_to(count: usize, share: f64) -> bool {
+        (count as f64 / DRAWS as f64 - share).abs() < 0.02
+    }
+
+    #[test]
+    fn kinds_follow_the_configured_chances() {
+        let (normal, expiring, multiplier) = kind_counts(&GameConfig::default());
+
+        // the expiring chance only applies to the food not a multiplier
+        assert!(close_to(multiplier, 0.05));
+        assert!(close_to(expiring, 0.95 * 0.2));
+        assert!(close_to(normal, 0.95 * 0.8));
+    }
+
+    #[test]
+    fn kinds_follow_other_chances() {
+        let config = GameConfig {
+            expiring_food_chance: 0.5,
+            multiplier_food_chance: 0.5,
+            ..GameConfig::default()
+        };
+        let (normal, expiring, multiplier) = kind_counts(&config);
+
+        assert!(close_to(multiplier, 0.5));
+        assert!(close_to(expiring, 0.25));
+        assert!(close_to(normal, 0.25));
+    }
+
+    #[test]
+    fn no_expiring_food_without_an_expiry_time() {
+        let config = GameConfig {
+            food_expiry_secs: 0,
+            expiring_food_chance: 1.0,
+            ..GameConfig::default()
+        };
+
+        assert_eq!(kind_counts(&config).1, 0);
+    }
+
+    #[test]
+    fn chances_out_of_range_are_cut() {
+        let config = GameConfig {
+            expiring_food_chance: 3.0,
+            multiplier_food_chance: f64::NAN,
+            ..GameConfig::default()
+        };
+
+        assert_eq!(kind_counts(&config), (0, DRAWS, 0));
+    }
+
+    #[test]
+    fn scores_spread_evenly_up_to_the_highest() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let config = GameConfig::default();
+        let mut counts = [0; 6];
+        for _ in 0..DRAWS {
+            counts[Food::random_score(&mut rng, &config) as usize] += 1;
+        }
+
+        assert_eq!(counts[0], 0);
+        assert!(counts[1..].iter().all(|&n| close_to(n, 0.2)));
+    }
+
+    #[test]
+    fn score_is_at_least_one() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let config = GameConfig {
+            food_max_score: 0,
+            ..GameConfig::default()
+        };
+
+        assert!((0..100).all(|_| Food::random_score(&mut rng, &config) == 1));
+    }
+
+    #[test]
+    fn only_expiring_food_expires() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let config = GameConfig::default();
+        for _ in 0..100 {
+            let food = Food::new_random(&mut rng, &config);
+            assert_eq!(food.expires_at.is_some(), food.kind == FoodKind::Expiring);
+        }
+    }
+
+    #[test]
+    fn food_expires_at_its_time_not_before() {
+        let now = Instant::now();
+        let food = Food {
+            score: 1,
+            kind: FoodKind::Expiring,
+            expires_at: Some(now + Duration::from_millis(1)),
+        };
+
+        assert!(!food.is_expired(now));
+        assert!(food.is_expired(now + Duration::from_millis(1)));
+        assert!(food.is_expired(now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn food_without_an_expiry_never_expires() {
+        let food = Food {
+            score: 1,
+            kind: FoodKind::Normal,
+            expires_at: None,
+        };
+
+        assert!(!food.is_expired(Instant::now() + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn expiring_food_lasts_the_configured_time() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let config = GameConfig {
+            food_expiry_secs: 7,
+            expiring_food_chance: 1.0,
+            multiplier_food_chance: 0.0,
+            ..GameConfig::default()
+        };
+        let before = Instant::now();
+
+        let food = Food::new_random(&mut rng, &config);
+
+        let expires_at = food.expires_at.unwrap();
+        assert!(expires_at >= before + Duration::from_secs(7));
+        assert!(expires_at <= Instant::now() + Duration::from_secs(7));
+    }
+}
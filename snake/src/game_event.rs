@@ -0,0 +1,16 @@
+use crate::{Direction, Position};
+
+/// The things happening in a game, reported to the event callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    /// the snake ate a food at a position
+    FoodEaten { pos: Position, score: u16 },
+    /// the snake hit its own body
+    SelfCollision,
+    /// the snake went through the wall
+    WallHit,
+    /// the snake turned to a new direction
+    DirectionChanged(Direction),
+    /// the score changed to a new value
+    ScoreChanged(u16),
+}
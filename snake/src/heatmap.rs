@@ -0,0 +1,87 @@
+use crossterm::style::Color;
+
+use crate::Position;
+
+/// the number of different tints in the heatmap,
+/// bucket 0 means never visited
+pub const HEATMAP_BUCKETS: u8 = 8;
+
+/// Counts how many times the snake passed over each cell of the arena.
+pub struct Heatmap {
+    width: u16,
+    height: u16,
+    visits: Vec<u32>,
+}
+
+impl Heatmap {
+    pub fn new(width: u16, height: u16) -> Self {
+        let size = width as usize * height as usize;
+        Heatmap {
+            width,
+            height,
+            visits: vec![0; size],
+        }
+    }
+
+    fn index(&self, pos: Position) -> Option<usize> {
+        if pos.x == 0 || pos.y == 0 || pos.x > self.width || pos.y > self.height {
+            return None;
+        }
+
+        Some((pos.y - 1) as usize * self.width as usize + (pos.x - 1) as usize)
+    }
+
+    /// record the snake passing over a cell
+    pub fn visit(&mut self, pos: Position) {
+        if let Some(i) = self.index(pos) {
+            self.visits[i] += 1;
+        }
+    }
+
+    /// how many times the snake passed over a cell
+    pub fn visits(&self, pos: Position) -> u32 {
+        self.index(pos).map_or(0, |i| self.visits[i])
+    }
+
+    /// the tint bucket of a cell
+    ///
+    /// grows with the log of the visits, so a few passes already show
+    pub fn bucket(&self, pos: Position) -> u8 {
+        match self.visits(pos) {
+            0 => 0,
+            v => (v.ilog2() as u8 + 1).min(HEATMAP_BUCKETS - 1),
+        }
+    }
+
+    /// the background color of a bucket,
+    /// from dark blue to bright red
+    pub fn color(bucket: u8) -> Color {
+        if bucket == 0 {
+            return Color::Reset;
+        }
+
+        let max = (HEATMAP_BUCKETS - 1) as u16;
+        let t = (bucket - 1) as u16;
+        let max_t = max - 1;
+        Color::Rgb {
+            r: (55 + 200 * t / max_t) as u8,
+            g: 0,
+            b: (120 - 120 * t / max_t) as u8,
+        }
+    }
+
+    /// the heatmap as text, one character per cell
+    pub fn to_text(&self) -> String {
+        const SHADES: [char; HEATMAP_BUCKETS as usize] = [' ', '.', ':', '-', '=', '+', '#', '@'];
+
+        let mut text = String::new();
+        for y in 1..=self.height {
+            for x in 1..=self.width {
+                text.push(SHADES[self.bucket(Position { x, y }) as usize]);
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+}
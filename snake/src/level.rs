@@ -0,0 +1,54 @@
+use crate::Position;
+
+/// A level with walls inside the arena.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Level {
+    pub name: String,
+    /// the cells killing the snake
+    pub obstacles: Vec<Position>,
+}
+
+/// the built-in levels, laid out for an arena of the given size
+///
+/// the row the snake starts on is kept free
+pub fn builtin_levels(width: u16, height: u16) -> Vec<Level> {
+    let start_row = height / 2;
+
+    // four 2x2 blocks, one in each quarter
+    let pillars = [(width / 4, height / 4), (width - width / 4, height / 4)]
+        .into_iter()
+        .chain([
+            (width / 4, height - height / 4),
+            (width - width / 4, height - height / 4),
+        ])
+        .flat_map(|(x, y)| [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)])
+        .map(|(x, y)| Position { x, y })
+        .collect();
+
+    // two walls across the middle half of the arena
+    let bars = [height / 4, height - height / 4]
+        .into_iter()
+        .flat_map(|y| (width / 4..=width - width / 4).map(move |x| Position { x, y }))
+        .collect();
+
+    // a wall down the middle, open around the starting row
+    let split = (1..=height)
+        .filter(|y| y.abs_diff(start_row) > 1)
+        .map(|y| Position { x: width / 2, y })
+        .collect();
+
+    [("Pillars", pillars), ("Bars", bars), ("Split", split)]
+        .into_iter()
+        .map(|(name, obstacles): (&str, Vec<Position>)| Level {
+            name: name.to_string(),
+            obstacles: obstacles
+                .into_iter()
+                .filter(|pos| {
+                    pos.y != start_row
+                        && (1..=width).contains(&pos.x)
+                        && (1..=height).contains(&pos.y)
+                })
+                .collect(),
+        })
+        .collect()
+}
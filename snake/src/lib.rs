@@ -0,0 +1,1954 @@
+use std::{
+    collections::HashSet,
+    io::{stdout, Write},
+};
+
+use crossterm::{cursor::MoveTo, event, style::Print, ExecutableCommand, QueueableCommand, Result};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+pub mod ai;
+pub mod config;
+pub mod food;
+pub mod game_event;
+pub mod heatmap;
+pub mod level;
+pub mod multiplayer;
+pub mod render;
+
+use config::{GameConfig, WallMode};
+use food::{Food, FoodKind, FoodMap};
+use game_event::GameEvent;
+use heatmap::Heatmap;
+use render::Renderer;
+
+pub(crate) const UPDATES_INTERVAL: std::time::Duration = std::time::Duration::from_millis(20);
+// the smallest the arena shrinks to
+const MIN_ARENA_SIZE: u16 = 5;
+/// default arena width when not fitting the terminal
+pub const ARENA_WIDTH: u16 = 40;
+/// default arena height when not fitting the terminal
+pub const ARENA_HEIGHT: u16 = 20;
+/// the terminal rows used besides the arena,
+/// the frame, the 3 lines score area and the help
+pub const EXTRA_ROWS: u16 = 6;
+/// the default starting speed, in blocks per second
+pub const INIT_SPEED_CPS: f64 = 2.0;
+
+/// convert a speed in blocks per nanosecond,
+/// as the game keeps it, to blocks per second
+///
+/// ```
+/// use snake::{cells_per_second_to_speed, speed_to_cells_per_second};
+///
+/// assert_eq!(speed_to_cells_per_second(0.0), 0.0);
+///
+/// let cps = speed_to_cells_per_second(cells_per_second_to_speed(8.0));
+/// assert!((cps - 8.0).abs() < 1e-5);
+/// ```
+pub fn speed_to_cells_per_second(speed: f32) -> f64 {
+    speed as f64 * 1e9
+}
+
+/// convert a speed in blocks per second
+/// to blocks per nanosecond, as the game keeps it
+///
+/// ```
+/// use snake::{cells_per_second_to_speed, speed_to_cells_per_second, INIT_SPEED_CPS};
+///
+/// assert_eq!(cells_per_second_to_speed(1e9), 1.0);
+///
+/// let speed = cells_per_second_to_speed(INIT_SPEED_CPS);
+/// assert!((speed_to_cells_per_second(speed) - INIT_SPEED_CPS).abs() < 1e-5);
+/// ```
+pub fn cells_per_second_to_speed(cps: f64) -> f32 {
+    (cps / 1e9) as f32
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// the four directions, always in this order
+    ///
+    /// ```
+    /// use snake::Direction;
+    ///
+    /// assert_eq!(
+    ///     Direction::all(),
+    ///     [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+    /// );
+    /// ```
+    pub fn all() -> [Direction; 4] {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+    }
+
+    /// the number of directions
+    ///
+    /// ```
+    /// use snake::Direction;
+    ///
+    /// assert_eq!(Direction::count(), 4);
+    /// ```
+    pub fn count() -> usize {
+        Self::all().len()
+    }
+
+    /// if the direction is along the x axis
+    ///
+    /// ```
+    /// use snake::Direction;
+    ///
+    /// assert!(Direction::Left.is_horizontal());
+    /// assert!(Direction::Right.is_horizontal());
+    /// assert!(!Direction::Up.is_horizontal());
+    /// ```
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Direction::Left | Direction::Right)
+    }
+
+    /// if the direction is along the y axis
+    ///
+    /// ```
+    /// use snake::Direction;
+    ///
+    /// assert!(Direction::Up.is_vertical());
+    /// assert!(Direction::Down.is_vertical());
+    /// assert!(!Direction::Right.is_vertical());
+    /// ```
+    pub fn is_vertical(&self) -> bool {
+        !self.is_horizontal()
+    }
+
+    /// the direction pointing the other way
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+/// What killed the snake.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CollisionKind {
+    /// the head entered the body
+    SelfCollision,
+    /// the head hit the wall, or was left outside the shrinking arena
+    WallDeath,
+    /// the head hit an obstacle of the level
+    ObstacleHit,
+}
+
+impl std::fmt::Display for CollisionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollisionKind::SelfCollision => write!(f, "the snake bit itself"),
+            CollisionKind::WallDeath => write!(f, "the snake hit the wall"),
+            CollisionKind::ObstacleHit => write!(f, "the snake hit an obstacle"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub struct Position {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl Position {
+    /// wrap a position that went through the wall to the other side
+    ///
+    /// the playable area is `1..=width` and `1..=height`,
+    /// so 0 wraps to the far side and `width + 1` wraps to 1
+    pub fn wrap(self, width: u16, height: u16) -> Self {
+        let mut pos = self;
+        if pos.x == 0 {
+            pos.x = width;
+        } else if pos.x == width + 1 {
+            pos.x = 1;
+        }
+        if pos.y == 0 {
+            pos.y = height;
+        } else if pos.y == height + 1 {
+            pos.y = 1;
+        }
+
+        pos
+    }
+
+    /// the position one step away in a direction,
+    /// possibly on the wall
+    pub fn step(self, direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Position {
+                x: self.x,
+                y: self.y - 1,
+            },
+            Direction::Down => Position {
+                x: self.x,
+                y: self.y + 1,
+            },
+            Direction::Left => Position {
+                x: self.x - 1,
+                y: self.y,
+            },
+            Direction::Right => Position {
+                x: self.x + 1,
+                y: self.y,
+            },
+        }
+    }
+
+    /// the four positions next to this one with the direction to reach them,
+    /// wrapped through the wall
+    pub fn neighbors(self, width: u16, height: u16) -> [(Direction, Position); 4] {
+        Direction::all().map(|direction| (direction, self.step(direction).wrap(width, height)))
+    }
+
+    /// the position counted from the top left cell of the arena, from 0,
+    /// the frame being at 0 in the position itself
+    ///
+    /// ```
+    /// use snake::Position;
+    ///
+    /// assert_eq!(Position { x: 1, y: 1 }.to_game_coords_string(), "(0, 0)");
+    /// assert_eq!(Position { x: 0, y: 5 }.to_game_coords_string(), "(-1, 4)");
+    /// ```
+    pub fn to_game_coords_string(&self) -> String {
+        format!("({}, {})", self.x as i32 - 1, self.y as i32 - 1)
+    }
+
+    /// the left of the two terminal cells showing this position,
+    /// relative to the arena
+    pub fn to_left_display(&self) -> (u16, u16) {
+        (self.x * 2 - 1, self.y)
+    }
+
+    /// the right of the two terminal cells showing this position,
+    /// relative to the arena
+    pub fn to_right_display(&self) -> (u16, u16) {
+        (self.x * 2, self.y)
+    }
+
+    /// the terminal cells showing this position, relative to the arena,
+    /// as x, y, width and height
+    pub fn to_display_rect(&self) -> (u16, u16, u16, u16) {
+        let (x, y) = self.to_left_display();
+        (x, y, 2, 1)
+    }
+
+    /// all the positions on the straight line from a to b, both included
+    ///
+    /// only horizontal and vertical lines are supported,
+    /// empty if the positions are not on the same row or column
+    pub fn all_between(a: Position, b: Position) -> Vec<Position> {
+        if a.x == b.x {
+            (a.y.min(b.y)..=a.y.max(b.y))
+                .map(|y| Position { x: a.x, y })
+                .collect()
+        } else if a.y == b.y {
+            (a.x.min(b.x)..=a.x.max(b.x))
+                .map(|x| Position { x, y: a.y })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// check if the position is on the wall around the playable area
+    pub fn is_on_boundary(&self, width: u16, height: u16) -> bool {
+        self.x == 0 || self.y == 0 || self.x == width + 1 || self.y == height + 1
+    }
+}
+
+impl From<Position> for u32 {
+    fn from(pos: Position) -> Self {
+        let mut res = pos.x as u32;
+        res <<= 16;
+        res += pos.y as u32;
+        res
+    }
+}
+
+impl From<(u16, u16)> for Position {
+    fn from((x, y): (u16, u16)) -> Self {
+        Position { x, y }
+    }
+}
+
+impl From<u32> for Position {
+    fn from(pos: u32) -> Self {
+        let mut res = pos;
+        let y = res & 0xffff;
+        res >>= 16;
+        let x = res & 0xffff;
+        Position {
+            x: x as u16,
+            y: y as u16,
+        }
+    }
+}
+
+/// written as `(x, y)`
+///
+/// ```
+/// use snake::Position;
+///
+/// assert_eq!(Position { x: 3, y: 7 }.to_string(), "(3, 7)");
+/// assert_eq!(format!("head at {}", Position { x: 0, y: 12 }), "head at (0, 12)");
+/// ```
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+pub struct Snake {
+    pub body: Vec<Position>,
+    pub direction: Direction,
+}
+
+impl Snake {
+    /// the body from the head, as `(x, y), (x, y), ...`
+    pub fn body_as_string(&self) -> String {
+        self.body
+            .iter()
+            .map(|pos| pos.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// the number of segments inside an arena of the given size
+    pub fn segments_in_bounds(&self, width: u16, height: u16) -> usize {
+        self.body.len() - self.segments_out_of_bounds(width, height).len()
+    }
+
+    /// the segments outside an arena of the given size, from the head
+    pub fn segments_out_of_bounds(&self, width: u16, height: u16) -> Vec<Position> {
+        self.body
+            .iter()
+            .filter(|pos| !((1..=width).contains(&pos.x) && (1..=height).contains(&pos.y)))
+            .copied()
+            .collect()
+    }
+
+    /// grow by n blocks
+    ///
+    /// the new blocks are stacked on the tail,
+    /// and unfold one by one as the snake moves on
+    pub fn grow(&mut self, n: u16) {
+        let tail = *self.body.last().unwrap();
+        for _ in 0..n {
+            self.body.push(tail);
+        }
+    }
+}
+
+pub struct Game {
+    pub snake: Snake,
+    pub food: FoodMap,
+    pub width: u16,
+    pub height: u16,
+    pub score: u16,
+    // the longest the snake has been in this game
+    pub longest_body_length: u16,
+    // per block per nanoseconds
+    //
+    // increase in ln(score)
+    pub speed: f32,
+    pub clear: Vec<Position>,
+    // unix timestamp in nanoseconds
+    pub last_move: u128,
+    // offset of the arena inside the terminal,
+    // used to center the arena
+    pub offset_x: u16,
+    pub offset_y: u16,
+    pub config: GameConfig,
+    // how many times the snake passed over each cell,
+    // only tracked in heatmap mode
+    pub heatmap: Option<Heatmap>,
+    // called for every game event
+    pub event_callbacks: Vec<Box<dyn Fn(GameEvent)>>,
+    // every game event with the time it happened,
+    // only collected once enabled, for debugging
+    pub event_log: Option<Vec<(std::time::Instant, GameEvent)>>,
+    // the source of the food placement,
+    // seeded from the config when set
+    pub rng: StdRng,
+    // pairs of cells linked together,
+    // the snake entering one of them comes out of the other
+    pub portals: Vec<(Position, Position)>,
+    // when the arena last shrank, or the game started
+    pub last_shrink: std::time::Instant,
+    // how many times the arena shrank
+    pub shrink_level: u16,
+    // the snake died, the game waits for a key before exiting
+    pub game_over: bool,
+    pub death_cause: Option<CollisionKind>,
+    // the score multiplier from the last multiplier food,
+    // and when it wears off
+    pub active_multiplier: Option<(u16, std::time::Instant)>,
+    // the cells inside the arena killing the snake,
+    // set by a level
+    pub obstacles: HashSet<Position>,
+}
+
+/// Loop with interval.
+///
+/// Each iteration of the loop will be executed with a given interval.
+/// If the execution of the loop body takes longer than the interval,
+/// the next iteration will be executed immediately.
+/// This function will block the current thread.
+pub(crate) fn loop_with_interval<F>(interval: std::time::Duration, mut f: F)
+where
+    F: FnMut(),
+{
+    loop {
+        let start = std::time::Instant::now();
+        f();
+        let elapsed = start.elapsed();
+        if elapsed < interval {
+            std::thread::sleep(interval - elapsed);
+        }
+    }
+}
+
+impl Game {
+    /// check if snake eat food
+    fn check_eat_food(&mut self) {
+        // get head position
+        let head = self.snake.body[0];
+
+        // check if snake eat food, removing it
+        if let Some(food) = self.remove_food(head) {
+            // the snake grows by the food score, only the score gained is multiplied
+            let score = food.score * self.score_multiplier();
+            self.emit(GameEvent::FoodEaten { pos: head, score });
+
+            if let FoodKind::Multiplier { factor, duration } = food.kind {
+                self.active_multiplier = Some((factor, std::time::Instant::now() + duration));
+            }
+
+            // increase score
+            self.score += score;
+            self.emit(GameEvent::ScoreChanged(self.score));
+
+            self.speed = self.speed_for_score();
+
+            // generate new food
+            self.generate_food();
+
+            self.grow_by(food.score);
+        }
+    }
+
+    /// the factor the score gained is multiplied by,
+    /// 1 when no multiplier is active
+    pub fn score_multiplier(&self) -> u16 {
+        match self.active_multiplier {
+            Some((factor, _)) if self.multiplier_remaining().is_some() => factor,
+            _ => 1,
+        }
+    }
+
+    /// the time left before the score multiplier wears off,
+    /// none when no multiplier is active
+    pub fn multiplier_remaining(&self) -> Option<std::time::Duration> {
+        let (_, expiry) = self.active_multiplier?;
+
+        expiry
+            .checked_duration_since(std::time::Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// grow the snake by n blocks,
+    /// keeping track of the longest it has been
+    pub fn grow_by(&mut self, n: u16) {
+        self.snake.grow(n);
+        self.longest_body_length = self.longest_body_length.max(self.snake_length());
+    }
+
+    /// check if hit wall
+    ///
+    /// if hit wall, then move snake to other side
+    fn check_hit_wall(&mut self) -> std::result::Result<(), CollisionKind> {
+        let head = self.snake.body[0];
+        if head.is_on_boundary(self.width, self.height) {
+            self.emit(GameEvent::WallHit);
+            match self.config.wall_mode {
+                WallMode::Wrap => self.snake.body[0] = head.wrap(self.width, self.height),
+                WallMode::Die => return Err(CollisionKind::WallDeath),
+            }
+        }
+
+        // the obstacles can't be wrapped around, whatever the wall mode
+        if self.obstacles.contains(&self.snake.body[0]) {
+            self.emit(GameEvent::WallHit);
+            return Err(CollisionKind::ObstacleHit);
+        }
+
+        Ok(())
+    }
+
+    /// check if hit itself
+    fn check_hit_itself(&mut self) -> std::result::Result<(), CollisionKind> {
+        // get head position
+        let head = self.snake.body[0];
+
+        // check if hit itself
+        if self.snake.body.iter().skip(1).any(|pos| *pos == head) {
+            self.emit(GameEvent::SelfCollision);
+            return Err(CollisionKind::SelfCollision);
+        }
+
+        Ok(())
+    }
+
+    /// register a callback called for every game event
+    pub fn on_event(&mut self, cb: impl Fn(GameEvent) + 'static) {
+        self.event_callbacks.push(Box::new(cb));
+    }
+
+    /// start collecting all the game events in the event log
+    pub fn enable_event_log(&mut self) {
+        if self.event_log.is_none() {
+            self.event_log = Some(Vec::new());
+        }
+    }
+
+    /// report an event to the callbacks and the event log
+    fn emit(&mut self, event: GameEvent) {
+        for cb in &self.event_callbacks {
+            cb(event);
+        }
+
+        if let Some(event_log) = &mut self.event_log {
+            event_log.push((std::time::Instant::now(), event));
+        }
+    }
+
+    /// change the direction of the snake,
+    /// reporting the change
+    fn set_direction(&mut self, direction: Direction) {
+        if self.snake.direction != direction {
+            self.snake.direction = direction;
+            self.emit(GameEvent::DirectionChanged(direction));
+        }
+    }
+
+    /// what killed the snake,
+    /// none while it is alive
+    pub fn collision_kind(&self) -> Option<CollisionKind> {
+        self.death_cause
+    }
+
+    /// end the game, the snake stops moving
+    fn die(&mut self, kind: CollisionKind) {
+        self.game_over = true;
+        self.death_cause = Some(kind);
+    }
+
+    /// leave the terminal, print the game over summary and exit
+    fn exit_game_over(&self) {
+        let mut stdout = stdout();
+        stdout.execute(crossterm::cursor::Show).unwrap();
+        stdout
+            .execute(crossterm::terminal::LeaveAlternateScreen)
+            .unwrap();
+        stdout.flush().unwrap();
+        crossterm::terminal::disable_raw_mode().unwrap();
+
+        let height = self.height + EXTRA_ROWS;
+
+        stdout.queue(MoveTo(0, height - 1)).unwrap();
+
+        // print game over
+        stdout.queue(Print("\nGame Over\n")).unwrap();
+
+        // print score
+        stdout
+            .queue(Print(format!("Score: {}\n", self.score)))
+            .unwrap();
+        stdout
+            .queue(Print(format!("Max length: {}\n", self.longest_body_length)))
+            .unwrap();
+
+        // print heatmap for sharing
+        if let Some(heatmap) = &self.heatmap {
+            stdout
+                .queue(Print(format!("\nHeatmap:\n{}", heatmap.to_text())))
+                .unwrap();
+        }
+
+        // flush
+        stdout.flush().unwrap();
+
+        // exit
+        std::process::exit(0);
+    }
+
+    /// the current length of the snake
+    pub fn snake_length(&self) -> u16 {
+        self.snake.body.len() as u16
+    }
+
+    /// the food at a position, if any
+    pub fn food_at(&self, pos: Position) -> Option<&Food> {
+        self.food.get(&pos)
+    }
+
+    /// the food at a position, to change it
+    pub fn food_at_mut(&mut self, pos: Position) -> Option<&mut Food> {
+        self.food.get_mut(&pos)
+    }
+
+    /// take the food away from a position
+    pub fn remove_food(&mut self, pos: Position) -> Option<Food> {
+        self.food.remove(&pos)
+    }
+
+    /// put a food at a position, replacing the one there
+    pub fn place_food(&mut self, pos: Position, food: Food) {
+        self.food.insert(pos, food);
+    }
+
+    /// the total score of the food in the arena
+    pub fn food_value_total(&self) -> u16 {
+        self.food.values().map(|food| food.score).sum()
+    }
+
+    /// the part of the arena covered by food
+    pub fn food_density(&self) -> f32 {
+        self.food.len() as f32 / self.playable_area() as f32
+    }
+
+    /// the number of cells in the arena
+    pub fn playable_area(&self) -> u32 {
+        self.width as u32 * self.height as u32
+    }
+
+    /// shrink the arena by its last row and column,
+    /// down to the minimum size
+    ///
+    /// the snake dies if its head is in the removed cells,
+    /// the rest of its body, the food, portals and obstacles there are removed
+    pub fn shrink_arena(&mut self) {
+        self.last_shrink = std::time::Instant::now();
+        if self.width <= MIN_ARENA_SIZE || self.height <= MIN_ARENA_SIZE {
+            return;
+        }
+
+        self.width -= 1;
+        self.height -= 1;
+        self.shrink_level += 1;
+
+        let (width, height) = (self.width, self.height);
+        let inside = |pos: &Position| pos.x <= width && pos.y <= height;
+        self.food.retain(|pos, _| inside(pos));
+        self.portals.retain(|(a, b)| inside(a) && inside(b));
+        self.obstacles.retain(inside);
+
+        if !inside(&self.snake.body[0]) {
+            self.die(CollisionKind::WallDeath);
+        } else {
+            // the head is inside, so the snake keeps at least one segment
+            self.snake.body.retain(inside);
+        }
+
+        self.generate_food();
+    }
+
+    /// shrink the arena if the shrink interval passed
+    fn check_shrink(&mut self) {
+        if self.config.shrink_mode && self.last_shrink.elapsed() >= self.config.shrink_interval {
+            self.shrink_arena();
+        }
+    }
+
+    /// center the arena in a terminal of the given size
+    pub fn center_in(&mut self, term_width: u16, term_height: u16) {
+        self.offset_x = term_width.saturating_sub(self.width * 2 + 2) / 2;
+        self.offset_y = term_height.saturating_sub(self.height + EXTRA_ROWS) / 2;
+    }
+
+    /// the starting speed, in blocks per nanoseconds
+    fn init_speed(&self) -> f32 {
+        cells_per_second_to_speed(self.config.speed as f64)
+    }
+
+    /// the speed at the current score,
+    /// growing with ln(score)
+    fn speed_for_score(&self) -> f32 {
+        ((self.score as f32).ln() + 1.0) * self.init_speed()
+    }
+
+    /// generate food in random position that not in snake body
+    fn generate_food(&mut self) {
+        let area = self.playable_area() as usize;
+        let max = self
+            .config
+            .food_num
+            .min(area.saturating_sub(self.snake.body.len()));
+        // the food can't be put on a portal either
+        let mut taken = self.occupied_cells();
+        taken.extend(self.portals.iter().flat_map(|(a, b)| [*a, *b]));
+
+        for _ in self.food.len()..max {
+            let p = taken.len() as f32 / area as f32;
+            let pos = if p < 0.7 {
+                // most cells are free, pick random ones until one is
+                loop {
+                    let pos = Position {
+                        x: self.rng.gen_range(1..=self.width),
+                        y: self.rng.gen_range(1..=self.height),
+                    };
+                    if !taken.contains(&pos) {
+                        break pos;
+                    }
+                }
+            } else {
+                let free: Vec<Position> = (1..=self.width)
+                    .flat_map(|x| (1..=self.height).map(move |y| Position { x, y }))
+                    .filter(|pos| !taken.contains(pos))
+                    .collect();
+                if free.is_empty() {
+                    break;
+                }
+
+                free[self.rng.gen_range(0..free.len())]
+            };
+
+            let food = Food::new_random(&mut self.rng, &self.config);
+            self.place_food(pos, food);
+            taken.insert(pos);
+        }
+    }
+
+    /// the cells taken by the snake, the food or the obstacles
+    pub fn occupied_cells(&self) -> HashSet<Position> {
+        self.snake
+            .body
+            .iter()
+            .chain(self.food.keys())
+            .chain(&self.obstacles)
+            .copied()
+            .collect()
+    }
+
+    /// the cells of the arena not taken by anything
+    pub fn free_cell_count(&self) -> usize {
+        (self.playable_area() as usize).saturating_sub(self.occupied_cells().len())
+    }
+
+    /// the other end of the portal at a position,
+    /// none if there is no portal there
+    pub fn portal_exit(&self, pos: Position) -> Option<Position> {
+        self.portals.iter().find_map(|&(a, b)| {
+            if a == pos {
+                Some(b)
+            } else if b == pos {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// remove the expired food,
+    /// and generate new one in place
+    fn remove_expired_food(&mut self) {
+        let now = std::time::Instant::now();
+        let expired: Vec<Position> = self
+            .food
+            .iter()
+            .filter(|(_, food)| food.is_expired(now))
+            .map(|(pos, _)| *pos)
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+
+        for pos in expired {
+            self.remove_food(pos);
+            self.clear.push(pos.to_left_display().into());
+            self.clear.push(pos.to_right_display().into());
+        }
+
+        self.generate_food();
+    }
+
+    /// handle event
+    fn handle_event(&mut self) -> Result<()> {
+        let event = event::poll(std::time::Duration::from_millis(0))?;
+        if event {
+            match event::read()? {
+                event::Event::Key(e) => match e.code {
+                    event::KeyCode::Char('q') => quit(),
+                    event::KeyCode::Esc => quit(),
+                    // the autopilot is driving
+                    _ if self.config.ai => {}
+                    code => {
+                        if let Some(direction) = self.config.input_mode.direction_from_keycode(code)
+                        {
+                            self.turn(direction)?;
+                        }
+                    }
+                },
+                event::Event::Resize(width, height) => {
+                    // re-center the arena
+                    self.center_in(width, height);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// handle a direction key
+    ///
+    /// pressing the current direction moves the snake forward once,
+    /// a 180° reversal is ignored
+    fn turn(&mut self, direction: Direction) -> Result<()> {
+        if self.snake.direction == direction {
+            if let Err(kind) = self.move_forward_once() {
+                self.die(kind);
+            }
+        } else if self.snake.direction != direction.opposite() {
+            self.set_direction(direction);
+        }
+
+        Ok(())
+    }
+
+    /// move snake
+    fn move_snake(&mut self) -> std::result::Result<(), CollisionKind> {
+        // get timestamp in milliseconds
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+
+        let interval = now - self.last_move;
+        let pass = 1.0 / self.speed;
+        // a speed too high for the clock still waits a nanosecond
+        let pass = (pass.floor() as u128).max(1);
+
+        if pass > interval {
+            return Ok(());
+        }
+
+        let jump = (interval / pass) as u16;
+
+        self.last_move += pass * jump as u128;
+
+        for _ in 0..jump {
+            if self.config.ai {
+                let direction = ai::next_direction(self);
+                self.set_direction(direction);
+            }
+            self.move_forward_once()?;
+        }
+
+        Ok(())
+    }
+
+    /// move forward
+    fn move_forward_once(&mut self) -> std::result::Result<(), CollisionKind> {
+        // get head position
+        let head = self.snake.body[0];
+
+        // get next position,
+        // going through the portal if there is one
+        let next = head.step(self.snake.direction);
+        let next = self.portal_exit(next).unwrap_or(next);
+
+        // move snake
+        self.snake.body.insert(0, next);
+
+        // clear tail, unless a block of the growth is still stacked on it
+        let tail = self.snake.body.pop().unwrap();
+        if self.snake.body.last() != Some(&tail) {
+            self.clear.push(tail.to_left_display().into());
+            self.clear.push(tail.to_right_display().into());
+        }
+
+        self.check_hit_wall()?;
+
+        if let Some(heatmap) = &mut self.heatmap {
+            heatmap.visit(self.snake.body[0]);
+        }
+        self.check_eat_food();
+        self.check_hit_itself()?;
+
+        Ok(())
+    }
+
+    /// turn to the given direction, then move forward once
+    ///
+    /// a 180° reversal is ignored and the snake keeps its current direction
+    pub fn apply_direction_single(
+        &mut self,
+        direction: Direction,
+    ) -> std::result::Result<(), CollisionKind> {
+        if direction != self.snake.direction.opposite() {
+            self.set_direction(direction);
+        }
+
+        self.move_forward_once()
+    }
+
+    /// apply a list of directions, moving forward once for each of them
+    ///
+    /// used to script game scenarios without going through crossterm events
+    pub fn apply_directions(
+        &mut self,
+        dirs: &[Direction],
+    ) -> std::result::Result<(), CollisionKind> {
+        for direction in dirs {
+            self.apply_direction_single(*direction)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn new(width: u16, height: u16, config: GameConfig) -> Self {
+        let heatmap = if config.heatmap {
+            Some(Heatmap::new(width, height))
+        } else {
+            None
+        };
+
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        // portals outside of the arena could never be entered
+        let portals = config
+            .portal_pairs
+            .iter()
+            .copied()
+            .filter(|(a, b)| {
+                [a, b]
+                    .iter()
+                    .all(|pos| (1..=width).contains(&pos.x) && (1..=height).contains(&pos.y))
+            })
+            .collect();
+
+        let mut snake = Snake {
+            body: vec![],
+            direction: Direction::Right,
+        };
+        // the snake need to fit in the arena
+        let init_length = config.init_length.clamp(1, width);
+        for i in (1..init_length + 1).rev() {
+            snake.body.push(Position {
+                x: i,
+                y: height / 2,
+            });
+        }
+
+        let mut game = Game {
+            snake,
+            food: FoodMap::new(),
+            width,
+            height,
+            score: 0,
+            longest_body_length: init_length,
+            speed: cells_per_second_to_speed(config.speed as f64),
+            clear: vec![],
+            last_move: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos(),
+            offset_x: 0,
+            offset_y: 0,
+            config,
+            heatmap,
+            event_callbacks: Vec::new(),
+            event_log: None,
+            rng,
+            portals,
+            last_shrink: std::time::Instant::now(),
+            shrink_level: 0,
+            game_over: false,
+            death_cause: None,
+            active_multiplier: None,
+            obstacles: HashSet::new(),
+        };
+
+        game.generate_food();
+
+        game
+    }
+
+    /// the game with walls inside the arena,
+    /// for a level
+    ///
+    /// the obstacles outside the arena or on the snake are dropped,
+    /// the food under them is moved
+    pub fn with_obstacles(mut self, obstacles: Vec<Position>) -> Self {
+        let (width, height) = (self.width, self.height);
+        self.obstacles = obstacles
+            .into_iter()
+            .filter(|pos| {
+                (1..=width).contains(&pos.x)
+                    && (1..=height).contains(&pos.y)
+                    && !self.snake.body.contains(pos)
+            })
+            .collect();
+
+        let covered: Vec<Position> = self
+            .food
+            .keys()
+            .copied()
+            .filter(|pos| self.obstacles.contains(pos))
+            .collect();
+        for pos in covered {
+            self.remove_food(pos);
+        }
+        self.generate_food();
+
+        self
+    }
+
+    /// Run the game
+    pub fn run(&mut self, renderer: &mut dyn Renderer) -> Result<()> {
+        let mut stdout = stdout();
+        stdout.execute(crossterm::cursor::Hide)?;
+        stdout.execute(crossterm::terminal::EnterAlternateScreen)?;
+        crossterm::terminal::enable_raw_mode()?;
+
+        // center the arena in the current terminal
+        let (width, height) = crossterm::terminal::size()?;
+        self.center_in(width, height);
+
+        // Draw the game
+        self.draw(renderer)?;
+
+        // Loop with interval
+        loop_with_interval(UPDATES_INTERVAL, || {
+            // Update game state
+            self.update().unwrap();
+
+            // Draw the game
+            self.draw(renderer).unwrap();
+
+            // the last frame stays until a key is pressed
+            if self.game_over {
+                wait_for_key().unwrap();
+                self.exit_game_over();
+            }
+        });
+
+        Ok(())
+    }
+
+    /// draw the game with a renderer,
+    /// the cleared cells are only drawn once
+    fn draw(&mut self, renderer: &mut dyn Renderer) -> Result<()> {
+        renderer.draw(self)?;
+        self.clear.clear();
+
+        Ok(())
+    }
+
+    /// update game state
+    pub fn update(&mut self) -> Result<()> {
+        if self.game_over {
+            return Ok(());
+        }
+
+        // handle event
+        self.handle_event()?;
+
+        // update snake
+        if let Err(kind) = self.move_snake() {
+            self.die(kind);
+        }
+
+        self.remove_expired_food();
+
+        self.check_shrink();
+
+        Ok(())
+    }
+}
+
+/// block until a key is pressed
+fn wait_for_key() -> Result<()> {
+    loop {
+        if let event::Event::Key(_) = event::read()? {
+            return Ok(());
+        }
+    }
+}
+
+pub(crate) fn quit() {
+    let mut stdout = stdout();
+    stdout.execute(crossterm::cursor::Show).unwrap();
+    stdout
+        .execute(crossterm::terminal::LeaveAlternateScreen)
+        .unwrap();
+    stdout.flush().unwrap();
+    crossterm::terminal::disable_raw_mode().unwrap();
+
+    std::process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// a seeded game without food,
+    /// the snake head at (3, height / 2) going right
+    fn game(width: u16, height: u16) -> Game {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 0,
+            ..GameConfig::default()
+        };
+
+        Game::new(width, height, config)
+    }
+
+    fn pos(x: u16, y: u16) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn each_death_has_its_collision_kind() {
+        let mut wall = game(10, 10);
+        wall.config.wall_mode = WallMode::Die;
+        assert_eq!(
+            wall.apply_directions(&[Direction::Up; 5]),
+            Err(CollisionKind::WallDeath)
+        );
+
+        let mut body = game(10, 10);
+        body.grow_by(2);
+        assert_eq!(
+            body.apply_directions(&[Direction::Down, Direction::Left, Direction::Up]),
+            Err(CollisionKind::SelfCollision)
+        );
+
+        let mut obstacle = game(10, 10).with_obstacles(vec![pos(3, 3)]);
+        assert_eq!(
+            obstacle.apply_directions(&[Direction::Up; 2]),
+            Err(CollisionKind::ObstacleHit)
+        );
+    }
+
+    #[test]
+    fn wrapping_through_the_wall_is_no_collision() {
+        let mut game = game(10, 10);
+
+        assert_eq!(game.apply_directions(&[Direction::Up; 12]), Ok(()));
+        assert_eq!(game.collision_kind(), None);
+    }
+
+    #[test]
+    fn death_keeps_its_collision_kind() {
+        let mut game = game(10, 10);
+        assert_eq!(game.collision_kind(), None);
+
+        game.die(CollisionKind::SelfCollision);
+
+        assert!(game.game_over);
+        assert_eq!(game.collision_kind(), Some(CollisionKind::SelfCollision));
+        assert_eq!(
+            game.collision_kind().unwrap().to_string(),
+            "the snake bit itself"
+        );
+    }
+
+    #[test]
+    fn shrink_drops_the_obstacles_outside_the_arena() {
+        let mut game = game(10, 10).with_obstacles(vec![pos(10, 3), pos(3, 10), pos(4, 4)]);
+
+        game.shrink_arena();
+
+        assert_eq!(game.obstacles, HashSet::from([pos(4, 4)]));
+    }
+
+    #[test]
+    fn obstacles_on_the_snake_or_outside_are_dropped() {
+        let game = game(10, 10).with_obstacles(vec![pos(2, 5), pos(11, 5), pos(6, 6)]);
+
+        assert_eq!(game.obstacles, HashSet::from([pos(6, 6)]));
+    }
+
+    #[test]
+    fn snake_dies_on_an_obstacle() {
+        let mut game = game(10, 10).with_obstacles(vec![pos(5, 5)]);
+
+        assert_eq!(game.apply_directions(&[Direction::Right]), Ok(()));
+        assert_eq!(
+            game.apply_directions(&[Direction::Right]),
+            Err(CollisionKind::ObstacleHit)
+        );
+    }
+
+    #[test]
+    fn obstacles_are_not_wrapped_around() {
+        let mut game = game(10, 10).with_obstacles(vec![pos(3, 10)]);
+
+        assert_eq!(
+            game.apply_directions(&[Direction::Up; 5]),
+            Err(CollisionKind::ObstacleHit)
+        );
+        assert_eq!(game.snake.body[0], pos(3, 10));
+    }
+
+    /// a snake along a row then down a column, ending at the bottom right
+    fn l_shaped_snake() -> Snake {
+        Snake {
+            body: vec![
+                pos(7, 7),
+                pos(8, 7),
+                pos(9, 7),
+                pos(10, 7),
+                pos(10, 8),
+                pos(10, 9),
+            ],
+            direction: Direction::Left,
+        }
+    }
+
+    #[test]
+    fn whole_snake_is_in_bounds_of_its_arena() {
+        let snake = l_shaped_snake();
+
+        assert_eq!(snake.segments_in_bounds(10, 10), 6);
+        assert!(snake.segments_out_of_bounds(10, 10).is_empty());
+    }
+
+    #[test]
+    fn segments_outside_a_shrunk_arena() {
+        let snake = l_shaped_snake();
+
+        assert_eq!(
+            snake.segments_out_of_bounds(9, 9),
+            [pos(10, 7), pos(10, 8), pos(10, 9)]
+        );
+        assert_eq!(snake.segments_in_bounds(9, 9), 3);
+
+        assert_eq!(
+            snake.segments_out_of_bounds(8, 8),
+            [pos(9, 7), pos(10, 7), pos(10, 8), pos(10, 9)]
+        );
+        assert_eq!(snake.segments_in_bounds(8, 8), 2);
+    }
+
+    #[test]
+    fn segments_outside_one_side_only() {
+        let snake = l_shaped_snake();
+
+        assert_eq!(snake.segments_out_of_bounds(10, 8), [pos(10, 9)]);
+        assert_eq!(snake.segments_in_bounds(10, 8), 5);
+        assert_eq!(snake.segments_in_bounds(6, 10), 0);
+    }
+
+    #[test]
+    fn shrink_keeps_the_segments_in_bounds() {
+        let mut game = shrinking(std::time::Duration::from_secs(60));
+        game.snake = l_shaped_snake();
+        let kept = game.snake.segments_in_bounds(9, 9);
+
+        game.shrink_arena();
+
+        assert!(!game.game_over);
+        assert_eq!(game.snake.body.len(), kept);
+        assert!(game.snake.segments_out_of_bounds(9, 9).is_empty());
+    }
+
+    /// a seeded game without food shrinking at the given interval
+    fn shrinking(interval: std::time::Duration) -> Game {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 0,
+            shrink_mode: true,
+            shrink_interval: interval,
+            ..GameConfig::default()
+        };
+
+        Game::new(10, 10, config)
+    }
+
+    #[test]
+    fn arena_shrinks_once_the_interval_passed() {
+        let mut game = shrinking(std::time::Duration::ZERO);
+
+        game.check_shrink();
+
+        assert_eq!((game.width, game.height, game.shrink_level), (9, 9, 1));
+    }
+
+    #[test]
+    fn arena_does_not_shrink_before_the_interval() {
+        let mut game = shrinking(std::time::Duration::from_secs(3600));
+
+        game.check_shrink();
+
+        assert_eq!((game.width, game.height, game.shrink_level), (10, 10, 0));
+    }
+
+    #[test]
+    fn arena_does_not_shrink_without_the_shrink_mode() {
+        let mut game = shrinking(std::time::Duration::ZERO);
+        game.config.shrink_mode = false;
+
+        game.check_shrink();
+
+        assert_eq!((game.width, game.height), (10, 10));
+    }
+
+    #[test]
+    fn arena_stops_at_the_minimum_size() {
+        let mut game = shrinking(std::time::Duration::ZERO);
+
+        for _ in 0..10 {
+            game.check_shrink();
+        }
+
+        assert_eq!((game.width, game.height), (MIN_ARENA_SIZE, MIN_ARENA_SIZE));
+        assert!(!game.game_over);
+    }
+
+    #[test]
+    fn snake_dies_with_its_head_on_the_removed_column() {
+        let mut game = shrinking(std::time::Duration::ZERO);
+        assert_eq!(game.apply_directions(&[Direction::Right; 7]), Ok(()));
+        assert_eq!(game.snake.body[0], pos(10, 5));
+
+        game.check_shrink();
+
+        assert!(game.game_over);
+        assert_eq!(game.collision_kind(), Some(CollisionKind::WallDeath));
+    }
+
+    #[test]
+    fn snake_lives_next_to_the_removed_column() {
+        let mut game = shrinking(std::time::Duration::ZERO);
+        assert_eq!(game.apply_directions(&[Direction::Right; 6]), Ok(()));
+
+        game.check_shrink();
+
+        assert!(!game.game_over);
+        assert_eq!(game.snake.body[0], pos(9, 5));
+    }
+
+    /// every position of an arena and its wall
+    fn arena_and_wall(width: u16, height: u16) -> impl Iterator<Item = Position> {
+        (0..=width + 1).flat_map(move |x| (0..=height + 1).map(move |y| pos(x, y)))
+    }
+
+    const SIZES: [(u16, u16); 4] = [(1, 1), (2, 7), (10, 10), (33, 5)];
+
+    #[test]
+    fn wrap_twice_is_wrap_once() {
+        for (width, height) in SIZES {
+            for p in arena_and_wall(width, height) {
+                let once = p.wrap(width, height);
+
+                assert_eq!(once.wrap(width, height), once, "{:?}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn wrap_lands_inside_the_arena() {
+        for (width, height) in SIZES {
+            for p in arena_and_wall(width, height) {
+                let wrapped = p.wrap(width, height);
+
+                assert!(!wrapped.is_on_boundary(width, height), "{:?}", p);
+                if !p.is_on_boundary(width, height) {
+                    assert_eq!(wrapped, p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrap_goes_to_the_far_side() {
+        assert_eq!(pos(0, 4).wrap(10, 8), pos(10, 4));
+        assert_eq!(pos(11, 4).wrap(10, 8), pos(1, 4));
+        assert_eq!(pos(4, 0).wrap(10, 8), pos(4, 8));
+        assert_eq!(pos(4, 9).wrap(10, 8), pos(4, 1));
+        assert_eq!(pos(0, 9).wrap(10, 8), pos(10, 1));
+    }
+
+    #[test]
+    fn body_is_written_from_the_head() {
+        let snake = Snake {
+            body: vec![pos(4, 2), pos(3, 2), pos(3, 3)],
+            direction: Direction::Right,
+        };
+
+        assert_eq!(snake.body_as_string(), "(4, 2), (3, 2), (3, 3)");
+        assert_eq!(
+            game(10, 10).snake.body_as_string(),
+            "(3, 5), (2, 5), (1, 5)"
+        );
+    }
+
+    #[test]
+    fn position_is_drawn_on_two_cells() {
+        assert_eq!(pos(1, 1).to_left_display(), (1, 1));
+        assert_eq!(pos(1, 1).to_right_display(), (2, 1));
+        assert_eq!(pos(7, 3).to_left_display(), (13, 3));
+        assert_eq!(pos(7, 3).to_right_display(), (14, 3));
+    }
+
+    #[test]
+    fn display_rect_covers_both_cells() {
+        for p in arena_and_wall(10, 6).filter(|p| p.x > 0) {
+            let (x, y, width, height) = p.to_display_rect();
+
+            assert_eq!((x, y), p.to_left_display());
+            assert_eq!((x + width - 1, y + height - 1), p.to_right_display());
+        }
+    }
+
+    #[test]
+    fn neighbours_do_not_share_cells() {
+        let a = pos(4, 2);
+        let b = a.step(Direction::Right);
+
+        assert_eq!(a.to_right_display().0 + 1, b.to_left_display().0);
+        assert_eq!(pos(9, 9).to_display_rect(), (17, 9, 2, 1));
+    }
+
+    #[test]
+    fn fresh_snake_takes_its_length() {
+        let game = game(10, 10);
+
+        assert_eq!(game.occupied_cells().len(), 3);
+        assert_eq!(game.free_cell_count(), 97);
+    }
+
+    #[test]
+    fn growth_takes_a_cell_per_move() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 5), food(3));
+
+        let mut free = Vec::new();
+        for _ in 0..4 {
+            assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+            free.push(game.free_cell_count());
+        }
+
+        // the stacked blocks share the tail cell until they unfold
+        assert_eq!(free, [97, 96, 95, 94]);
+        assert_eq!(game.occupied_cells().len(), game.snake.body.len());
+    }
+
+    #[test]
+    fn placed_food_and_obstacles_take_cells() {
+        let mut game = game(10, 10).with_obstacles(vec![pos(8, 8), pos(8, 9)]);
+        game.place_food(pos(5, 1), food(1));
+
+        let occupied = game.occupied_cells();
+        assert_eq!(occupied.len(), 6);
+        assert!(occupied.contains(&pos(5, 1)));
+        assert!(occupied.contains(&pos(8, 9)));
+        assert_eq!(game.free_cell_count(), 94);
+    }
+
+    #[test]
+    fn food_fills_the_free_cells_of_a_small_arena() {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 13,
+            ..GameConfig::default()
+        };
+        let game = Game::new(4, 4, config);
+
+        assert_eq!(game.food.len(), 13);
+        assert_eq!(game.occupied_cells().len(), 16);
+        assert_eq!(game.free_cell_count(), 0);
+    }
+
+    /// a seeded game with portals and the given food number
+    fn portal_game(portals: Vec<(Position, Position)>, food_num: usize) -> Game {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num,
+            portal_pairs: portals,
+            ..GameConfig::default()
+        };
+
+        Game::new(10, 10, config)
+    }
+
+    #[test]
+    fn portal_sends_the_head_to_the_other_end() {
+        let mut game = portal_game(vec![(pos(4, 5), pos(8, 2))], 0);
+
+        assert_eq!(game.apply_directions(&[Direction::Right]), Ok(()));
+
+        assert_eq!(game.snake.body[0], pos(8, 2));
+        assert_eq!(game.snake.direction, Direction::Right);
+        assert_eq!(game.apply_directions(&[Direction::Right]), Ok(()));
+        assert_eq!(game.snake.body[0], pos(9, 2));
+    }
+
+    #[test]
+    fn portal_works_both_ways() {
+        let mut game = portal_game(vec![(pos(8, 2), pos(4, 5))], 0);
+
+        assert_eq!(game.apply_directions(&[Direction::Right]), Ok(()));
+
+        assert_eq!(game.snake.body[0], pos(8, 2));
+        assert_eq!(game.portal_exit(pos(8, 2)), Some(pos(4, 5)));
+        assert_eq!(game.portal_exit(pos(4, 5)), Some(pos(8, 2)));
+        assert_eq!(game.portal_exit(pos(5, 5)), None);
+    }
+
+    #[test]
+    fn portals_outside_the_arena_are_dropped() {
+        let game = portal_game(vec![(pos(4, 5), pos(11, 2)), (pos(1, 1), pos(9, 9))], 0);
+
+        assert_eq!(game.portals, [(pos(1, 1), pos(9, 9))]);
+    }
+
+    #[test]
+    fn food_is_eaten_at_the_portal_exit() {
+        let mut game = portal_game(vec![(pos(4, 5), pos(8, 2))], 0);
+        game.place_food(pos(8, 2), food(2));
+
+        assert_eq!(game.apply_directions(&[Direction::Right]), Ok(()));
+
+        assert_eq!(game.score, 2);
+        assert_eq!(game.snake_length(), 5);
+    }
+
+    #[test]
+    fn no_food_on_a_portal() {
+        let portals = vec![(pos(4, 5), pos(8, 2)), (pos(1, 1), pos(10, 10))];
+        // every cell left besides the snake and the portals
+        let game = portal_game(portals.clone(), 93);
+
+        for (a, b) in portals {
+            assert!(game.food_at(a).is_none());
+            assert!(game.food_at(b).is_none());
+        }
+        assert_eq!(game.food.len(), 93);
+    }
+
+    #[test]
+    fn drawing_empties_the_clear_list() {
+        let mut game = game(10, 10);
+        game.apply_directions(&[Direction::Down]).unwrap();
+        assert!(!game.clear.is_empty());
+
+        game.draw(&mut render::NullRenderer).unwrap();
+
+        assert!(game.clear.is_empty());
+    }
+
+    #[test]
+    fn game_plays_on_without_a_terminal() {
+        let mut game = game(10, 10);
+
+        for direction in [Direction::Down, Direction::Left, Direction::Up] {
+            game.apply_direction_single(direction).unwrap();
+            game.draw(&mut render::NullRenderer).unwrap();
+        }
+
+        assert_eq!(game.snake.body[0], pos(2, 5));
+        assert!(game.clear.is_empty());
+        assert!(!game.game_over);
+    }
+
+    /// a normal food never expiring
+    fn food(score: u16) -> Food {
+        Food {
+            score,
+            kind: FoodKind::Normal,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn food_at_finds_the_placed_food() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 2), food(3));
+
+        assert_eq!(game.food_at(pos(4, 2)), Some(&food(3)));
+        assert_eq!(game.food_at(pos(2, 4)), None);
+    }
+
+    #[test]
+    fn food_at_mut_changes_the_food() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 2), food(3));
+
+        game.food_at_mut(pos(4, 2)).unwrap().score = 5;
+
+        assert_eq!(game.food_at(pos(4, 2)), Some(&food(5)));
+        assert!(game.food_at_mut(pos(2, 4)).is_none());
+    }
+
+    #[test]
+    fn remove_food_takes_it_once() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 2), food(3));
+
+        assert_eq!(game.remove_food(pos(4, 2)), Some(food(3)));
+        assert_eq!(game.remove_food(pos(4, 2)), None);
+        assert_eq!(game.food_at(pos(4, 2)), None);
+    }
+
+    #[test]
+    fn place_food_replaces_the_food_there() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 2), food(3));
+        game.place_food(pos(4, 2), food(1));
+
+        assert_eq!(game.food.len(), 1);
+        assert_eq!(game.food_at(pos(4, 2)), Some(&food(1)));
+    }
+
+    /// the events reported to a callback from now on
+    fn record_events(game: &mut Game) -> Rc<RefCell<Vec<GameEvent>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&events);
+        game.on_event(move |event| recorded.borrow_mut().push(event));
+
+        events
+    }
+
+    #[test]
+    fn eating_reports_the_food_then_the_score() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 5), food(2));
+        let events = record_events(&mut game);
+
+        assert_eq!(game.apply_directions(&[Direction::Right]), Ok(()));
+
+        assert_eq!(
+            *events.borrow(),
+            [
+                GameEvent::FoodEaten {
+                    pos: pos(4, 5),
+                    score: 2
+                },
+                GameEvent::ScoreChanged(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn turning_is_reported_once_per_change() {
+        let mut game = game(10, 10);
+        let events = record_events(&mut game);
+
+        let moves = [
+            Direction::Right,
+            Direction::Down,
+            Direction::Down,
+            Direction::Left,
+            // a reversal is ignored
+            Direction::Right,
+        ];
+        assert_eq!(game.apply_directions(&moves), Ok(()));
+
+        assert_eq!(
+            *events.borrow(),
+            [
+                GameEvent::DirectionChanged(Direction::Down),
+                GameEvent::DirectionChanged(Direction::Left),
+            ]
+        );
+    }
+
+    #[test]
+    fn going_through_the_wall_is_reported_when_it_happens() {
+        let mut game = game(10, 10);
+        let events = record_events(&mut game);
+
+        assert_eq!(game.apply_directions(&[Direction::Up; 4]), Ok(()));
+        assert_eq!(
+            *events.borrow(),
+            [GameEvent::DirectionChanged(Direction::Up)]
+        );
+
+        assert_eq!(game.apply_directions(&[Direction::Up]), Ok(()));
+        assert_eq!(events.borrow().last(), Some(&GameEvent::WallHit));
+    }
+
+    #[test]
+    fn biting_itself_is_logged_last() {
+        let mut game = game(10, 10);
+        game.grow_by(2);
+        game.enable_event_log();
+
+        assert_eq!(
+            game.apply_directions(&[Direction::Down, Direction::Left, Direction::Up]),
+            Err(CollisionKind::SelfCollision)
+        );
+
+        let logged: Vec<GameEvent> = game
+            .event_log
+            .unwrap()
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect();
+        assert_eq!(
+            logged,
+            [
+                GameEvent::DirectionChanged(Direction::Down),
+                GameEvent::DirectionChanged(Direction::Left),
+                GameEvent::DirectionChanged(Direction::Up),
+                GameEvent::SelfCollision,
+            ]
+        );
+    }
+
+    #[test]
+    fn longest_length_grows_with_each_food_eaten() {
+        let mut game = game(20, 10);
+        game.place_food(pos(4, 5), food(1));
+        game.place_food(pos(6, 5), food(3));
+        assert_eq!(game.longest_body_length, 3);
+
+        let mut longest = Vec::new();
+        for _ in 0..4 {
+            assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+            longest.push(game.longest_body_length);
+        }
+
+        assert_eq!(longest, [4, 4, 7, 7]);
+        assert_eq!(game.longest_body_length, game.snake_length());
+    }
+
+    /// a food multiplying the score by 3 for a minute once eaten
+    fn multiplier(score: u16) -> Food {
+        Food {
+            score,
+            kind: FoodKind::Multiplier {
+                factor: 3,
+                duration: std::time::Duration::from_secs(60),
+            },
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn no_multiplier_at_the_start() {
+        let game = game(10, 10);
+
+        assert_eq!(game.score_multiplier(), 1);
+        assert_eq!(game.multiplier_remaining(), None);
+    }
+
+    #[test]
+    fn eating_a_multiplier_starts_it() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 5), multiplier(2));
+
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+
+        // the multiplier food itself is not multiplied
+        assert_eq!(game.score, 2);
+        assert_eq!(game.score_multiplier(), 3);
+        let remaining = game.multiplier_remaining().unwrap();
+        assert!(remaining <= std::time::Duration::from_secs(60));
+        assert!(remaining > std::time::Duration::from_secs(50));
+    }
+
+    #[test]
+    fn food_eaten_under_a_multiplier_scores_more() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 5), multiplier(1));
+        game.place_food(pos(5, 5), food(2));
+
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+
+        assert_eq!(game.score, 1 + 2 * 3);
+        assert_eq!(game.snake_length(), 3 + 1 + 2);
+    }
+
+    #[test]
+    fn worn_off_multiplier_scores_normally() {
+        let mut game = game(10, 10);
+        game.active_multiplier = Some((3, std::time::Instant::now()));
+        game.place_food(pos(4, 5), food(2));
+
+        assert_eq!(game.score_multiplier(), 1);
+        assert_eq!(game.multiplier_remaining(), None);
+
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+        assert_eq!(game.score, 2);
+    }
+
+    #[test]
+    fn longest_length_counts_the_growth_not_the_multiplied_score() {
+        let mut game = game(20, 10);
+        game.active_multiplier = Some((
+            3,
+            std::time::Instant::now() + std::time::Duration::from_secs(60),
+        ));
+        game.place_food(pos(4, 5), food(2));
+
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+
+        assert_eq!(game.score, 6);
+        assert_eq!(game.longest_body_length, 5);
+    }
+
+    /// test if a cell of the snake body is in the clear list
+    fn clears_the_body(game: &Game) -> bool {
+        game.snake.body.iter().any(|block| {
+            game.clear.contains(&block.to_left_display().into())
+                || game.clear.contains(&block.to_right_display().into())
+        })
+    }
+
+    #[test]
+    fn grow_by_adds_blocks_on_the_tail() {
+        let mut game = game(10, 10);
+        let head = game.snake.body[0];
+        let tail = *game.snake.body.last().unwrap();
+
+        game.grow_by(4);
+
+        assert_eq!(game.snake_length(), 7);
+        assert_eq!(game.snake.body[0], head);
+        assert!(game.snake.body[2..].iter().all(|&block| block == tail));
+        assert!(game.clear.is_empty());
+    }
+
+    #[test]
+    fn eating_grows_by_the_food_score() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 5), food(3));
+
+        assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+
+        assert_eq!(game.snake_length(), 6);
+        assert_eq!(game.snake.body[0], pos(4, 5));
+        assert!(!clears_the_body(&game));
+    }
+
+    #[test]
+    fn growth_unfolds_one_block_per_move() {
+        let mut game = game(10, 10);
+        game.place_food(pos(4, 5), food(2));
+
+        let mut lengths = Vec::new();
+        for _ in 0..3 {
+            assert_eq!(game.apply_direction_single(Direction::Right), Ok(()));
+            lengths.push(game.snake_length());
+            assert!(!clears_the_body(&game));
+        }
+
+        assert_eq!(lengths, [5, 5, 5]);
+        let distinct: HashSet<Position> = game.snake.body.iter().copied().collect();
+        assert_eq!(distinct.len(), 5);
+    }
+
+    #[test]
+    fn no_food_has_no_value_or_density() {
+        let game = game(10, 10);
+
+        assert_eq!(game.food_value_total(), 0);
+        assert_eq!(game.food_density(), 0.0);
+    }
+
+    #[test]
+    fn food_value_and_density_add_up_the_food() {
+        let mut game = game(10, 5);
+        game.place_food(pos(7, 2), food(3));
+        game.place_food(pos(8, 2), food(5));
+
+        assert_eq!(game.food_value_total(), 8);
+        assert_eq!(game.food_density(), 2.0 / 50.0);
+
+        game.remove_food(pos(7, 2));
+        assert_eq!(game.food_value_total(), 5);
+        assert_eq!(game.food_density(), 1.0 / 50.0);
+    }
+
+    #[test]
+    fn expired_food_is_removed_and_cleared() {
+        let mut game = game(10, 10);
+        let expiring = Food {
+            score: 1,
+            kind: FoodKind::Expiring,
+            expires_at: Some(std::time::Instant::now()),
+        };
+        game.place_food(pos(7, 2), expiring);
+        game.place_food(pos(8, 2), food(1));
+
+        game.remove_expired_food();
+
+        assert!(game.food_at(pos(7, 2)).is_none());
+        assert!(game.food_at(pos(8, 2)).is_some());
+        assert_eq!(
+            game.clear,
+            [
+                pos(7, 2).to_left_display().into(),
+                pos(7, 2).to_right_display().into()
+            ]
+        );
+    }
+
+    #[test]
+    fn expired_food_is_replaced() {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 1,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(10, 10, config);
+        let (&old, _) = game.food.iter().next().unwrap();
+        game.remove_food(old);
+        let expiring = Food {
+            score: 1,
+            kind: FoodKind::Expiring,
+            expires_at: Some(std::time::Instant::now()),
+        };
+        game.place_food(old, expiring);
+
+        game.remove_expired_food();
+
+        assert_eq!(game.food.len(), 1);
+        assert!(game.food_at(old).is_none());
+    }
+
+    #[test]
+    fn food_not_yet_expired_stays() {
+        let mut game = game(10, 10);
+        let expiring = Food {
+            score: 1,
+            kind: FoodKind::Expiring,
+            expires_at: Some(std::time::Instant::now() + std::time::Duration::from_secs(60)),
+        };
+        game.place_food(pos(7, 2), expiring);
+
+        game.remove_expired_food();
+
+        assert!(game.food_at(pos(7, 2)).is_some());
+        assert!(game.clear.is_empty());
+    }
+}
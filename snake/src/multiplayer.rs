@@ -0,0 +1,409 @@
+use std::io::{stdout, Write};
+
+use crossterm::{cursor::MoveTo, event, style::Print, ExecutableCommand, QueueableCommand, Result};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    cells_per_second_to_speed,
+    config::{GameConfig, InputMode, WallMode},
+    food::{Food, FoodKind, FoodMap},
+    loop_with_interval, quit,
+    render::CrosstermRenderer,
+    Direction, Position, Snake, EXTRA_ROWS, UPDATES_INTERVAL,
+};
+
+/// Two players snake on the same keyboard,
+/// player 1 moves with the arrow keys and player 2 with wasd.
+pub struct MultiplayerGame {
+    pub snakes: [Snake; 2],
+    pub food: FoodMap,
+    pub scores: [u16; 2],
+    pub width: u16,
+    pub height: u16,
+    // per block per nanoseconds,
+    // both snakes move at the same pace
+    pub speed: f32,
+    pub clear: Vec<Position>,
+    // unix timestamp in nanoseconds
+    pub last_move: u128,
+    // offset of the arena inside the terminal,
+    // used to center the arena
+    pub offset_x: u16,
+    pub offset_y: u16,
+    pub config: GameConfig,
+    pub rng: StdRng,
+}
+
+/// find which snakes lost after both of them moved
+///
+/// a snake loses when its head enters its own body or the other snake,
+/// when the heads meet both lose
+pub fn collisions(snakes: &[Snake; 2]) -> [bool; 2] {
+    let lost = |player: usize| {
+        let snake = &snakes[player];
+        let other = &snakes[1 - player];
+        let head = snake.body[0];
+
+        snake.body.iter().skip(1).any(|pos| *pos == head) || other.body.contains(&head)
+    };
+
+    [lost(0), lost(1)]
+}
+
+impl MultiplayerGame {
+    pub fn new(width: u16, height: u16, config: GameConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        // the snakes start on their own side, facing each other
+        let init_length = config.init_length.clamp(1, width / 2);
+        let p1 = Snake {
+            body: (1..=init_length)
+                .rev()
+                .map(|x| Position {
+                    x,
+                    y: (height / 3).max(1),
+                })
+                .collect(),
+            direction: Direction::Right,
+        };
+        let p2 = Snake {
+            body: (width - init_length + 1..=width)
+                .map(|x| Position {
+                    x,
+                    y: height - height / 3,
+                })
+                .collect(),
+            direction: Direction::Left,
+        };
+
+        let mut game = MultiplayerGame {
+            snakes: [p1, p2],
+            food: FoodMap::new(),
+            scores: [0, 0],
+            width,
+            height,
+            speed: cells_per_second_to_speed(config.speed as f64),
+            clear: vec![],
+            last_move: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos(),
+            offset_x: 0,
+            offset_y: 0,
+            config,
+            rng,
+        };
+
+        game.generate_food();
+
+        game
+    }
+
+    /// center the arena in a terminal of the given size
+    pub fn center_in(&mut self, term_width: u16, term_height: u16) {
+        self.offset_x = term_width.saturating_sub(self.width * 2 + 2) / 2;
+        self.offset_y = term_height.saturating_sub(self.height + EXTRA_ROWS) / 2;
+    }
+
+    /// fill the arena with food up to the configured number,
+    /// on the cells not taken by any snake
+    fn generate_food(&mut self) {
+        let mut free: Vec<Position> = (1..=self.width)
+            .flat_map(|x| (1..=self.height).map(move |y| Position { x, y }))
+            .filter(|pos| {
+                !self.food.contains_key(pos)
+                    && !self.snakes.iter().any(|snake| snake.body.contains(pos))
+            })
+            .collect();
+        free.shuffle(&mut self.rng);
+
+        let missing = self.config.food_num.saturating_sub(self.food.len());
+        for pos in free.into_iter().take(missing) {
+            let score = Food::random_score(&mut self.rng, &self.config);
+            self.food.insert(
+                pos,
+                Food {
+                    score,
+                    kind: FoodKind::Normal,
+                    expires_at: None,
+                },
+            );
+        }
+    }
+
+    /// handle event
+    fn handle_event(&mut self) -> Result<()> {
+        let event = event::poll(std::time::Duration::from_millis(0))?;
+        if event {
+            match event::read()? {
+                event::Event::Key(e) => match e.code {
+                    event::KeyCode::Char('q') => quit(),
+                    event::KeyCode::Esc => quit(),
+                    code => {
+                        if let Some(direction) = InputMode::ArrowKeys.direction_from_keycode(code) {
+                            self.turn(0, direction);
+                        } else if let Some(direction) = InputMode::WASD.direction_from_keycode(code)
+                        {
+                            self.turn(1, direction);
+                        }
+                    }
+                },
+                event::Event::Resize(width, height) => {
+                    // re-center the arena
+                    self.center_in(width, height);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// change the direction of a snake,
+    /// a 180° reversal is ignored
+    fn turn(&mut self, player: usize, direction: Direction) {
+        let snake = &mut self.snakes[player];
+        if snake.direction != direction.opposite() {
+            snake.direction = direction;
+        }
+    }
+
+    /// move both snakes as many steps as the time passed
+    fn move_snakes(&mut self) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+
+        let interval = now - self.last_move;
+        let pass = 1.0 / self.speed;
+        // a speed too high for the clock still waits a nanosecond
+        let pass = (pass.floor() as u128).max(1);
+
+        if pass > interval {
+            return;
+        }
+
+        let jump = (interval / pass) as u16;
+
+        self.last_move += pass * jump as u128;
+
+        for _ in 0..jump {
+            self.move_forward_once();
+        }
+    }
+
+    /// move both snakes forward in the same tick,
+    /// then check who lost
+    fn move_forward_once(&mut self) {
+        let mut lost = [false, false];
+
+        for (player, snake) in self.snakes.iter_mut().enumerate() {
+            let next = snake.body[0].step(snake.direction);
+            snake.body.insert(0, next);
+
+            // clear tail, unless a block of the growth is still stacked on it
+            let tail = snake.body.pop().unwrap();
+            if snake.body.last() != Some(&tail) {
+                self.clear.push(tail.to_left_display().into());
+                self.clear.push(tail.to_right_display().into());
+            }
+
+            if next.is_on_boundary(self.width, self.height) {
+                match self.config.wall_mode {
+                    WallMode::Wrap => snake.body[0] = next.wrap(self.width, self.height),
+                    WallMode::Die => lost[player] = true,
+                }
+            }
+        }
+
+        for player in 0..2 {
+            self.check_eat_food(player);
+        }
+
+        let collided = collisions(&self.snakes);
+        let lost = [lost[0] || collided[0], lost[1] || collided[1]];
+        if lost.contains(&true) {
+            self.game_over(lost);
+        }
+    }
+
+    /// check if a snake eat food
+    fn check_eat_food(&mut self, player: usize) {
+        let head = self.snakes[player].body[0];
+
+        if let Some(Food { score, .. }) = self.food.remove(&head) {
+            self.scores[player] += score;
+            self.snakes[player].grow(score);
+
+            self.generate_food();
+        }
+    }
+
+    /// game over, naming the winner
+    fn game_over(&self, lost: [bool; 2]) {
+        let mut stdout = stdout();
+        stdout.execute(crossterm::cursor::Show).unwrap();
+        stdout
+            .execute(crossterm::terminal::LeaveAlternateScreen)
+            .unwrap();
+        stdout.flush().unwrap();
+        crossterm::terminal::disable_raw_mode().unwrap();
+
+        let height = self.height + EXTRA_ROWS;
+
+        stdout.queue(MoveTo(0, height - 1)).unwrap();
+
+        let result = match lost {
+            [true, false] => "Game Over - Player 2 wins",
+            [false, true] => "Game Over - Player 1 wins",
+            _ => "Game Over - Draw",
+        };
+        stdout.queue(Print(format!("\n{}\n", result))).unwrap();
+
+        stdout
+            .queue(Print(format!(
+                "P1: {}\nP2: {}\n",
+                self.scores[0], self.scores[1]
+            )))
+            .unwrap();
+
+        stdout.flush().unwrap();
+
+        std::process::exit(0);
+    }
+
+    /// Run the game
+    pub fn run(&mut self, renderer: &mut CrosstermRenderer) -> Result<()> {
+        let mut stdout = stdout();
+        stdout.execute(crossterm::cursor::Hide)?;
+        stdout.execute(crossterm::terminal::EnterAlternateScreen)?;
+        crossterm::terminal::enable_raw_mode()?;
+
+        // center the arena in the current terminal
+        let (width, height) = crossterm::terminal::size()?;
+        self.center_in(width, height);
+
+        self.draw(renderer)?;
+
+        loop_with_interval(UPDATES_INTERVAL, || {
+            self.handle_event().unwrap();
+            self.move_snakes();
+
+            self.draw(renderer).unwrap();
+        });
+
+        Ok(())
+    }
+
+    /// draw the game,
+    /// the cleared cells are only drawn once
+    fn draw(&mut self, renderer: &mut CrosstermRenderer) -> Result<()> {
+        renderer.draw_multiplayer(self)?;
+        self.clear.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a snake with the body given from the head
+    fn snake(body: &[(u16, u16)]) -> Snake {
+        Snake {
+            body: body.iter().map(|&pos| pos.into()).collect(),
+            direction: Direction::Right,
+        }
+    }
+
+    #[test]
+    fn snakes_apart_do_not_collide() {
+        let snakes = [
+            snake(&[(3, 2), (2, 2), (1, 2)]),
+            snake(&[(3, 4), (2, 4), (1, 4)]),
+        ];
+
+        assert_eq!(collisions(&snakes), [false, false]);
+    }
+
+    #[test]
+    fn head_on_the_other_body_loses() {
+        let snakes = [
+            snake(&[(3, 3), (2, 3), (1, 3)]),
+            snake(&[(2, 3), (2, 4), (2, 5)]),
+        ];
+
+        assert_eq!(collisions(&snakes), [false, true]);
+    }
+
+    #[test]
+    fn heads_meeting_is_a_draw() {
+        let snakes = [
+            snake(&[(3, 3), (2, 3), (1, 3)]),
+            snake(&[(3, 3), (4, 3), (5, 3)]),
+        ];
+
+        assert_eq!(collisions(&snakes), [true, true]);
+    }
+
+    #[test]
+    fn head_on_its_own_body_loses() {
+        let snakes = [
+            snake(&[(2, 2), (3, 2), (3, 3), (2, 3), (2, 2)]),
+            snake(&[(5, 5), (6, 5), (7, 5)]),
+        ];
+
+        assert_eq!(collisions(&snakes), [true, false]);
+    }
+
+    #[test]
+    fn eating_food_grows_the_snake_by_its_score() {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 0,
+            ..GameConfig::default()
+        };
+        let mut game = MultiplayerGame::new(20, 10, config);
+        let head = game.snakes[1].body[0];
+        let length = game.snakes[1].body.len();
+        game.food.insert(
+            head,
+            Food {
+                score: 4,
+                kind: FoodKind::Normal,
+                expires_at: None,
+            },
+        );
+
+        game.check_eat_food(1);
+
+        assert_eq!(game.scores, [0, 4]);
+        assert_eq!(game.snakes[1].body.len(), length + 4);
+        assert!(game.food.is_empty());
+    }
+
+    #[test]
+    fn stacked_tail_is_not_cleared() {
+        let config = GameConfig {
+            seed: Some(0),
+            food_num: 0,
+            ..GameConfig::default()
+        };
+        let mut game = MultiplayerGame::new(20, 10, config);
+        let tail = *game.snakes[0].body.last().unwrap();
+        game.snakes[0].grow(2);
+
+        game.move_forward_once();
+
+        assert_eq!(game.snakes[0].body.last(), Some(&tail));
+        assert!(!game.clear.contains(&tail.to_left_display().into()));
+        assert!(!game.clear.contains(&tail.to_right_display().into()));
+        // the other snake did not grow, its tail is cleared
+        assert_eq!(game.clear.len(), 2);
+    }
+}
@@ -0,0 +1,500 @@
+use std::{
+    collections::HashMap,
+    io::{stdout, Stdout, Write},
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    style::{Color, Print, SetBackgroundColor},
+    terminal::{Clear, ClearType},
+    QueueableCommand, Result,
+};
+
+use crate::{
+    config::GameConfig,
+    food::{FoodKind, FoodMap},
+    heatmap::Heatmap,
+    multiplayer::MultiplayerGame,
+    Game, Position, EXTRA_ROWS,
+};
+
+/// The parts of a game drawn the same way in every mode.
+pub trait Arena {
+    /// the playable width, in blocks
+    fn width(&self) -> u16;
+    /// the playable height, in blocks
+    fn height(&self) -> u16;
+    fn config(&self) -> &GameConfig;
+    fn food(&self) -> &FoodMap;
+    /// the arena relative screen cells to clear
+    fn cleared(&self) -> &[Position];
+    /// the offset of the arena inside the terminal
+    fn offset(&self) -> (u16, u16);
+
+    /// translate a position relative to the arena into terminal position
+    fn to_screen(&self, pos: Position) -> Position {
+        let (x, y) = self.offset();
+        Position {
+            x: pos.x + x,
+            y: pos.y + y,
+        }
+    }
+}
+
+/// Draw a game to some output.
+pub trait Renderer {
+    /// draw the current state of the game
+    fn draw(&mut self, game: &Game) -> Result<()>;
+}
+
+/// Renderer drawing the game to the terminal with crossterm,
+/// or any other output taking the same commands.
+pub struct CrosstermRenderer<W: Write = Stdout> {
+    out: W,
+    /// the heatmap bucket last drawn for each cell
+    heat_drawn: HashMap<Position, u8>,
+    /// the arena offset and size of the last frame,
+    /// the screen is wiped when it changes
+    last_layout: Option<(u16, u16, u16, u16)>,
+}
+
+/// Renderer doing nothing, for running a game without a terminal.
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn draw(&mut self, _game: &Game) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Default for CrosstermRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Renderer for CrosstermRenderer<W> {
+    /// Draw the game
+    fn draw(&mut self, game: &Game) -> Result<()> {
+        let help = match game.collision_kind() {
+            Some(kind) => format!("Game Over, {}. Press any key", kind),
+            None => format!("Move: {} Quit: q, Esc", game.config.input_mode.help()),
+        };
+
+        self.clear_screen(game)?;
+        self.draw_frame(game)?;
+        self.draw_obstacles(game)?;
+        self.draw_score_area(game)?;
+        self.draw_help(game, &help)?;
+        self.draw_heatmap(game)?;
+        self.draw_portals(game)?;
+        self.draw_snake(game, &game.snake.body, Color::Green, "##")?;
+        self.draw_food(game)?;
+
+        self.out.flush()?;
+
+        Ok(())
+    }
+}
+
+impl CrosstermRenderer {
+    pub fn new() -> Self {
+        CrosstermRenderer::with_writer(stdout())
+    }
+}
+
+impl<W: Write> CrosstermRenderer<W> {
+    /// a renderer writing the terminal commands to an output
+    pub fn with_writer(out: W) -> Self {
+        CrosstermRenderer {
+            out,
+            heat_drawn: HashMap::new(),
+            last_layout: None,
+        }
+    }
+
+    /// the output the commands are written to
+    pub fn writer(&self) -> &W {
+        &self.out
+    }
+
+    /// queue a background color change,
+    /// skipped when the game is rendered without color
+    fn queue_background(&mut self, arena: &impl Arena, color: Color) -> Result<()> {
+        if !arena.config().no_color {
+            self.out.queue(SetBackgroundColor(color))?;
+        }
+
+        Ok(())
+    }
+
+    fn clear_screen(&mut self, arena: &impl Arena) -> Result<()> {
+        // the arena moved or shrank, e.g. after a resize,
+        // the old frame need to be wiped
+        let (x, y) = arena.offset();
+        let layout = (x, y, arena.width(), arena.height());
+        if self.last_layout != Some(layout) {
+            self.out.queue(Clear(ClearType::All))?;
+            self.heat_drawn.clear();
+            self.last_layout = Some(layout);
+        }
+
+        self.queue_background(arena, Color::Reset)?;
+
+        for pos in arena.cleared() {
+            // the cleared cell need to get its heatmap tint back
+            self.heat_drawn.remove(&Position {
+                x: pos.x.div_ceil(2),
+                y: pos.y,
+            });
+
+            let pos = arena.to_screen(*pos);
+            self.out.queue(MoveTo(pos.x, pos.y))?;
+            self.out.queue(Print(" "))?;
+        }
+
+        Ok(())
+    }
+
+    /// repaint the heatmap cells whose tint changed,
+    /// the snake and food are drawn on top of it
+    fn draw_heatmap(&mut self, game: &Game) -> Result<()> {
+        if game.config.no_color {
+            return Ok(());
+        }
+        let heatmap = match &game.heatmap {
+            Some(heatmap) => heatmap,
+            None => return Ok(()),
+        };
+
+        for y in 1..=game.height {
+            for x in 1..=game.width {
+                let pos = Position { x, y };
+                let bucket = heatmap.bucket(pos);
+                if self.heat_drawn.get(&pos) == Some(&bucket) {
+                    continue;
+                }
+                self.heat_drawn.insert(pos, bucket);
+
+                self.queue_background(game, Heatmap::color(bucket))?;
+                let pos = game.to_screen(pos.to_left_display().into());
+                self.out.queue(MoveTo(pos.x, pos.y))?;
+                self.out.queue(Print("  "))?;
+            }
+        }
+        self.queue_background(game, Color::Reset)?;
+
+        Ok(())
+    }
+
+    /// draw the obstacles of the level,
+    /// the same for every frame
+    fn draw_obstacles(&mut self, game: &Game) -> Result<()> {
+        self.queue_background(game, Color::White)?;
+        for pos in &game.obstacles {
+            let pos = game.to_screen(pos.to_left_display().into());
+            self.out.queue(MoveTo(pos.x, pos.y))?;
+            if game.config.no_color {
+                self.out.queue(Print("[]"))?;
+            } else {
+                self.out.queue(Print("  "))?;
+            }
+        }
+        self.queue_background(game, Color::Reset)?;
+
+        Ok(())
+    }
+
+    /// draw both ends of the portals,
+    /// the snake is drawn on top of them
+    fn draw_portals(&mut self, game: &Game) -> Result<()> {
+        self.queue_background(game, Color::Cyan)?;
+        for pos in game.portals.iter().flat_map(|(a, b)| [a, b]) {
+            let pos = game.to_screen(pos.to_left_display().into());
+            self.out.queue(MoveTo(pos.x, pos.y))?;
+            if game.config.no_color {
+                self.out.queue(Print("()"))?;
+            } else {
+                self.out.queue(Print("  "))?;
+            }
+        }
+        self.queue_background(game, Color::Reset)?;
+
+        Ok(())
+    }
+
+    fn draw_food(&mut self, arena: &impl Arena) -> Result<()> {
+        // Draw the food
+        for (pos, food) in arena.food() {
+            // TODO change color based on score
+            let (color, text) = match food.kind {
+                FoodKind::Normal => (Color::Red, "**"),
+                FoodKind::Expiring => (Color::Yellow, "++"),
+                // closest to gold, set apart from the expiring food
+                FoodKind::Multiplier { .. } => (Color::DarkYellow, "$$"),
+            };
+            self.queue_background(arena, color)?;
+
+            let pos = arena.to_screen(pos.to_left_display().into());
+            self.out.queue(MoveTo(pos.x, pos.y))?;
+            if arena.config().no_color {
+                self.out.queue(Print(text))?;
+            } else {
+                self.out.queue(Print("  "))?;
+            }
+        }
+        self.queue_background(arena, Color::Reset)?;
+
+        Ok(())
+    }
+
+    fn draw_frame(&mut self, arena: &impl Arena) -> Result<()> {
+        // Draw the frame of the game
+        // Top line
+        self.queue_background(arena, Color::Reset)?;
+        let stdout = &mut self.out;
+        let pos = arena.to_screen(Position { x: 0, y: 0 });
+        stdout.queue(MoveTo(pos.x, pos.y))?;
+        stdout.queue(Print("╔"))?;
+        for _ in 1..arena.width() * 2 + 1 {
+            stdout.queue(Print("═"))?;
+        }
+        stdout.queue(Print("╗"))?;
+        // line break
+        // Middle lines
+        for i in 1..arena.height() + 1 {
+            let pos = arena.to_screen(Position { x: 0, y: i });
+            stdout.queue(MoveTo(pos.x, pos.y))?;
+            stdout.queue(Print("║"))?;
+            let pos = arena.to_screen(Position {
+                x: arena.width() * 2 + 1,
+                y: i,
+            });
+            stdout.queue(MoveTo(pos.x, pos.y))?;
+            stdout.queue(Print("║"))?;
+        }
+        // Bottom line
+        let pos = arena.to_screen(Position {
+            x: 0,
+            y: arena.height() + 1,
+        });
+        stdout.queue(MoveTo(pos.x, pos.y))?;
+        stdout.queue(Print("╚"))?;
+        for _ in 1..arena.width() * 2 + 1 {
+            stdout.queue(Print("═"))?;
+        }
+        stdout.queue(Print("╝"))?;
+
+        Ok(())
+    }
+
+    /// the help line at the bottom
+    fn draw_help(&mut self, arena: &impl Arena, help: &str) -> Result<()> {
+        let pos = arena.to_screen(Position {
+            x: 0,
+            y: arena.height() + EXTRA_ROWS - 1,
+        });
+        self.out.queue(MoveTo(pos.x, pos.y))?;
+        self.out.queue(Print(help))?;
+
+        Ok(())
+    }
+
+    /// the lines below the arena,
+    /// padded as the numbers might get shorter
+    fn draw_score_area(&mut self, game: &Game) -> Result<()> {
+        // blinking every half second while the multiplier lasts
+        let multiplier = match game.multiplier_remaining() {
+            Some(remaining) if remaining.as_millis() / 500 % 2 == 1 => {
+                format!("{}x SCORE!", game.score_multiplier())
+            }
+            _ => String::new(),
+        };
+        let lines = [
+            format!("Score: {:<5} {:<10}", game.score, multiplier),
+            format!("Max length: {:<5}", game.longest_body_length),
+            format!("Available: {:<5}", game.food_value_total()),
+        ];
+
+        for (i, line) in lines.into_iter().enumerate() {
+            let pos = game.to_screen(Position {
+                x: 0,
+                y: game.height + 2 + i as u16,
+            });
+            self.out.queue(MoveTo(pos.x, pos.y))?;
+            self.out.queue(Print(line))?;
+        }
+
+        Ok(())
+    }
+
+    /// draw a snake body in a color,
+    /// or with the text when rendered without color
+    fn draw_snake(
+        &mut self,
+        arena: &impl Arena,
+        body: &[Position],
+        color: Color,
+        text: &str,
+    ) -> Result<()> {
+        // Draw the snake
+        self.queue_background(arena, color)?;
+        for pos in body {
+            let pos = arena.to_screen(pos.to_left_display().into());
+            self.out.queue(MoveTo(pos.x, pos.y))?;
+            if arena.config().no_color {
+                self.out.queue(Print(text))?;
+            } else {
+                self.out.queue(Print("  "))?;
+            }
+        }
+        self.queue_background(arena, Color::Reset)?;
+
+        Ok(())
+    }
+
+    /// draw a two player game
+    pub fn draw_multiplayer(&mut self, game: &MultiplayerGame) -> Result<()> {
+        self.clear_screen(game)?;
+        self.draw_frame(game)?;
+
+        let scores = format!("P1: {:<5} P2: {:<5}", game.scores[0], game.scores[1]);
+        let pos = game.to_screen(Position {
+            x: 0,
+            y: game.height + 2,
+        });
+        self.out.queue(MoveTo(pos.x, pos.y))?;
+        self.out.queue(Print(scores))?;
+
+        self.draw_help(game, "P1: ←↑→↓ P2: wasd Quit: q, Esc")?;
+        self.draw_snake(game, &game.snakes[0].body, Color::Green, "##")?;
+        self.draw_snake(game, &game.snakes[1].body, Color::Blue, "@@")?;
+        self.draw_food(game)?;
+
+        self.out.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Arena for Game {
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        self.height
+    }
+
+    fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    fn food(&self) -> &FoodMap {
+        &self.food
+    }
+
+    fn cleared(&self) -> &[Position] {
+        &self.clear
+    }
+
+    fn offset(&self) -> (u16, u16) {
+        (self.offset_x, self.offset_y)
+    }
+}
+
+impl Arena for MultiplayerGame {
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        self.height
+    }
+
+    fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    fn food(&self) -> &FoodMap {
+        &self.food
+    }
+
+    fn cleared(&self) -> &[Position] {
+        &self.clear
+    }
+
+    fn offset(&self) -> (u16, u16) {
+        (self.offset_x, self.offset_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Direction;
+
+    /// a game with every kind of cell drawn:
+    /// snake, food, a portal, an obstacle and the heatmap
+    fn game(no_color: bool) -> Game {
+        let config = GameConfig {
+            seed: Some(0),
+            no_color,
+            heatmap: true,
+            portal_pairs: vec![((5, 2).into(), (15, 8).into())],
+            ..GameConfig::default()
+        };
+
+        Game::new(20, 10, config).with_obstacles(vec![(10, 9).into()])
+    }
+
+    /// the output of a first frame, then of a second one after a move
+    fn frames(no_color: bool) -> (String, String) {
+        let mut game = game(no_color);
+        let mut renderer = CrosstermRenderer::with_writer(Vec::new());
+        renderer.draw(&game).unwrap();
+        let first = renderer.writer().len();
+
+        game.apply_directions(&[Direction::Up]).unwrap();
+        renderer.draw(&game).unwrap();
+        let output = String::from_utf8(renderer.writer().clone()).unwrap();
+
+        (output[..first].to_string(), output[first..].to_string())
+    }
+
+    /// the terminal command moving the cursor to a screen position
+    fn move_to(x: u16, y: u16) -> String {
+        format!("\x1b[{};{}H", y + 1, x + 1)
+    }
+
+    #[test]
+    fn no_color_never_sets_the_background() {
+        let (first, second) = frames(true);
+
+        for frame in [&first, &second] {
+            assert!(!frame.contains("\x1b[48"));
+            assert!(!frame.contains("\x1b[49"));
+        }
+        assert!(first.contains("##"));
+        assert!(first.contains("**") || first.contains("++") || first.contains("$$"));
+        assert!(first.contains("()"));
+        assert!(first.contains("[]"));
+    }
+
+    #[test]
+    fn color_sets_the_background() {
+        let (first, _) = frames(false);
+
+        assert!(first.contains("\x1b[48"));
+        assert!(!first.contains("##"));
+    }
+
+    #[test]
+    fn no_color_clears_the_tail_with_a_space() {
+        let (_, second) = frames(true);
+
+        // the tail left (1, 5), drawn on the cells 1 and 2 of its row
+        assert!(second.contains(&format!("{} ", move_to(1, 5))));
+        assert!(second.contains(&format!("{} ", move_to(2, 5))));
+    }
+}
@@ -0,0 +1,94 @@
+use snake::{
+    config::{GameConfig, WallMode},
+    CollisionKind, Direction, Game, Position,
+};
+
+/// a seeded game without food, so the snake never grows,
+/// the head of a snake of the given length at (length, 5) going right
+fn game(init_length: u16, wall_mode: WallMode) -> Game {
+    let config = GameConfig {
+        seed: Some(0),
+        food_num: 0,
+        init_length,
+        wall_mode,
+        ..GameConfig::default()
+    };
+
+    Game::new(20, 10, config)
+}
+
+fn head(game: &Game) -> Position {
+    game.snake.body[0]
+}
+
+#[test]
+fn scripted_moves_end_where_expected() {
+    let mut game = game(3, WallMode::Wrap);
+    assert_eq!(head(&game), Position { x: 3, y: 5 });
+
+    let res = game.apply_directions(&[
+        Direction::Right,
+        Direction::Right,
+        Direction::Down,
+        Direction::Down,
+    ]);
+
+    assert_eq!(res, Ok(()));
+    assert_eq!(head(&game), Position { x: 5, y: 7 });
+    assert_eq!(game.snake.direction, Direction::Down);
+    assert_eq!(game.snake.body.len(), 3);
+}
+
+#[test]
+fn single_direction_moves_once() {
+    let mut game = game(3, WallMode::Wrap);
+
+    assert_eq!(game.apply_direction_single(Direction::Up), Ok(()));
+
+    assert_eq!(head(&game), Position { x: 3, y: 4 });
+    assert_eq!(game.snake.body[1], Position { x: 3, y: 5 });
+}
+
+#[test]
+fn reversal_keeps_going_forward() {
+    let mut game = game(3, WallMode::Wrap);
+
+    assert_eq!(game.apply_directions(&[Direction::Left]), Ok(()));
+
+    assert_eq!(game.snake.direction, Direction::Right);
+    assert_eq!(head(&game), Position { x: 4, y: 5 });
+}
+
+#[test]
+fn snake_wraps_through_the_wall() {
+    let mut game = game(3, WallMode::Wrap);
+
+    assert_eq!(game.apply_directions(&[Direction::Right; 18]), Ok(()));
+
+    assert_eq!(head(&game), Position { x: 1, y: 5 });
+}
+
+#[test]
+fn script_stops_at_the_wall_when_it_kills() {
+    let mut game = game(3, WallMode::Die);
+
+    let res = game.apply_directions(&[Direction::Up; 10]);
+
+    assert_eq!(res, Err(CollisionKind::WallDeath));
+    assert_eq!(head(&game).y, 0);
+}
+
+#[test]
+fn script_stops_when_the_snake_bites_itself() {
+    let mut game = game(5, WallMode::Wrap);
+
+    let res = game.apply_directions(&[
+        Direction::Down,
+        Direction::Left,
+        Direction::Up,
+        Direction::Right,
+    ]);
+
+    assert_eq!(res, Err(CollisionKind::SelfCollision));
+    assert_eq!(head(&game), Position { x: 4, y: 5 });
+}
@@ -1,25 +1,146 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use tui::layout::Rect;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 use tui::style::Color;
 
-#[derive(Debug, Clone, Copy)]
+use crate::game::verify_under;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Card {
     pub suit: Suit,
     pub rank: Rank,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A pile of cards to deal a game from,
+/// the last card is drawn first.
+#[derive(Debug, Clone, Default)]
+pub struct Deck(Vec<Card>);
+
+impl Deck {
+    /// the 52 cards of a standard deck
+    pub fn standard() -> Self {
+        Self::multi(1)
+    }
+
+    /// n standard decks put together
+    pub fn multi(n: u8) -> Self {
+        Self::of_suits(
+            &[Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades],
+            n as usize,
+        )
+    }
+
+    /// the 104 cards of a spider game
+    ///
+    /// the deck is made of 8 times 13 cards,
+    /// split evenly between the suits in play
+    pub fn spider(game_suit: GameSuitNumber) -> Self {
+        let suits = game_suit.card_suits_allowed();
+
+        Self::of_suits(&suits, 8 / suits.len())
+    }
+
+    /// every rank of the suits, the given times each
+    fn of_suits(suits: &[Suit], copies: usize) -> Self {
+        let mut cards = Vec::with_capacity(suits.len() * copies * 13);
+        for suit in suits {
+            for _ in 0..copies {
+                for i in 1..14 {
+                    cards.push(Card {
+                        suit: *suit,
+                        rank: Rank::try_from(i).unwrap(),
+                    });
+                }
+            }
+        }
+
+        Deck(cards)
+    }
+
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.0.shuffle(rng);
+    }
+
+    /// take the last card of the deck
+    pub fn draw(&mut self) -> Option<Card> {
+        self.0.pop()
+    }
+
+    /// the number of cards left in the deck
+    pub fn remaining(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A run of face up cards,
+/// the first card is the bottom one in the pile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hand(Vec<Card>);
+
+impl Hand {
+    /// the face up cards of a pile from the given index,
+    /// the face down cards at the start are skipped
+    pub fn from_pile_slice(pile: &[GameCard], from: usize) -> Self {
+        Hand(
+            pile.get(from..)
+                .unwrap_or_default()
+                .iter()
+                .skip_while(|card| !card.is_up)
+                .map(|card| card.card)
+                .collect(),
+        )
+    }
+
+    /// test if each card could go under the one before it
+    pub fn is_valid_sequence(&self, game_suit: GameSuitNumber) -> bool {
+        self.0
+            .windows(2)
+            .all(|pair| verify_under(game_suit, pair[0], pair[1]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// the last card of the run, on top of the pile
+    pub fn top(&self) -> Option<&Card> {
+        self.0.last()
+    }
+
+    /// the first card of the run, the deepest in the pile
+    pub fn bottom(&self) -> Option<&Card> {
+        self.0.first()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct GameCard {
     pub card: Card,
     pub is_up: bool,
-    /// should initialised at first render
-    ///
-    /// used to decide whether the card has been clicked
-    pub pos: Option<Rect>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl GameCard {
+    /// if the card can be picked up and moved,
+    /// only face up cards can, drawn or not
+    pub fn is_movable(&self) -> bool {
+        self.is_up
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Suit {
     Clubs,
     Diamonds,
@@ -27,7 +148,9 @@ pub enum Suit {
     Spades,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// serialised as its value, 1 for Ace to 13 for King
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
 pub enum Rank {
     Ace,
     Two,
@@ -44,23 +167,37 @@ pub enum Rank {
     King,
 }
 
-impl From<u8> for Rank {
-    fn from(value: u8) -> Self {
+/// The error converting a value outside `1..=13` to a rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidRank(pub u8);
+
+impl Display for InvalidRank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid rank {}, expected 1 to 13", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRank {}
+
+impl TryFrom<u8> for Rank {
+    type Error = InvalidRank;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            1 => Rank::Ace,
-            2 => Rank::Two,
-            3 => Rank::Three,
-            4 => Rank::Four,
-            5 => Rank::Five,
-            6 => Rank::Six,
-            7 => Rank::Seven,
-            8 => Rank::Eight,
-            9 => Rank::Nine,
-            10 => Rank::Ten,
-            11 => Rank::Jack,
-            12 => Rank::Queen,
-            13 => Rank::King,
-            _ => panic!("Invalid rank"),
+            1 => Ok(Rank::Ace),
+            2 => Ok(Rank::Two),
+            3 => Ok(Rank::Three),
+            4 => Ok(Rank::Four),
+            5 => Ok(Rank::Five),
+            6 => Ok(Rank::Six),
+            7 => Ok(Rank::Seven),
+            8 => Ok(Rank::Eight),
+            9 => Ok(Rank::Nine),
+            10 => Ok(Rank::Ten),
+            11 => Ok(Rank::Jack),
+            12 => Ok(Rank::Queen),
+            13 => Ok(Rank::King),
+            _ => Err(InvalidRank(value)),
         }
     }
 }
@@ -85,7 +222,7 @@ impl From<Rank> for u8 {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameSuitNumber {
     One,
     #[default]
@@ -93,6 +230,61 @@ pub enum GameSuitNumber {
     Four,
 }
 
+/// The error of parsing a game suit number other than 1, 2 or 4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidGameSuit(pub String);
+
+impl Display for InvalidGameSuit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid number of suits {}, expected 1, 2 or 4", self.0)
+    }
+}
+
+impl std::error::Error for InvalidGameSuit {}
+
+impl FromStr for GameSuitNumber {
+    type Err = InvalidGameSuit;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(GameSuitNumber::One),
+            "2" => Ok(GameSuitNumber::Two),
+            "4" => Ok(GameSuitNumber::Four),
+            _ => Err(InvalidGameSuit(s.to_string())),
+        }
+    }
+}
+
+impl GameSuitNumber {
+    /// the suits of the cards dealt in this mode
+    pub fn card_suits_allowed(&self) -> Vec<Suit> {
+        match self {
+            GameSuitNumber::One => vec![Suit::Spades],
+            GameSuitNumber::Two => vec![Suit::Spades, Suit::Hearts],
+            GameSuitNumber::Four => vec![Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades],
+        }
+    }
+
+    /// test if a fresh deal is made of the right cards for this mode
+    ///
+    /// the 104 cards are split evenly between the allowed suits,
+    /// with each rank appearing the same number of times
+    pub fn verify_deal_is_valid(&self, tableau: &[Vec<GameCard>], stock: &[GameCard]) -> bool {
+        let suits = self.card_suits_allowed();
+        let copies = 8 / suits.len();
+
+        let mut counts = std::collections::HashMap::new();
+        for card in tableau.iter().flatten().chain(stock) {
+            *counts.entry(card.card).or_insert(0) += 1;
+        }
+
+        counts.len() == suits.len() * 13
+            && counts
+                .iter()
+                .all(|(card, &n)| suits.contains(&card.suit) && n == copies)
+    }
+}
+
 impl Display for GameSuitNumber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -123,14 +315,23 @@ impl Display for Rank {
     }
 }
 
+/// the suits are displayed as C, D, H and S
+static ASCII_SUITS: AtomicBool = AtomicBool::new(false);
+
+/// display the suits as letters rather than symbols,
+/// for the terminals or fonts without the suit glyphs
+pub fn set_ascii_suits(ascii: bool) {
+    ASCII_SUITS.store(ascii, Ordering::Relaxed);
+}
+
+/// test if the suits are displayed as letters
+pub fn ascii_suits() -> bool {
+    ASCII_SUITS.load(Ordering::Relaxed)
+}
+
 impl Display for Suit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Suit::Clubs => write!(f, "♣"),
-            Suit::Diamonds => write!(f, "♦"),
-            Suit::Hearts => write!(f, "♥"),
-            Suit::Spades => write!(f, "♠"),
-        }
+        write!(f, "{}", self.symbol(ascii_suits()))
     }
 }
 
@@ -140,13 +341,145 @@ impl Display for Card {
     }
 }
 
+/// the rank characters of the card notation, Ace first
+const NOTATION_RANKS: &str = "A23456789TJQK";
+
+/// parsed from the notation character, case insensitive
+impl TryFrom<char> for Suit {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c.to_ascii_uppercase() {
+            'C' => Ok(Suit::Clubs),
+            'D' => Ok(Suit::Diamonds),
+            'H' => Ok(Suit::Hearts),
+            'S' => Ok(Suit::Spades),
+            _ => Err(c),
+        }
+    }
+}
+
+/// parsed from the notation character, case insensitive
+impl TryFrom<char> for Rank {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        let rank = NOTATION_RANKS.find(c.to_ascii_uppercase()).ok_or(c)?;
+
+        Rank::try_from(rank as u8 + 1).map_err(|_| c)
+    }
+}
+
+impl Card {
+    /// the two characters notation of the card, rank then suit
+    ///
+    /// e.g. `AS` for the Ace of Spades, `TH` for the Ten of Hearts
+    pub fn to_notation(&self) -> String {
+        format!("{}{}", self.rank.as_char(), self.suit.as_char())
+    }
+
+    /// the rank and suit without a space, padded to 4 characters
+    ///
+    /// e.g. `A♣  ` or `10♥ `, so the card titles line up
+    pub fn display_compact(&self) -> String {
+        let card = format!("{}{}", self.rank.to_string().trim_end(), self.suit);
+
+        format!("{:<4}", card)
+    }
+
+    /// the rank notation and the suit, 2 characters
+    ///
+    /// e.g. `T♥` for the Ten of Hearts, for the narrow card blocks
+    pub fn display_short(&self) -> String {
+        format!("{}{}", self.rank.as_char(), self.suit)
+    }
+
+    /// the card in words, e.g. `Ace of Clubs`
+    pub fn display_long(&self) -> String {
+        format!("{} of {}", self.rank.name(), self.suit.name())
+    }
+
+    /// parse the two characters notation of a card, case insensitive
+    pub fn from_notation(s: &str) -> Option<Card> {
+        let mut chars = s.chars();
+        let (rank, suit) = (chars.next()?, chars.next()?);
+        if chars.next().is_some() {
+            return None;
+        }
+
+        Some(Card {
+            suit: Suit::try_from(suit).ok()?,
+            rank: Rank::try_from(rank).ok()?,
+        })
+    }
+}
+
+impl Rank {
+    /// the notation character, e.g. `T` for Ten
+    pub fn as_char(&self) -> char {
+        let rank: u8 = (*self).into();
+
+        NOTATION_RANKS.as_bytes()[rank as usize - 1] as char
+    }
+
+    /// the rank in words
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rank::Ace => "Ace",
+            Rank::Two => "Two",
+            Rank::Three => "Three",
+            Rank::Four => "Four",
+            Rank::Five => "Five",
+            Rank::Six => "Six",
+            Rank::Seven => "Seven",
+            Rank::Eight => "Eight",
+            Rank::Nine => "Nine",
+            Rank::Ten => "Ten",
+            Rank::Jack => "Jack",
+            Rank::Queen => "Queen",
+            Rank::King => "King",
+        }
+    }
+}
+
 impl Suit {
+    /// the notation character, e.g. `S` for Spades
+    pub fn as_char(&self) -> char {
+        self.symbol(true)
+    }
+
+    /// the suit glyph, or its letter in ascii
+    pub fn symbol(&self, ascii: bool) -> char {
+        match (self, ascii) {
+            (Suit::Clubs, false) => '♣',
+            (Suit::Diamonds, false) => '♦',
+            (Suit::Hearts, false) => '♥',
+            (Suit::Spades, false) => '♠',
+            (Suit::Clubs, true) => 'C',
+            (Suit::Diamonds, true) => 'D',
+            (Suit::Hearts, true) => 'H',
+            (Suit::Spades, true) => 'S',
+        }
+    }
+
+    /// the suit in words
+    pub fn name(&self) -> &'static str {
+        match self {
+            Suit::Clubs => "Clubs",
+            Suit::Diamonds => "Diamonds",
+            Suit::Hearts => "Hearts",
+            Suit::Spades => "Spades",
+        }
+    }
+
+    /// the default color of the suit,
+    /// readable on both dark and light terminals
     pub fn color(&self) -> Color {
         match self {
-            Suit::Clubs => Color::Green,
-            Suit::Diamonds => Color::Red,
-            Suit::Hearts => Color::Red,
-            Suit::Spades => Color::Green,
+            Suit::Clubs => Color::Gray,
+            Suit::Diamonds => Color::LightRed,
+            Suit::Hearts => Color::LightRed,
+            Suit::Spades => Color::Gray,
         }
     }
 }
@@ -155,3 +488,472 @@ impl Suit {
 pub struct GameSuitNumberPrompt {
     pub current_select: GameSuitNumber,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Mutex,
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// the number of copies of each card in a deck
+    fn card_counts(deck: &Deck) -> HashMap<Card, usize> {
+        let mut counts = HashMap::new();
+        for card in &deck.0 {
+            *counts.entry(*card).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn one_suit_deck_is_eight_times_spades() {
+        let counts = card_counts(&Deck::spider(GameSuitNumber::One));
+
+        assert_eq!(counts.len(), 13);
+        assert!(counts
+            .iter()
+            .all(|(card, &n)| card.suit == Suit::Spades && n == 8));
+    }
+
+    #[test]
+    fn two_suit_deck_is_four_times_spades_and_hearts() {
+        let deck = Deck::spider(GameSuitNumber::Two);
+        let counts = card_counts(&deck);
+
+        assert_eq!(deck.remaining(), 104);
+        assert_eq!(counts.len(), 26);
+        assert!(counts
+            .iter()
+            .all(|(card, &n)| { matches!(card.suit, Suit::Spades | Suit::Hearts) && n == 4 }));
+    }
+
+    #[test]
+    fn four_suit_deck_is_two_standard_decks() {
+        let deck = Deck::spider(GameSuitNumber::Four);
+
+        assert_eq!(deck.remaining(), 104);
+        assert_eq!(card_counts(&deck), card_counts(&Deck::multi(2)));
+        assert!(card_counts(&deck).values().all(|&n| n == 2));
+    }
+
+    #[test]
+    fn standard_deck_has_each_card_once() {
+        let deck = Deck::standard();
+        let counts = card_counts(&deck);
+
+        assert_eq!(deck.remaining(), 52);
+        assert_eq!(counts.len(), 52);
+        assert!(counts.values().all(|&n| n == 1));
+        for suit in [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades] {
+            assert_eq!(counts.keys().filter(|card| card.suit == suit).count(), 13);
+        }
+    }
+
+    #[test]
+    fn multi_deck_has_each_card_n_times() {
+        let deck = Deck::multi(3);
+
+        assert_eq!(deck.remaining(), 156);
+        assert_eq!(card_counts(&deck).len(), 52);
+        assert!(card_counts(&deck).values().all(|&n| n == 3));
+        assert!(Deck::multi(0).is_empty());
+    }
+
+    #[test]
+    fn shuffle_keeps_the_cards() {
+        let mut deck = Deck::multi(2);
+        let before = deck.0.clone();
+
+        deck.shuffle(&mut StdRng::seed_from_u64(1));
+
+        assert_ne!(deck.0, before);
+        assert_eq!(card_counts(&deck), card_counts(&Deck::multi(2)));
+    }
+
+    #[test]
+    fn shuffle_follows_the_seed() {
+        let shuffled = |seed| {
+            let mut deck = Deck::standard();
+            deck.shuffle(&mut StdRng::seed_from_u64(seed));
+            deck.0
+        };
+
+        assert_eq!(shuffled(5), shuffled(5));
+        assert_ne!(shuffled(5), shuffled(6));
+    }
+
+    #[test]
+    fn draw_takes_the_last_card_until_empty() {
+        let mut deck = Deck::standard();
+        let last = *deck.0.last().unwrap();
+
+        assert_eq!(deck.draw(), Some(last));
+        assert_eq!(deck.remaining(), 51);
+        while deck.draw().is_some() {}
+        assert!(deck.is_empty());
+        assert_eq!(deck.draw(), None);
+    }
+
+    fn round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    const SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    #[test]
+    fn every_card_round_trips() {
+        for suit in SUITS {
+            assert_eq!(round_trip(&suit), suit);
+            for value in 1..=13 {
+                let rank = Rank::try_from(value).unwrap();
+                let card = Card { suit, rank };
+
+                assert_eq!(round_trip(&rank), rank);
+                assert_eq!(round_trip(&card), card);
+            }
+        }
+    }
+
+    #[test]
+    fn rank_is_written_as_its_value() {
+        assert_eq!(serde_json::to_string(&Rank::Ace).unwrap(), "1");
+        assert_eq!(serde_json::to_string(&Rank::King).unwrap(), "13");
+        assert!(serde_json::from_str::<Rank>("0").is_err());
+        assert!(serde_json::from_str::<Rank>("14").is_err());
+    }
+
+    #[test]
+    fn game_card_round_trips_face_up_and_down() {
+        let card = Card::from_notation("QH").unwrap();
+        for is_up in [false, true] {
+            let game_card = round_trip(&GameCard { card, is_up });
+
+            assert_eq!(game_card.card, card);
+            assert_eq!(game_card.is_up, is_up);
+        }
+    }
+
+    #[test]
+    fn game_suits_round_trip() {
+        for game_suit in GAME_SUITS {
+            assert_eq!(round_trip(&game_suit), game_suit);
+        }
+    }
+
+    /// a random pile, mostly face up runs going down so valid runs show up
+    fn random_pile(rng: &mut StdRng) -> Vec<GameCard> {
+        let len = rng.gen_range(0..16);
+        let face_down = rng.gen_range(0..=len);
+        let mut rank: u8 = rng.gen_range(1..=13);
+        let mut suit = SUITS[rng.gen_range(0..4)];
+
+        (0..len)
+            .map(|i| {
+                if rng.gen_bool(0.2) {
+                    suit = SUITS[rng.gen_range(0..4)];
+                }
+                rank = if rank > 1 && rng.gen_bool(0.8) {
+                    rank - 1
+                } else {
+                    rng.gen_range(1..=13)
+                };
+                GameCard {
+                    card: Card {
+                        suit,
+                        rank: Rank::try_from(rank).unwrap(),
+                    },
+                    is_up: i >= face_down || rng.gen_bool(0.1),
+                }
+            })
+            .collect()
+    }
+
+    /// the cards of a pile from an index, from the first face up one on
+    fn reference_hand(pile: &[GameCard], from: usize) -> Vec<Card> {
+        let mut cards = Vec::new();
+        let mut seen_up = false;
+        for card in pile.iter().skip(from) {
+            seen_up |= card.is_up;
+            if seen_up {
+                cards.push(card.card);
+            }
+        }
+
+        cards
+    }
+
+    /// test if every card is one rank below the card before it,
+    /// of the same suit unless playing one suit
+    fn reference_is_valid(cards: &[Card], game_suit: GameSuitNumber) -> bool {
+        for i in 1..cards.len() {
+            let (up, down) = (cards[i - 1], cards[i]);
+            if u8::from(up.rank) != u8::from(down.rank) + 1 {
+                return false;
+            }
+            if game_suit != GameSuitNumber::One && up.suit != down.suit {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    #[test]
+    fn hand_matches_the_reference_on_random_piles() {
+        let mut rng = StdRng::seed_from_u64(356);
+        let mut valid_runs = 0;
+        for _ in 0..2000 {
+            let pile = random_pile(&mut rng);
+            let from = rng.gen_range(0..=pile.len() + 1);
+
+            let hand = Hand::from_pile_slice(&pile, from);
+            let reference = reference_hand(&pile, from);
+
+            assert_eq!(hand, Hand(reference.clone()), "{:?} from {}", pile, from);
+            assert_eq!(hand.len(), reference.len());
+            assert_eq!(hand.is_empty(), reference.is_empty());
+            assert_eq!(hand.top(), reference.last());
+            assert_eq!(hand.bottom(), reference.first());
+            for game_suit in GAME_SUITS {
+                let valid = reference_is_valid(&reference, game_suit);
+                assert_eq!(hand.is_valid_sequence(game_suit), valid, "{:?}", reference);
+                valid_runs += (valid && reference.len() > 2) as usize;
+            }
+        }
+
+        // the random piles do try some valid runs
+        assert!(valid_runs > 100);
+    }
+
+    /// the 52 distinct cards
+    fn all_cards() -> Vec<Card> {
+        Deck::standard().0
+    }
+
+    #[test]
+    fn every_card_round_trips_through_the_notation() {
+        for card in all_cards() {
+            let notation = card.to_notation();
+
+            assert_eq!(notation.len(), 2);
+            assert_eq!(Card::from_notation(&notation), Some(card));
+            assert_eq!(Card::from_notation(&notation.to_lowercase()), Some(card));
+        }
+    }
+
+    /// the suit display mode is global, so the tests setting it take turns
+    static ASCII_LOCK: Mutex<()> = Mutex::new(());
+
+    /// run with the suits displayed as letters or symbols,
+    /// putting the default symbols back after
+    fn with_ascii_suits<T>(ascii: bool, f: impl FnOnce() -> T) -> T {
+        let _lock = ASCII_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        set_ascii_suits(ascii);
+        let result = f();
+        set_ascii_suits(false);
+
+        result
+    }
+
+    #[test]
+    fn every_compact_name_is_four_characters() {
+        for ascii in [false, true] {
+            with_ascii_suits(ascii, || {
+                for card in all_cards() {
+                    let name = card.display_compact();
+
+                    assert_eq!(name.chars().count(), 4, "{:?}", name);
+                    assert!(name.starts_with(card.rank.to_string().trim_end()));
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn compact_name_is_padded_after_the_suit() {
+        let ace = Card::from_notation("AC").unwrap();
+        let ten = Card::from_notation("TH").unwrap();
+
+        with_ascii_suits(false, || {
+            assert_eq!(ace.display_compact(), "A♣  ");
+            assert_eq!(ten.display_compact(), "10♥ ");
+        });
+        with_ascii_suits(true, || {
+            assert_eq!(ace.display_compact(), "AC  ");
+            assert_eq!(ten.display_compact(), "10H ");
+        });
+    }
+
+    #[test]
+    fn every_card_shows_its_suit_symbol_or_letter() {
+        let symbols: HashMap<Suit, (char, char)> = HashMap::from([
+            (Suit::Clubs, ('♣', 'C')),
+            (Suit::Diamonds, ('♦', 'D')),
+            (Suit::Hearts, ('♥', 'H')),
+            (Suit::Spades, ('♠', 'S')),
+        ]);
+
+        for ascii in [false, true] {
+            with_ascii_suits(ascii, || {
+                for card in all_cards() {
+                    let (glyph, letter) = symbols[&card.suit];
+                    let suit = if ascii { letter } else { glyph };
+                    let rank = card.rank.to_string();
+
+                    assert_eq!(card.suit.symbol(ascii), suit);
+                    assert_eq!(card.suit.to_string(), suit.to_string());
+                    assert_eq!(card.to_string(), format!("{} {}", rank, suit));
+                    assert_eq!(
+                        card.display_short(),
+                        format!("{}{}", card.rank.as_char(), suit)
+                    );
+                    assert_eq!(card.display_short().chars().count(), 2);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn all_cards_look_different_in_both_modes() {
+        for ascii in [false, true] {
+            with_ascii_suits(ascii, || {
+                let names: HashSet<String> =
+                    all_cards().iter().map(Card::display_compact).collect();
+                let short: HashSet<String> = all_cards().iter().map(Card::display_short).collect();
+
+                assert_eq!(names.len(), 52);
+                assert_eq!(short.len(), 52);
+            });
+        }
+    }
+
+    #[test]
+    fn only_face_up_cards_are_movable() {
+        for card in all_cards() {
+            assert!(GameCard { card, is_up: true }.is_movable());
+            assert!(!GameCard { card, is_up: false }.is_movable());
+        }
+    }
+
+    #[test]
+    fn every_suit_char_converts_both_ways() {
+        for (c, suit) in "CDHS".chars().zip(SUITS) {
+            assert_eq!(Suit::try_from(c), Ok(suit));
+            assert_eq!(Suit::try_from(c.to_ascii_lowercase()), Ok(suit));
+            assert_eq!(suit.as_char(), c);
+        }
+    }
+
+    #[test]
+    fn every_rank_char_converts_both_ways() {
+        for (value, c) in (1..=13).zip("A23456789TJQK".chars()) {
+            let rank = Rank::try_from(value).unwrap();
+
+            assert_eq!(Rank::try_from(c), Ok(rank));
+            assert_eq!(Rank::try_from(c.to_ascii_lowercase()), Ok(rank));
+            assert_eq!(rank.as_char(), c);
+        }
+    }
+
+    #[test]
+    fn invalid_char_is_given_back() {
+        for c in ['X', 'z', '0', '1', ' ', '♠'] {
+            assert_eq!(Suit::try_from(c), Err(c));
+        }
+        for c in ['X', 'S', '0', '1', ' ', '♠'] {
+            assert_eq!(Rank::try_from(c), Err(c));
+        }
+    }
+
+    #[test]
+    fn invalid_notation_is_none() {
+        for notation in [
+            "", "A", "ASS", "1S", "0H", "10H", "AX", "SA", "ZZ", " AS", "A S",
+        ] {
+            assert_eq!(Card::from_notation(notation), None, "{:?}", notation);
+        }
+    }
+
+    const GAME_SUITS: [GameSuitNumber; 3] = [
+        GameSuitNumber::One,
+        GameSuitNumber::Two,
+        GameSuitNumber::Four,
+    ];
+
+    /// the deck of the mode as the tableau and the stock of a fresh deal
+    fn deal(game_suit: GameSuitNumber) -> (Vec<Vec<GameCard>>, Vec<GameCard>) {
+        let mut cards: Vec<GameCard> = Deck::spider(game_suit)
+            .0
+            .into_iter()
+            .map(|card| GameCard { card, is_up: false })
+            .collect();
+        let stock = cards.split_off(54);
+
+        (vec![cards], stock)
+    }
+
+    #[test]
+    fn suits_allowed_per_mode() {
+        assert_eq!(GameSuitNumber::One.card_suits_allowed(), vec![Suit::Spades]);
+        assert_eq!(
+            GameSuitNumber::Two.card_suits_allowed(),
+            vec![Suit::Spades, Suit::Hearts]
+        );
+        assert_eq!(
+            GameSuitNumber::Four.card_suits_allowed(),
+            vec![Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades]
+        );
+    }
+
+    #[test]
+    fn deck_of_each_mode_is_a_valid_deal() {
+        for game_suit in GAME_SUITS {
+            let (tableau, stock) = deal(game_suit);
+
+            assert!(game_suit.verify_deal_is_valid(&tableau, &stock));
+        }
+    }
+
+    #[test]
+    fn deal_of_another_mode_is_not_valid() {
+        for game_suit in GAME_SUITS {
+            for other in GAME_SUITS.into_iter().filter(|other| *other != game_suit) {
+                let (tableau, stock) = deal(other);
+
+                assert!(!game_suit.verify_deal_is_valid(&tableau, &stock));
+            }
+        }
+    }
+
+    #[test]
+    fn deal_with_a_card_missing_or_swapped_is_not_valid() {
+        for game_suit in GAME_SUITS {
+            let (tableau, mut stock) = deal(game_suit);
+            let card = stock.pop().unwrap();
+            assert!(!game_suit.verify_deal_is_valid(&tableau, &stock));
+
+            // a card of the mode, but one copy too many
+            stock.push(stock[0]);
+            assert!(!game_suit.verify_deal_is_valid(&tableau, &stock));
+
+            stock.pop();
+            stock.push(card);
+            assert!(game_suit.verify_deal_is_valid(&tableau, &stock));
+        }
+    }
+
+    #[test]
+    fn clubs_are_not_dealt_in_one_or_two_suits() {
+        for game_suit in [GameSuitNumber::One, GameSuitNumber::Two] {
+            let (tableau, mut stock) = deal(game_suit);
+            stock[0].card.suit = Suit::Clubs;
+
+            assert!(!game_suit.verify_deal_is_valid(&tableau, &stock));
+        }
+    }
+}
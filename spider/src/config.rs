@@ -0,0 +1,782 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use tui::style::Color;
+
+use crate::card::{GameSuitNumber, Suit};
+
+/// the command line usage, printed by `--help`
+const USAGE: &str = "Usage: spider [options]
+
+Options:
+  -s, --suits 1|2|4       the number of suits, skipping the prompt
+  --keys arrow|vim|both   the keys moving the card cursor, defaults to both
+  --relaxed-deal          allow dealing while a tableau pile is empty
+  --seed <number>         the seed of the first deal, to play it again
+  --winnable              only deal games with a known winning line
+  --time-limit <seconds>  lose the game when the time is up
+  --max-undos <number>    the most undos in a game, unlimited by default
+  --daily                 play the deal of the day, the same for everyone
+  --stats                 print the statistics of the games played
+  --solve                 print a deal and search a win without the ui
+  --no-animation          skip the cascading cards on a win
+  --ascii                 draw the suits as C, D, H, S and no block characters
+  --card-back hatched|plain
+                          how the face down cards are drawn, defaults to hatched
+  --write-config          write the current options to ~/.rgames/spider.toml
+  --verify-history        replay the saved game and check its history
+  --replay <file>         step through a replay exported with e
+  -h, --help              print this help";
+
+/// The configuration of a spider game.
+#[derive(Debug, Clone, Default)]
+pub struct GameConfig {
+    /// which keys move the keyboard cursor
+    pub key_binding_mode: KeyBindingMode,
+    /// allow dealing from the stock while a tableau pile is empty,
+    /// for casual play
+    pub relaxed_deal: bool,
+    /// search a winning sequence of moves and print it,
+    /// without starting the ui
+    pub solve: bool,
+    /// the number of suits given on the command line,
+    /// the player is asked when not set
+    pub game_suit: Option<GameSuitNumber>,
+    /// the seed of the first deal,
+    /// random when not set
+    pub seed: Option<u64>,
+    /// play the deal of the day,
+    /// the same for every player with the same suits
+    pub daily: bool,
+    /// deal games made backwards from a won one,
+    /// the same seed giving the same deal
+    pub winnable: bool,
+    /// print the statistics and exit
+    pub stats: bool,
+    /// the most undos in a game,
+    /// unlimited when not set
+    pub undo_limit: Option<u32>,
+    /// the points lost for each undo,
+    /// `DEFAULT_UNDO_PENALTY` when not set
+    pub undo_penalty: Option<u32>,
+    /// the time to win a game in,
+    /// untimed when not set
+    pub time_limit: Option<Duration>,
+    /// write the config to the config file and exit
+    pub write_config: bool,
+    /// skip the cascading cards shown on a win
+    pub no_animation: bool,
+    /// draw the suits as letters and no block characters,
+    /// for the terminals or fonts without them
+    pub ascii: bool,
+    /// how the face down cards are drawn
+    pub card_back: CardBack,
+    /// the colors of the cards,
+    /// before the colors set one by one
+    pub theme: Theme,
+    /// the color of the hatched card backs,
+    /// the theme one when not set
+    pub card_back_color: Option<Color>,
+    /// the color of clubs and spades,
+    /// the theme one when not set
+    pub black_suit_color: Option<Color>,
+    /// the color of diamonds and hearts,
+    /// the theme one when not set
+    pub red_suit_color: Option<Color>,
+    /// replay the history of the saved game and exit
+    pub verify_history: bool,
+    /// the replay file to step through instead of playing
+    pub replay: Option<PathBuf>,
+}
+
+/// The command line arguments, overriding the config file.
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    pub key_binding_mode: Option<KeyBindingMode>,
+    pub relaxed_deal: bool,
+    pub solve: bool,
+    pub game_suit: Option<GameSuitNumber>,
+    pub seed: Option<u64>,
+    /// in seconds
+    pub time_limit: Option<u64>,
+    pub undo_limit: Option<u32>,
+    pub daily: bool,
+    pub winnable: bool,
+    pub stats: bool,
+    pub write_config: bool,
+    pub no_animation: bool,
+    pub ascii: bool,
+    pub card_back: Option<CardBack>,
+    pub verify_history: bool,
+    pub replay: Option<PathBuf>,
+}
+
+/// The content of the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    game: GameSection,
+    limits: LimitsSection,
+    display: DisplaySection,
+}
+
+/// The `[game]` section of the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct GameSection {
+    /// "one", "two" or "four"
+    suit: Option<String>,
+    seed: Option<u64>,
+}
+
+/// The `[limits]` section of the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LimitsSection {
+    undo_limit: Option<u32>,
+    undo_penalty: Option<u32>,
+    /// in seconds
+    time_limit: Option<u64>,
+}
+
+/// The `[display]` section of the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct DisplaySection {
+    /// cascade the cards on a win
+    animation: Option<bool>,
+    /// suits as letters and no block characters
+    ascii: Option<bool>,
+    /// "hatched" or "plain"
+    card_back: Option<String>,
+    /// one of the `Theme` names
+    theme: Option<String>,
+    /// one of the `COLOR_NAMES`
+    card_back_color: Option<String>,
+    /// the color of clubs and spades, one of the `COLOR_NAMES`
+    black_suit_color: Option<String>,
+    /// the color of diamonds and hearts, one of the `COLOR_NAMES`
+    red_suit_color: Option<String>,
+}
+
+/// How the back of the face down cards is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CardBack {
+    /// filled with a hatch pattern
+    #[default]
+    Hatched,
+    /// left empty, for the terminals without the block characters
+    Plain,
+}
+
+impl CardBack {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hatched" => Some(CardBack::Hatched),
+            "plain" => Some(CardBack::Plain),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CardBack::Hatched => "hatched",
+            CardBack::Plain => "plain",
+        }
+    }
+}
+
+/// The colors the cards are drawn in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// gray and light red suits, readable on dark terminals
+    #[default]
+    Default,
+    /// black and red suits, readable on light terminals
+    Light,
+    /// everything in the terminal colors
+    Mono,
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::Default),
+            "light" => Some(Theme::Light),
+            "mono" => Some(Theme::Mono),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::Light => "light",
+            Theme::Mono => "mono",
+        }
+    }
+
+    /// the color a suit is drawn in
+    pub fn suit_color(&self, suit: Suit) -> Color {
+        match (self, suit) {
+            (Theme::Default, _) => suit.color(),
+            (Theme::Light, Suit::Clubs | Suit::Spades) => Color::Black,
+            (Theme::Light, Suit::Diamonds | Suit::Hearts) => Color::Red,
+            (Theme::Mono, _) => Color::Reset,
+        }
+    }
+
+    /// the color of the hatched card backs
+    pub fn card_back_color(&self) -> Color {
+        match self {
+            Theme::Default | Theme::Light => Color::Blue,
+            Theme::Mono => Color::Reset,
+        }
+    }
+}
+
+/// the colors that could be set in the config file, by name
+const COLOR_NAMES: [(&str, Color); 12] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("lightred", Color::LightRed),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("white", Color::White),
+    ("reset", Color::Reset),
+];
+
+/// the color of a name in the config file,
+/// none with a warning if it is unknown
+fn color_from_name(key: &str, name: &str) -> Option<Color> {
+    let color = COLOR_NAMES
+        .iter()
+        .find(|(color_name, _)| *color_name == name)
+        .map(|(_, color)| *color);
+    if color.is_none() {
+        eprintln!("warning: ignoring the {} {} in the config file", key, name);
+    }
+
+    color
+}
+
+/// the name of a color for the config file
+fn color_name(color: Color) -> Option<String> {
+    COLOR_NAMES
+        .iter()
+        .find(|(_, c)| *c == color)
+        .map(|(name, _)| name.to_string())
+}
+
+/// The keys used to move the keyboard cursor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyBindingMode {
+    /// arrow keys only
+    Arrow,
+    /// h, j, k, l only
+    Vim,
+    /// both arrow keys and h, j, k, l
+    #[default]
+    Both,
+}
+
+/// The way the keyboard cursor could move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMove {
+    /// to the pile on the left
+    Left,
+    /// to the pile on the right
+    Right,
+    /// to the card below in the pile
+    Up,
+    /// to the card above in the pile
+    Down,
+}
+
+impl KeyBindingMode {
+    /// get the cursor move a key stands for in this mode
+    ///
+    /// none if the key does not move the cursor
+    pub fn cursor_move_from_keycode(&self, code: KeyCode) -> Option<CursorMove> {
+        let arrow = match code {
+            KeyCode::Left => Some(CursorMove::Left),
+            KeyCode::Right => Some(CursorMove::Right),
+            KeyCode::Up => Some(CursorMove::Up),
+            KeyCode::Down => Some(CursorMove::Down),
+            _ => None,
+        };
+        let vim = match code {
+            KeyCode::Char('h') => Some(CursorMove::Left),
+            KeyCode::Char('l') => Some(CursorMove::Right),
+            KeyCode::Char('k') => Some(CursorMove::Up),
+            KeyCode::Char('j') => Some(CursorMove::Down),
+            _ => None,
+        };
+
+        match self {
+            KeyBindingMode::Arrow => arrow,
+            KeyBindingMode::Vim => vim,
+            KeyBindingMode::Both => arrow.or(vim),
+        }
+    }
+}
+
+/// the points lost for each undo by default
+pub const DEFAULT_UNDO_PENALTY: u32 = 1;
+
+impl GameConfig {
+    /// the points lost for each undo
+    pub fn undo_penalty(&self) -> u32 {
+        self.undo_penalty.unwrap_or(DEFAULT_UNDO_PENALTY)
+    }
+
+    /// the color a suit is drawn in
+    pub fn suit_color(&self, suit: Suit) -> Color {
+        let color = match suit {
+            Suit::Clubs | Suit::Spades => self.black_suit_color,
+            Suit::Diamonds | Suit::Hearts => self.red_suit_color,
+        };
+
+        color.unwrap_or_else(|| self.theme.suit_color(suit))
+    }
+
+    /// the color of the hatched card backs
+    pub fn back_color(&self) -> Color {
+        self.card_back_color
+            .unwrap_or_else(|| self.theme.card_back_color())
+    }
+}
+
+/// a time limit of no seconds would lose the game at once
+fn is_valid_time_limit(secs: u64) -> bool {
+    secs > 0
+}
+
+impl CliArgs {
+    /// parse the command line arguments
+    ///
+    /// an invalid number of suits or seed exits with an error,
+    /// `--help` prints the usage and exits
+    pub fn parse() -> Self {
+        let mut cli = CliArgs::default();
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--keys" => match args.next().as_deref() {
+                    Some("arrow") => cli.key_binding_mode = Some(KeyBindingMode::Arrow),
+                    Some("vim") => cli.key_binding_mode = Some(KeyBindingMode::Vim),
+                    Some("both") => cli.key_binding_mode = Some(KeyBindingMode::Both),
+                    _ => {}
+                },
+                "--relaxed-deal" => cli.relaxed_deal = true,
+                "--solve" => cli.solve = true,
+                "--suits" | "-s" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse() {
+                        Ok(game_suit) => cli.game_suit = Some(game_suit),
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--seed" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse() {
+                        Ok(seed) => cli.seed = Some(seed),
+                        Err(_) => {
+                            eprintln!("invalid seed {}, expected a number", value);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--time-limit" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse() {
+                        Ok(secs) if is_valid_time_limit(secs) => cli.time_limit = Some(secs),
+                        _ => {
+                            eprintln!("invalid time limit {}, expected a number of seconds", value);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--max-undos" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse() {
+                        Ok(undo_limit) => cli.undo_limit = Some(undo_limit),
+                        Err(_) => {
+                            eprintln!("invalid undo limit {}, expected a number", value);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--daily" => cli.daily = true,
+                "--winnable" => cli.winnable = true,
+                "--stats" => cli.stats = true,
+                "--write-config" => cli.write_config = true,
+                "--no-animation" => cli.no_animation = true,
+                "--ascii" => cli.ascii = true,
+                "--card-back" => {
+                    let value = args.next().unwrap_or_default();
+                    match CardBack::from_name(&value) {
+                        Some(card_back) => cli.card_back = Some(card_back),
+                        None => {
+                            eprintln!("invalid card back {}, expected hatched or plain", value);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                "--verify-history" => cli.verify_history = true,
+                "--replay" => match args.next() {
+                    Some(path) => cli.replay = Some(PathBuf::from(path)),
+                    None => {
+                        eprintln!("missing replay file");
+                        std::process::exit(1);
+                    }
+                },
+                "--help" | "-h" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => {}
+            }
+        }
+
+        cli
+    }
+}
+
+/// the game suit written in the config file
+fn game_suit_from_name(name: &str) -> Option<GameSuitNumber> {
+    match name {
+        "one" => Some(GameSuitNumber::One),
+        "two" => Some(GameSuitNumber::Two),
+        "four" => Some(GameSuitNumber::Four),
+        _ => None,
+    }
+}
+
+impl GameConfig {
+    /// build the config from the config file
+    /// and the command line arguments, which take precedence
+    pub fn from_args() -> Self {
+        GameConfig::from_file_or_default().merged_with_cli(&CliArgs::parse())
+    }
+
+    /// `~/.rgames/spider.toml`,
+    /// none if the home directory is unknown
+    pub fn config_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+
+        Some(Path::new(&home).join(".rgames").join("spider.toml"))
+    }
+
+    /// the config from the config file,
+    /// or the default one when there is no file
+    ///
+    /// a file that can't be parsed is reported and ignored,
+    /// so is an invalid value
+    pub fn from_file_or_default() -> Self {
+        match Self::config_path().and_then(|p| std::fs::read_to_string(p).ok()) {
+            Some(content) => Self::from_toml_str(&content),
+            None => Self::default(),
+        }
+    }
+
+    /// the config from the content of a config file
+    ///
+    /// content that can't be parsed is reported and ignored,
+    /// so is an invalid value
+    pub fn from_toml_str(content: &str) -> Self {
+        let file: ConfigFile = match toml::from_str(content) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("warning: ignoring the invalid config file: {}", err);
+                return Self::default();
+            }
+        };
+
+        let game_suit = file.game.suit.and_then(|suit| {
+            let game_suit = game_suit_from_name(&suit);
+            if game_suit.is_none() {
+                eprintln!(
+                    "warning: ignoring the suit {} in the config file, expected one, two or four",
+                    suit
+                );
+            }
+            game_suit
+        });
+        let time_limit = file.limits.time_limit.and_then(|secs| {
+            if !is_valid_time_limit(secs) {
+                eprintln!(
+                    "warning: ignoring the time limit {} in the config file, expected a number of seconds",
+                    secs
+                );
+                return None;
+            }
+            Some(Duration::from_secs(secs))
+        });
+
+        let display = &file.display;
+        let card_back = display
+            .card_back
+            .as_deref()
+            .and_then(|name| {
+                let card_back = CardBack::from_name(name);
+                if card_back.is_none() {
+                    eprintln!(
+                        "warning: ignoring the card back {} in the config file, expected hatched or plain",
+                        name
+                    );
+                }
+                card_back
+            })
+            .unwrap_or_default();
+        let theme = display
+            .theme
+            .as_deref()
+            .and_then(|name| {
+                let theme = Theme::from_name(name);
+                if theme.is_none() {
+                    eprintln!(
+                        "warning: ignoring the theme {} in the config file, expected default, light or mono",
+                        name
+                    );
+                }
+                theme
+            })
+            .unwrap_or_default();
+        let card_back_color = display
+            .card_back_color
+            .as_deref()
+            .and_then(|name| color_from_name("card back color", name));
+        let black_suit_color = display
+            .black_suit_color
+            .as_deref()
+            .and_then(|name| color_from_name("black suit color", name));
+        let red_suit_color = display
+            .red_suit_color
+            .as_deref()
+            .and_then(|name| color_from_name("red suit color", name));
+
+        GameConfig {
+            game_suit,
+            seed: file.game.seed,
+            undo_limit: file.limits.undo_limit,
+            undo_penalty: file.limits.undo_penalty,
+            time_limit,
+            no_animation: file.display.animation == Some(false),
+            ascii: file.display.ascii == Some(true),
+            card_back,
+            theme,
+            card_back_color,
+            black_suit_color,
+            red_suit_color,
+            ..Self::default()
+        }
+    }
+
+    /// the config with the command line arguments applied
+    pub fn merged_with_cli(&self, cli: &CliArgs) -> Self {
+        let mut config = self.clone();
+
+        if let Some(key_binding_mode) = cli.key_binding_mode {
+            config.key_binding_mode = key_binding_mode;
+        }
+        config.relaxed_deal |= cli.relaxed_deal;
+        config.solve |= cli.solve;
+        if cli.game_suit.is_some() {
+            config.game_suit = cli.game_suit;
+        }
+        if cli.seed.is_some() {
+            config.seed = cli.seed;
+        }
+        if cli.undo_limit.is_some() {
+            config.undo_limit = cli.undo_limit;
+        }
+        if let Some(secs) = cli.time_limit {
+            config.time_limit = Some(Duration::from_secs(secs));
+        }
+        config.daily |= cli.daily;
+        config.winnable |= cli.winnable;
+        config.stats |= cli.stats;
+        config.write_config |= cli.write_config;
+        config.no_animation |= cli.no_animation;
+        config.ascii |= cli.ascii;
+        if let Some(card_back) = cli.card_back {
+            config.card_back = card_back;
+        }
+        config.verify_history |= cli.verify_history;
+        if cli.replay.is_some() {
+            config.replay = cli.replay.clone();
+        }
+
+        config
+    }
+
+    /// the config as the content of a config file
+    ///
+    /// fails if the seed is too large for a toml integer
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        let file = ConfigFile {
+            game: GameSection {
+                suit: self
+                    .game_suit
+                    .map(|game_suit| game_suit.to_string().to_lowercase()),
+                seed: self.seed,
+            },
+            limits: LimitsSection {
+                undo_limit: self.undo_limit,
+                undo_penalty: self.undo_penalty,
+                time_limit: self.time_limit.map(|limit| limit.as_secs()),
+            },
+            display: DisplaySection {
+                animation: Some(!self.no_animation),
+                ascii: Some(self.ascii),
+                card_back: Some(self.card_back.name().to_string()),
+                theme: Some(self.theme.name().to_string()),
+                card_back_color: self.card_back_color.and_then(color_name),
+                black_suit_color: self.black_suit_color.and_then(color_name),
+                red_suit_color: self.red_suit_color.and_then(color_name),
+            },
+        };
+
+        toml::to_string(&file)
+    }
+
+    /// write the config to the config file
+    pub fn write_config_file(&self) -> std::io::Result<PathBuf> {
+        let path =
+            Self::config_path().ok_or_else(|| std::io::Error::other("unknown home directory"))?;
+        let content = self.to_toml_string().map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, content)?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_file_round_trip() {
+        let config = GameConfig {
+            game_suit: Some(GameSuitNumber::Two),
+            seed: Some(12345),
+            undo_limit: Some(50),
+            undo_penalty: Some(3),
+            time_limit: Some(Duration::from_secs(600)),
+            no_animation: true,
+            ascii: true,
+            card_back: CardBack::Plain,
+            theme: Theme::Light,
+            card_back_color: Some(Color::Green),
+            black_suit_color: Some(Color::White),
+            red_suit_color: Some(Color::Magenta),
+            ..GameConfig::default()
+        };
+        let content = config.to_toml_string().unwrap();
+
+        let parsed = GameConfig::from_toml_str(&content);
+
+        assert_eq!(parsed.game_suit, config.game_suit);
+        assert_eq!(parsed.seed, config.seed);
+        assert_eq!(parsed.undo_limit, config.undo_limit);
+        assert_eq!(parsed.undo_penalty, config.undo_penalty);
+        assert_eq!(parsed.time_limit, config.time_limit);
+        assert_eq!(parsed.no_animation, config.no_animation);
+        assert_eq!(parsed.ascii, config.ascii);
+        assert_eq!(parsed.card_back, config.card_back);
+        assert_eq!(parsed.theme, config.theme);
+        assert_eq!(parsed.card_back_color, config.card_back_color);
+        assert_eq!(parsed.black_suit_color, config.black_suit_color);
+        assert_eq!(parsed.red_suit_color, config.red_suit_color);
+        assert_eq!(parsed.to_toml_string().unwrap(), content);
+    }
+
+    #[test]
+    fn default_config_round_trip() {
+        let content = GameConfig::default().to_toml_string().unwrap();
+
+        assert_eq!(
+            GameConfig::from_toml_str(&content)
+                .to_toml_string()
+                .unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn invalid_values_fall_back_to_the_default() {
+        let config = GameConfig::from_toml_str(
+            r#"
+            [game]
+            suit = "three"
+            seed = 7
+
+            [limits]
+            time_limit = 0
+
+            [display]
+            theme = "neon"
+            card_back = "striped"
+            red_suit_color = "pink"
+            "#,
+        );
+
+        assert_eq!(config.game_suit, None);
+        assert_eq!(config.seed, Some(7));
+        assert_eq!(config.time_limit, None);
+        assert_eq!(config.theme, Theme::Default);
+        assert_eq!(config.card_back, CardBack::Hatched);
+        assert_eq!(config.red_suit_color, None);
+    }
+
+    #[test]
+    fn unparsable_file_is_the_default() {
+        let config = GameConfig::from_toml_str("[game\nsuit = ");
+
+        assert_eq!(config.game_suit, None);
+        assert_eq!(config.theme, Theme::Default);
+    }
+
+    #[test]
+    fn theme_colors_give_way_to_the_colors_set() {
+        let config = GameConfig {
+            theme: Theme::Mono,
+            red_suit_color: Some(Color::Yellow),
+            ..GameConfig::default()
+        };
+
+        assert_eq!(config.suit_color(Suit::Spades), Color::Reset);
+        assert_eq!(config.suit_color(Suit::Hearts), Color::Yellow);
+        assert_eq!(config.back_color(), Color::Reset);
+        assert_eq!(GameConfig::default().back_color(), Color::Blue);
+    }
+
+    #[test]
+    fn deal_is_strict_unless_relaxed_on_the_command_line() {
+        let strict = GameConfig::default().merged_with_cli(&CliArgs::default());
+        let relaxed = GameConfig::default().merged_with_cli(&CliArgs {
+            relaxed_deal: true,
+            ..CliArgs::default()
+        });
+
+        assert!(!strict.relaxed_deal);
+        assert!(relaxed.relaxed_deal);
+    }
+}
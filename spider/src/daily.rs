@@ -0,0 +1,66 @@
+use crate::card::GameSuitNumber;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// the days since the unix epoch, in UTC
+pub fn today() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        / SECONDS_PER_DAY
+}
+
+/// the seed of the daily deal of a day
+///
+/// mixed with splitmix64 rather than the std hasher,
+/// which is not guaranteed to be the same across builds
+pub fn seed(day: u64, game_suit: GameSuitNumber) -> u64 {
+    let suits = game_suit.card_suits_allowed().len() as u64;
+
+    let mut z = (day << 3 | suits).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// the date of a day since the unix epoch, as YYYY-MM-DD
+pub fn date_string(day: u64) -> String {
+    // the civil from days algorithm by Howard Hinnant
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day_of_month = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_zero_is_the_unix_epoch() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(1), "1970-01-02");
+    }
+
+    #[test]
+    fn leap_days() {
+        assert_eq!(date_string(11_016), "2000-02-29");
+        assert_eq!(date_string(11_017), "2000-03-01");
+        assert_eq!(date_string(157_113), "2400-02-29");
+    }
+
+    #[test]
+    fn dates_after_2100() {
+        // 2100 is not a leap year
+        assert_eq!(date_string(47_540), "2100-02-28");
+        assert_eq!(date_string(47_541), "2100-03-01");
+    }
+}
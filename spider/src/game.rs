@@ -1,56 +1,150 @@
-use std::io::{self, Stdout};
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
 
 use crossterm::event::{self, MouseEventKind};
-use rand::Rng;
-use tui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Layout, Rect},
-    style::Style,
-    widgets::{Block, Borders},
-    Frame,
-};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tui::layout::Rect;
 
 use crate::{
-    card::{Card, GameCard, GameSuitNumber, Rank, Suit},
-    TERMINAL,
+    card::{ascii_suits, Card, Deck, GameCard, GameSuitNumber, Hand, Rank, Suit},
+    config::{CursorMove, GameConfig, KeyBindingMode},
+    daily,
+    game_event::{EventCallbacks, GameEvent},
+    game_suit_prompt::ask_for_game_suit_loop,
+    hint,
+    render::{Renderer, ScreenLayout},
+    replay, save,
+    stats::{GameResult, Stats},
+    winnable,
 };
 
-#[derive(Debug)]
 pub struct Game {
     /// in unix milliseconds
     ///
     /// start from the first move
     pub start_time: Option<u128>,
+    /// in unix milliseconds
+    ///
+    /// set when the game is won or quit, stopping the clock
+    pub end_time: Option<u128>,
+    /// the number of moves made by the player,
+    /// undone moves included
+    pub move_count: u32,
     /// the tableau
     pub tableau: Vec<Vec<GameCard>>,
-    /// the stock
-    pub stock: Vec<GameCard>,
-    /// current stock position
+    /// the stock, face down
     ///
-    /// indicate how many card already been draw
-    pub current_stock_pos: usize,
+    /// the last card is dealt first
+    pub stock: Vec<GameCard>,
     /// the score
     pub score: u32,
+    /// the move count and the score after each score change,
+    /// starting from no move and no score
+    pub score_history: Vec<(u32, u32)>,
     /// the game suit
     pub game_suit: GameSuitNumber,
+    /// the seed of the shuffle,
+    /// the same seed and game suit always give the same deal
+    pub seed: u64,
+    /// the day of the daily deal, in days since the unix epoch,
+    /// none if the game is not the daily deal
+    pub daily: Option<u64>,
+    /// the cards as they were dealt,
+    /// restored by undoing every move
+    pub initial_state: Deal,
     /// history moves
-    pub history_moves: Vec<GameMove>,
-    /// the ui pos of the stock,
-    /// should be initialised after first render
-    ///
-    /// used to decided whether the stock has been clicked
-    pub stock_ui_pos: Option<Rect>,
-    stock_chunks: Vec<Rect>,
-    tableau_chunks: Vec<Rect>,
+    pub history_moves: Vec<AnnotatedMove>,
+    /// hash the state around each move,
+    /// off for the copies searched by the solver, the hashes being 0
+    pub(crate) annotate_moves: bool,
+    /// the undone moves, the last undone on top
+    ///
+    /// cleared by any new move
+    pub redo_moves: Vec<GameMove>,
+    /// the suit of each completed run, in completion order
+    pub foundations: Vec<Suit>,
+    /// the card under the keyboard cursor
+    ///
+    /// none until the cursor is first moved
+    pub selected: Option<CardPosition>,
+    /// the card picked to be moved,
+    /// waiting for a destination pile
+    pub source: Option<CardPosition>,
+    /// the piles the picked cards can go to,
+    /// with the picked card and history length they were found for
+    pub(crate) destinations: Option<(CardPosition, usize, Vec<usize>)>,
+    /// the time and card of the last left click,
+    /// used to detect double clicks
+    pub(crate) last_click: Option<(std::time::Instant, CardPosition)>,
+    /// the last position of the mouse,
+    /// the card under it is highlighted
+    pub mouse_pos: Option<(u16, u16)>,
+    /// the move suggested to the player and when,
+    /// cleared on the next input or after a while
+    pub hint: Option<(GameMove, std::time::Instant)>,
+    /// there is no move left,
+    /// the player is asked how to go on
+    pub deadlocked: bool,
+    /// the time limit is up,
+    /// the player is asked how to go on
+    pub timed_out: bool,
+    /// the moves undone by the player,
+    /// limited by the config
+    pub undo_count: u32,
+    /// the longest face up run of a single suit seen on the tableau
+    pub best_run: u32,
+    /// the action waiting for the player to confirm
+    pub confirm: Option<Confirm>,
+    /// when the cards started to cascade after a win,
+    /// none once the animation is over
+    pub win_animation: Option<std::time::Instant>,
+    /// when the help was opened, in unix milliseconds,
+    /// the clock is paused until it is closed by the next input
+    pub help_opened: Option<u128>,
+    /// the move history shown to the player,
+    /// closed with Esc
+    pub history_view: Option<HistoryView>,
+    /// the computer playing, stopped by any key
+    pub autoplay: Option<AutoPlay>,
+    /// the statistics shown to the player,
+    /// closed on the next input
+    pub stats: Option<Stats>,
+    /// the game result is already in the statistics
+    pub(crate) stats_recorded: bool,
+    /// the game config
+    pub config: GameConfig,
+    /// a message for the player and when,
+    /// cleared on the next input or after a while
+    pub status_message: Option<(String, std::time::Instant)>,
+    /// called for every game event
+    pub event_callbacks: EventCallbacks,
+    /// where the last render drew the stock, piles and cards,
+    /// used to decide what has been clicked
+    pub layout: ScreenLayout,
+    /// the piles changed since the last render, 1-10,
+    /// the others can be drawn as they were
+    pub dirty_piles: HashSet<usize>,
+    /// draw every pile on the next render,
+    /// after a resize or a change of the whole tableau
+    pub force_full_redraw: bool,
+}
+
+/// The cards of a game before any move.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deal {
+    pub tableau: Vec<Vec<GameCard>>,
+    pub stock: Vec<GameCard>,
 }
 
 /// The position of a card in the game
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CardPosition {
     /// The pile position.
     ///
-    /// 0 is the stock
-    ///
     /// 1-10 are the tableau
     pub pile: usize,
     /// The card position in the pile.
@@ -59,13 +153,134 @@ pub struct CardPosition {
     pub card: usize,
 }
 
+/// How good a pile is to move a card onto,
+/// from the worst to the best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DestinationRank {
+    EmptyPile,
+    OtherSuit,
+    SameSuit,
+}
+
+/// An action throwing the current progress away,
+/// only done once the player confirmed it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirm {
+    /// deal a new game, asking for the game suit again
+    NewGame,
+    /// deal a new game with the same game suit
+    NewGameSameSuit,
+    /// restart the current deal from its initial layout
+    Restart,
+    /// leave the game, it is saved on the way out
+    Quit,
+}
+
+impl Confirm {
+    /// the question asked to the player
+    pub fn question(&self) -> &'static str {
+        match self {
+            Confirm::NewGame => "Start a new game?",
+            Confirm::NewGameSameSuit => "Start a new game with the same suits?",
+            Confirm::Restart => "Restart this deal?",
+            Confirm::Quit => "Quit game? Your progress will be autosaved",
+        }
+    }
+}
+
+/// The computer playing the game for the player to watch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoPlay {
+    /// when the last move was made
+    pub last_move: std::time::Instant,
+    /// the states already played through,
+    /// coming back to one means no progress is made
+    pub seen: HashSet<u64>,
+}
+
+/// The move history shown to the player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryView {
+    /// the moves in words, the newest first
+    pub lines: Vec<String>,
+    /// the line under the cursor
+    pub selected: usize,
+}
+
+/// What a key does in the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Quit,
+    MoveSelected,
+    Undo,
+    Redo,
+    Restart,
+    Stats,
+    NewGame,
+    NewGameSameSuit,
+    Hint,
+    DealRow,
+    Save,
+    Help,
+    History,
+    Export,
+    AutoPlay,
+}
+
+/// the keys of the game and what they do,
+/// the help overlay is generated from it
+///
+/// a key also moving the cursor is taken by the cursor
+pub const KEY_BINDINGS: &[(char, KeyAction, &str)] = &[
+    ('s', KeyAction::DealRow, "deal a row from the stock"),
+    (
+        ' ',
+        KeyAction::MoveSelected,
+        "move the card under the cursor",
+    ),
+    ('u', KeyAction::Undo, "undo"),
+    ('r', KeyAction::Redo, "redo"),
+    ('H', KeyAction::Hint, "hint"),
+    // only with the arrow keys, vim moves the cursor left with it
+    ('h', KeyAction::Hint, "hint"),
+    ('R', KeyAction::Restart, "restart this deal"),
+    ('n', KeyAction::NewGame, "new game"),
+    ('N', KeyAction::NewGameSameSuit, "new game, same suits"),
+    ('S', KeyAction::Save, "save"),
+    ('t', KeyAction::Stats, "statistics"),
+    ('m', KeyAction::History, "move history"),
+    ('e', KeyAction::Export, "export a replay"),
+    (
+        'A',
+        KeyAction::AutoPlay,
+        "let the computer play, any key stops it",
+    ),
+    ('?', KeyAction::Help, "this help"),
+    ('q', KeyAction::Quit, "quit"),
+];
+
+/// what a key does with the cursor keys of a mode,
+/// none for a key moving the cursor or bound to nothing
+pub fn key_action(mode: KeyBindingMode, key: char) -> Option<KeyAction> {
+    if mode
+        .cursor_move_from_keycode(event::KeyCode::Char(key))
+        .is_some()
+    {
+        return None;
+    }
+
+    KEY_BINDINGS
+        .iter()
+        .find(|(k, ..)| *k == key)
+        .map(|(_, action, _)| *action)
+}
+
 /// The move the player wants to make.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameMove {
-    /// Draw a card from the stock.
-    DrawStock,
-    /// Recycle the stock.
-    RecycleStock,
+    /// Deal a row from the stock,
+    /// one face up card on each tableau pile.
+    DealRow,
     /// Move a card from the tableau to the tableau.
     ///
     /// Or a list of cards from the tableau to the tableau.
@@ -80,165 +295,798 @@ pub enum GameMove {
         /// If the card before the src card is turn up,
         /// then after the move, before_visible is set to false.
         ///
-        /// Otherwise None.
+        /// Otherwise None, there is no card before the src card.
+        ///
+        /// Only a guess when the move is made,
+        /// the history records the flip the move actually made.
         before_visible: Option<bool>,
     },
+    /// Remove a completed King to Ace run of a single suit
+    /// from the top of a tableau pile.
+    CompleteRun {
+        /// the tableau pile, 1-10
+        pile: usize,
+        suit: Suit,
+        /// the card under the run was face down,
+        /// and turned up once the run was removed
+        #[serde(default)]
+        flipped: bool,
+    },
+}
+
+impl GameMove {
+    /// the move in words, read from the game before the move
+    ///
+    /// e.g. `Moved 7♠–5♠ from pile 3 to pile 6`
+    pub fn describe(&self, game: &Game) -> String {
+        match self {
+            GameMove::DealRow => String::from("Dealt a row"),
+            GameMove::MoveCard { src, dst, .. } => {
+                let pile = game.tableau.get(src.pile.wrapping_sub(1));
+                let cards = pile
+                    .and_then(|pile| pile.get(src.card..))
+                    .unwrap_or_default();
+                let name = |card: &GameCard| card.card.display_compact().trim_end().to_string();
+                let run = match (cards.first(), cards.last()) {
+                    (Some(first), Some(last)) if cards.len() > 1 => {
+                        let dash = if ascii_suits() { "-" } else { "–" };
+                        format!("{}{}{} ", name(first), dash, name(last))
+                    }
+                    (Some(card), _) => format!("{} ", name(card)),
+                    _ => String::new(),
+                };
+
+                format!("Moved {}from pile {} to pile {}", run, src.pile, dst.pile)
+            }
+            GameMove::CompleteRun { suit, .. } => format!("Completed {} run", suit),
+        }
+    }
+
+    /// test if the move changes a tableau pile, 1-10
+    ///
+    /// dealing a row changes all of them
+    pub fn affects_pile(&self, pile: usize) -> bool {
+        match self {
+            GameMove::DealRow => (1..=10).contains(&pile),
+            GameMove::MoveCard { src, dst, .. } => src.pile == pile || dst.pile == pile,
+            GameMove::CompleteRun { pile: run_pile, .. } => *run_pile == pile,
+        }
+    }
+}
+
+/// A move in the history, with the state of the game around it,
+/// to find where a history went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotatedMove {
+    pub game_move: GameMove,
+    /// the state hash before the move
+    pub pre_state_hash: u64,
+    /// the state hash after the move
+    pub post_state_hash: u64,
+    /// when the move was made, in unix milliseconds
+    pub timestamp_ms: u128,
 }
 
+/// the score gained for each completed run
+const COMPLETE_RUN_SCORE: u32 = 100;
+
+/// the number of completed runs needed to win
+pub const RUNS_TO_WIN: usize = 8;
+
+/// the number of cards dealt from the stock at once
+pub const DEAL_SIZE: usize = 10;
+
+/// the number of cards in the stock at the start of a game
+pub const STOCK_SIZE: usize = 50;
+
+/// the longest time between the two clicks of a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// how long a hint stays highlighted
+const HINT_DURATION: Duration = Duration::from_secs(3);
+
+/// how long a status message stays shown
+const STATUS_DURATION: Duration = Duration::from_secs(4);
+
+/// how often the ui is refreshed while waiting for input
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+/// the time left under which the countdown turns yellow
+pub const TIME_PRESSURE: Duration = Duration::from_secs(30);
+/// the time left under which the countdown turns red and pulses
+pub const TIME_CRITICAL: Duration = Duration::from_secs(10);
+/// the time between two moves of the autoplay
+const AUTOPLAY_INTERVAL: Duration = Duration::from_millis(300);
+/// how long the cards cascade on a win
+pub const WIN_ANIMATION_DURATION: Duration = Duration::from_secs(2);
+/// the time between two frames of the win animation
+pub const WIN_ANIMATION_FRAME: Duration = Duration::from_millis(40);
+
 /// the error might occurred in a move
 pub enum MoveError {
-    /// try to draw a empty stock
-    DrawEmptyStock,
-    /// try to recycle a none empty stock
-    RecycleNoneEmptyStock,
+    /// try to deal from a empty stock
+    DealEmptyStock,
+    /// try to deal while a tableau pile is empty
+    DealWithEmptyPile,
     /// move card src not exist
     MoveSrcNotExist,
-    /// move invalid card in stock
-    MoveInvalidStockCard,
-    /// move dst not exist or occupied,
-    /// or not valid regarding the game suit
+    /// move dst not exist or occupied
     MoveDstNotValid,
+    /// the moved card is not one rank below the card of the dst pile
+    MoveCardNotUnder { card: Card, onto: Card },
+    /// the moved cards are not a descending run,
+    /// or not valid regarding the game suit
+    MoveGroupNotSequential,
+    /// there is no completed run on the pile
+    NoCompletedRun,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::DealEmptyStock => write!(f, "No deals left, the stock is empty"),
+            MoveError::DealWithEmptyPile => write!(f, "Can't deal while a tableau pile is empty"),
+            MoveError::MoveSrcNotExist => write!(f, "There is no card to move"),
+            MoveError::MoveDstNotValid => write!(f, "The cards can't be placed there"),
+            MoveError::MoveCardNotUnder { card, onto } => {
+                write!(f, "Can't place {} on {}", card, onto)
+            }
+            MoveError::MoveGroupNotSequential => write!(f, "The cards to move are not a run"),
+            MoveError::NoCompletedRun => write!(f, "There is no completed run"),
+        }
+    }
+}
+
+/// the game state over several lines,
+/// the ui state and the history are left out
+impl std::fmt::Debug for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stock: Vec<String> = self
+            .stock
+            .iter()
+            .map(|card| card.card.to_notation())
+            .collect();
+
+        writeln!(f, "Game {{")?;
+        writeln!(
+            f,
+            "  suits: {}, seed: {}, daily: {:?}",
+            self.game_suit, self.seed, self.daily
+        )?;
+        writeln!(
+            f,
+            "  score: {}, moves: {}, undos: {}, history: {}",
+            self.score,
+            self.move_count,
+            self.undo_count,
+            self.history_moves.len()
+        )?;
+        writeln!(
+            f,
+            "  deals left: {}, foundations: {:?}",
+            self.deals_remaining(),
+            self.foundations
+        )?;
+        writeln!(f, "  stock: [{}]", stock.join(", "))?;
+        for line in self.debug_tableau_string().lines() {
+            writeln!(f, "  {}", line)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// the current time in unix milliseconds
+pub(crate) fn now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
 }
 
 /// test if a point is in the Rect
-fn test_point_in_rect(x: u16, y: u16, rect: Rect) -> bool {
+pub(crate) fn test_point_in_rect(x: u16, y: u16, rect: Rect) -> bool {
     x >= rect.x && y >= rect.y && x < rect.x + rect.width && y < rect.y + rect.height
 }
 
-/// verity a card could go under another card
-fn verify_under(game_suit: GameSuitNumber, up: Card, down: Card) -> bool {
+/// verity a card could be placed on another card,
+/// the next higher rank of any suit
+pub(crate) fn verify_rank_under(up: Card, down: Card) -> bool {
     let up_rank: u8 = up.rank.into();
     let down_rank: u8 = down.rank.into();
-    if up_rank < down_rank {
-        return false;
-    }
-    if up_rank - down_rank != 1 {
+
+    up_rank == down_rank + 1
+}
+
+/// verity a card could go under another card in a run,
+/// a run moving together only if it is of a single suit
+pub(crate) fn verify_under(game_suit: GameSuitNumber, up: Card, down: Card) -> bool {
+    if !verify_rank_under(up, down) {
         return false;
     }
 
     match game_suit {
         GameSuitNumber::One => true,
-        GameSuitNumber::Two => up.suit.color() == down.suit.color(),
-        GameSuitNumber::Four => up.suit == down.suit,
+        GameSuitNumber::Two | GameSuitNumber::Four => up.suit == down.suit,
+    }
+}
+
+/// test if the cards can be moved together,
+/// all face up and each one valid under the one before
+fn is_sequential_group(game_suit: GameSuitNumber, cards: &[GameCard]) -> bool {
+    cards.iter().all(|card| card.is_up)
+        && Hand::from_pile_slice(cards, 0).is_valid_sequence(game_suit)
+}
+
+/// the length of the longest face up descending run of a single suit in a pile
+fn longest_run(pile: &[GameCard]) -> u32 {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<Card> = None;
+    for card in pile {
+        if !card.is_up {
+            run = 0;
+            previous = None;
+            continue;
+        }
+
+        run = match previous {
+            Some(up)
+                if up.suit == card.card.suit
+                    && verify_under(GameSuitNumber::One, up, card.card) =>
+            {
+                run + 1
+            }
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(card.card);
     }
+
+    longest
 }
 
 impl Game {
     /// test if a game is win
+    ///
+    /// a game is win when all the runs are completed,
+    /// a run only reaches the foundations
+    /// once checked to be King to Ace of a single suit
     pub fn test_win(&self) -> bool {
-        // if stock not empty,
-        // return false
-        if !self.stock.is_empty() {
+        self.completed_run_count() == RUNS_TO_WIN
+    }
+
+    /// add the current score to the score history
+    fn record_score(&mut self) {
+        self.score_history.push((self.move_count, self.score));
+    }
+
+    /// the score gained per move on average
+    pub fn score_per_move(&self) -> f64 {
+        self.score as f64 / self.move_count.max(1) as f64
+    }
+
+    /// the best score per move reached during the game
+    pub fn peak_score_per_move(&self) -> f64 {
+        self.score_history
+            .iter()
+            .map(|&(moves, score)| score as f64 / moves.max(1) as f64)
+            .fold(0.0, f64::max)
+    }
+
+    /// the score right after the given number of moves,
+    /// none if the game has not reached that many moves yet
+    pub fn score_at_move(&self, n: usize) -> Option<u32> {
+        if n > self.move_count as usize {
+            return None;
+        }
+
+        self.score_history
+            .iter()
+            .take_while(|&&(moves, _)| moves as usize <= n)
+            .last()
+            .map(|&(_, score)| score)
+    }
+
+    /// register a callback called for every game event
+    pub fn on_event(&mut self, cb: impl Fn(&GameEvent) + 'static) {
+        self.event_callbacks.push(cb);
+    }
+
+    /// the time passed since the first move,
+    /// until the game is won or quit
+    pub fn elapsed(&self) -> Duration {
+        let start_time = match self.start_time {
+            Some(start_time) => start_time,
+            None => return Duration::ZERO,
+        };
+        let end_time = self
+            .end_time
+            .or(self.help_opened)
+            .unwrap_or_else(now_millis);
+
+        Duration::from_millis(end_time.saturating_sub(start_time) as u64)
+    }
+
+    /// stop the clock
+    fn stop_timer(&mut self) {
+        if self.end_time.is_none() {
+            self.end_time = Some(now_millis());
+        }
+    }
+
+    /// close the help, the clock going on
+    /// as if it was never opened
+    fn close_help(&mut self) {
+        let opened = match self.help_opened.take() {
+            Some(opened) => opened,
+            None => return,
+        };
+        if let (Some(start_time), None) = (self.start_time, self.end_time) {
+            self.start_time = Some(start_time + now_millis().saturating_sub(opened));
+        }
+    }
+
+    /// the keys and rules, for the help overlay
+    pub fn help_text(&self) -> String {
+        let cursor = match self.config.key_binding_mode {
+            KeyBindingMode::Arrow => "arrows",
+            KeyBindingMode::Vim => "hjkl",
+            KeyBindingMode::Both => "arrows, hjkl",
+        };
+        let mut lines = vec![
+            format!("{:<14}move the cursor", cursor),
+            format!("{:<14}pick the card, then drop it", "Enter"),
+            format!("{:<14}select a pile", "1-9, 0"),
+            format!("{:<14}cancel the picked card", "Esc"),
+        ];
+        let keys = KEY_BINDINGS
+            .iter()
+            .filter(|(key, ..)| key_action(self.config.key_binding_mode, *key).is_some());
+        for (key, _, description) in keys {
+            let key = match key {
+                ' ' => String::from("Space"),
+                key => key.to_string(),
+            };
+            lines.push(format!("{:<14}{}", key, description));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("Put a card on the next higher rank."));
+        lines.push(match self.game_suit {
+            GameSuitNumber::One => String::from("Move a run in order together."),
+            _ => String::from("Move a run in order of a single suit together."),
+        });
+        lines.push(String::from("King to Ace of a suit is removed."));
+        if !self.config.relaxed_deal {
+            lines.push(String::from("Dealing needs every pile to have a card."));
+        }
+        lines.push(format!("Remove {} runs to win.", RUNS_TO_WIN));
+        let penalty = self.config.undo_penalty();
+        let mut undo = format!(
+            "An undo costs {} point{}, a redo does not refund it.",
+            penalty,
+            if penalty == 1 { "" } else { "s" }
+        );
+        if let Some(remaining) = self.undos_remaining() {
+            undo.push_str(&format!(" {} undos left.", remaining));
+        }
+        lines.push(undo);
+
+        lines.join("\n")
+    }
+
+    /// the number of runs completed so far
+    pub fn completed_run_count(&self) -> usize {
+        self.foundations.len()
+    }
+
+    /// the suit of the completed run on the top of a tableau pile
+    ///
+    /// none if there is no face up King to Ace run of a single suit
+    fn completed_run_suit(&self, pile: usize) -> Option<Suit> {
+        let pile = self.tableau.get(pile.checked_sub(1)?)?;
+        if pile.len() < 13 {
+            return None;
+        }
+
+        // a face down card would be skipped and make the run shorter,
+        // checked as four suits so the run is of a single suit
+        let run = Hand::from_pile_slice(pile, pile.len() - 13);
+        if run.len() != 13 || !run.is_valid_sequence(GameSuitNumber::Four) {
+            return None;
+        }
+
+        run.bottom()
+            .filter(|card| card.rank == Rank::King)
+            .map(|card| card.suit)
+    }
+
+    /// remove the completed run on the top of a tableau pile,
+    /// recorded in the history
+    ///
+    /// true if there was one
+    pub fn check_and_remove_completed_pile(&mut self, pile: usize) -> bool {
+        let Some(suit) = self.completed_run_suit(pile) else {
             return false;
+        };
+
+        let cards = &self.tableau[pile - 1];
+        let flipped = cards
+            .len()
+            .checked_sub(14)
+            .is_some_and(|under| !cards[under].is_up);
+        self.do_move(GameMove::CompleteRun {
+            pile,
+            suit,
+            flipped,
+        })
+        .is_ok()
+    }
+
+    /// the longest run seen, after a move
+    ///
+    /// only the piles touched by the move are scanned,
+    /// before a completed run is removed
+    fn update_best_run(&mut self, game_move: GameMove) {
+        let longest = match game_move {
+            GameMove::MoveCard { src, dst, .. } => [src.pile, dst.pile]
+                .into_iter()
+                .filter_map(|pile| self.tableau.get(pile.checked_sub(1)?))
+                .map(|pile| longest_run(pile))
+                .max(),
+            _ => self.tableau.iter().map(|pile| longest_run(pile)).max(),
+        };
+        self.best_run = self.best_run.max(longest.unwrap_or(0));
+    }
+
+    /// forget the changed piles, once they are drawn
+    pub fn clear_dirty(&mut self) {
+        self.dirty_piles.clear();
+        self.force_full_redraw = false;
+    }
+
+    /// mark the piles a move changes, to be drawn again
+    fn mark_dirty(&mut self, game_move: GameMove) {
+        let piles = (1..=self.tableau.len()).filter(|&pile| game_move.affects_pile(pile));
+        self.dirty_piles.extend(piles);
+    }
+
+    /// remove all the completed runs on the tableau,
+    /// each of them is recorded in the history
+    fn remove_completed_runs(&mut self) {
+        for pile in 1..=10 {
+            self.check_and_remove_completed_pile(pile);
         }
+    }
 
-        for i in 0..10 {
-            let pile = self.tableau.get(i);
-            if pile.is_none() {
-                continue;
+    /// undo once
+    ///
+    /// the completed runs are undone together with the move completing them
+    ///
+    /// true if a move was undone
+    pub fn undo_once(&mut self) -> bool {
+        let mut undone = false;
+        loop {
+            let game_move = self.history_moves.last();
+            if game_move.is_none() {
+                return undone;
             }
-            let pile = pile.unwrap();
+            let game_move = game_move.unwrap().game_move;
 
-            if pile.is_empty() {
-                continue;
+            let res = self.undo_move(game_move);
+            if res.is_err() {
+                return undone;
             }
-
-            if pile.len() != 13 {
-                return false;
+            undone = true;
+            self.mark_dirty(game_move);
+            self.history_moves.pop();
+            self.event_callbacks.emit(&GameEvent::MoveUndone(game_move));
+            // the game is no longer won
+            self.end_time = None;
+
+            // completed runs are removed again when redoing the move
+            if !matches!(game_move, GameMove::CompleteRun { .. }) {
+                self.redo_moves.push(game_move);
+                return true;
             }
+        }
+    }
 
-            #[allow(clippy::needless_range_loop)]
-            for j in 0..13 {
-                let card = pile[j];
-                if !card.is_up {
-                    return false;
-                }
-            }
+    /// undo the last move for the player,
+    /// unless the undo limit is reached
+    fn undo_by_player(&mut self) {
+        if !self.can_undo() {
+            return;
+        }
+        if self
+            .config
+            .undo_limit
+            .is_some_and(|limit| self.undo_count >= limit)
+        {
+            self.set_status(String::from("No undo left"));
+            return;
+        }
+
+        if !self.undo_once() {
+            return;
         }
+        self.undo_count += 1;
+        self.score = self.score.saturating_sub(self.config.undo_penalty());
+        self.record_score();
+    }
+
+    /// the undos the player has left,
+    /// none when unlimited
+    pub fn undos_remaining(&self) -> Option<u32> {
+        let limit = self.config.undo_limit?;
 
-        true
+        Some(limit.saturating_sub(self.undo_count))
     }
 
-    /// undo once
-    pub fn undo_once(&mut self) {
-        let game_move = self.history_moves.last();
-        if game_move.is_none() {
+    /// the moves on the board, the undone ones left out,
+    /// the completed runs being part of the move before them
+    pub fn num_moves_made(&self) -> usize {
+        self.history_moves
+            .iter()
+            .filter(|annotated| !matches!(annotated.game_move, GameMove::CompleteRun { .. }))
+            .count()
+    }
+
+    /// the moves undone by the player
+    pub fn num_undos_made(&self) -> usize {
+        self.undo_count as usize
+    }
+
+    /// the moves on the board less the undos it took to get there
+    pub fn net_moves(&self) -> i64 {
+        self.num_moves_made() as i64 - self.num_undos_made() as i64
+    }
+
+    /// test if there is any move left, a deal included
+    pub fn has_any_move(&self) -> bool {
+        !self.legal_moves().is_empty()
+    }
+
+    /// ask the player how to go on
+    /// once there is no move left
+    fn check_deadlock(&mut self) {
+        if self.deadlocked || self.test_win() || self.has_any_move() {
             return;
         }
-        let game_move = *game_move.unwrap();
 
-        let res = self.undo_move(game_move);
-        if res.is_ok() {
-            self.history_moves.pop();
+        self.deadlocked = true;
+        self.event_callbacks.emit(&GameEvent::Deadlocked);
+    }
+
+    /// the time left to win the game,
+    /// none when it is not timed
+    pub fn time_remaining(&self) -> Option<Duration> {
+        let time_limit = self.config.time_limit?;
+
+        Some(time_limit.saturating_sub(self.elapsed()))
+    }
+
+    /// the time left is short enough to hurry the player
+    pub fn time_pressure_mode(&self) -> bool {
+        self.time_remaining()
+            .is_some_and(|remaining| remaining < TIME_PRESSURE)
+    }
+
+    /// end the game as lost once the time is up
+    fn check_time_out(&mut self) {
+        if self.timed_out || self.test_win() || self.time_remaining() != Some(Duration::ZERO) {
+            return;
+        }
+
+        self.timed_out = true;
+        self.stop_timer();
+        self.record_result();
+    }
+
+    /// the game as it was dealt, before any move
+    pub fn initial_deal(&self) -> Game {
+        let mut game = Game::from_deal(
+            self.game_suit,
+            self.seed,
+            self.initial_state.clone(),
+            self.config.clone(),
+        );
+        game.daily = self.daily;
+
+        game
+    }
+
+    /// put the cards back as they were dealt,
+    /// with no history, score, move or undo count or clock
+    ///
+    /// the cards come from the snapshot taken when dealing,
+    /// so it does not depend on every move undoing cleanly
+    pub fn undo_all(&mut self) {
+        self.force_full_redraw = true;
+        self.tableau = self.initial_state.tableau.clone();
+        self.stock = self.initial_state.stock.clone();
+        self.foundations.clear();
+        self.score = 0;
+        self.history_moves.clear();
+        self.redo_moves.clear();
+        self.score_history = vec![(0, 0)];
+        self.start_time = None;
+        self.end_time = None;
+        self.move_count = 0;
+        self.undo_count = 0;
+    }
+
+    /// undo every move, back to the start of the deal
+    pub fn restart(&mut self) {
+        self.record_result();
+        self.undo_all();
+
+        self.best_run = self
+            .tableau
+            .iter()
+            .map(|pile| longest_run(pile))
+            .max()
+            .unwrap_or(0);
+        self.source = None;
+        self.deadlocked = false;
+        self.timed_out = false;
+        self.stats_recorded = false;
+    }
+
+    /// deal a new game with the same suits and config,
+    /// keeping the event callbacks
+    pub fn new_game(&mut self) {
+        self.new_game_with_suit(self.game_suit);
+    }
+
+    /// deal a new game with the given suits and the same config,
+    /// keeping the event callbacks
+    pub fn new_game_with_suit(&mut self, game_suit: GameSuitNumber) {
+        self.record_result();
+
+        let event_callbacks = std::mem::take(&mut self.event_callbacks);
+        // the seed or daily deal given on the command line is only for the first deal
+        let config = GameConfig {
+            seed: None,
+            daily: false,
+            ..self.config.clone()
+        };
+        *self = Game::new(game_suit, config);
+        self.event_callbacks = event_callbacks;
+    }
+
+    /// add the game to the statistics once,
+    /// as a win or, if a move was made, as abandoned
+    fn record_result(&mut self) {
+        if self.stats_recorded || self.move_count == 0 {
+            return;
+        }
+        self.stats_recorded = true;
+
+        let mut stats = Stats::load();
+        stats.record(&GameResult::from(&*self));
+        if let Err(err) = stats.save() {
+            self.set_status(format!("Can't save the statistics: {}", err));
+        }
+    }
+
+    /// do the confirmed action
+    fn do_confirmed(&mut self, confirm: Confirm) -> crossterm::Result<()> {
+        match confirm {
+            Confirm::NewGame => {
+                // going back to the game if the prompt is left
+                if let Some(game_suit) = ask_for_game_suit_loop()? {
+                    self.new_game_with_suit(game_suit);
+                }
+            }
+            Confirm::NewGameSameSuit => self.new_game(),
+            Confirm::Restart => self.restart(),
+            // leaving the game loop is up to the loop
+            Confirm::Quit => self.stop_timer(),
+        }
+
+        Ok(())
+    }
+
+    /// test if there is a move to undo
+    pub fn can_undo(&self) -> bool {
+        !self.history_moves.is_empty()
+    }
+
+    /// test if there is an undone move to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo_moves.is_empty()
+    }
+
+    /// the number of moves in the history,
+    /// completed runs included
+    pub fn moves_count(&self) -> usize {
+        self.history_moves.len()
+    }
+
+    /// redo the last undone move
+    pub fn redo_once(&mut self) {
+        // a new move clears the redo stack,
+        // keep it aside while the move is made again
+        let mut redo_moves = std::mem::take(&mut self.redo_moves);
+        let game_move = match redo_moves.pop() {
+            Some(game_move) => game_move,
+            None => return,
+        };
+
+        if self.do_move(game_move).is_err() {
+            redo_moves.push(game_move);
         }
+        self.redo_moves = redo_moves;
     }
 
     /// undo a move
     fn undo_move(&mut self, game_move: GameMove) -> Result<(), MoveError> {
         match game_move {
-            GameMove::DrawStock => self.undo_move_draw_stock(),
-            GameMove::RecycleStock => self.undo_recycle_stock(),
+            GameMove::DealRow => self.undo_deal_row(),
             GameMove::MoveCard {
                 src,
                 dst,
                 before_visible,
-            } => {
-                if src.pile == 0 {
-                    self.undo_move_stock_to_tableau(dst)
-                } else {
-                    self.undo_move_tableau_to_tableau(src, dst, before_visible)
-                }
-            }
+            } => self.undo_move_tableau_to_tableau(src, dst, before_visible),
+            GameMove::CompleteRun {
+                pile,
+                suit,
+                flipped,
+            } => self.undo_complete_run(pile, suit, flipped),
         }
     }
 
-    /// undo the draw stock move
-    fn undo_move_draw_stock(&mut self) -> Result<(), MoveError> {
-        if self.current_stock_pos == 0 {
-            return Err(MoveError::DrawEmptyStock);
+    /// undo the complete run move,
+    /// put the run back on the pile,
+    /// over the card under it turned down again if the removal flipped it
+    fn undo_complete_run(
+        &mut self,
+        pile: usize,
+        suit: Suit,
+        flipped: bool,
+    ) -> Result<(), MoveError> {
+        if pile == 0 {
+            return Err(MoveError::NoCompletedRun);
         }
+        let pile = self.tableau.get_mut(pile - 1);
+        if pile.is_none() {
+            return Err(MoveError::NoCompletedRun);
+        }
+        let pile = pile.unwrap();
 
-        self.current_stock_pos -= 1;
-
-        Ok(())
-    }
-
-    /// undo the recycle stock move
-    fn undo_recycle_stock(&mut self) -> Result<(), MoveError> {
-        if self.current_stock_pos != 0 {
-            return Err(MoveError::RecycleNoneEmptyStock);
+        if flipped {
+            if let Some(last) = pile.last_mut() {
+                last.is_up = false;
+            }
+        }
+        for rank in (1..14).rev() {
+            pile.push(GameCard {
+                card: Card {
+                    suit,
+                    rank: Rank::try_from(rank).unwrap(),
+                },
+                is_up: true,
+            });
         }
 
-        self.current_stock_pos = self.stock.len();
+        self.foundations.pop();
+        self.score = self.score.saturating_sub(COMPLETE_RUN_SCORE);
+        self.record_score();
 
         Ok(())
     }
 
-    /// undo the move card from stock to tableau
-    fn undo_move_stock_to_tableau(&mut self, dst: CardPosition) -> Result<(), MoveError> {
-        // get dst card
-        if dst.pile == 0 {
-            return Err(MoveError::MoveDstNotValid);
-        }
-        let dst_pile = self.tableau.get_mut(dst.pile - 1);
-        if dst_pile.is_none() {
-            return Err(MoveError::MoveDstNotValid);
-        }
-        let dst_pile = dst_pile.unwrap();
-        let dst_card = dst_pile.get(dst.card);
-        if dst_card.is_none() {
-            return Err(MoveError::MoveDstNotValid);
+    /// undo the deal row move,
+    /// put the top card of each pile back to the stock
+    fn undo_deal_row(&mut self) -> Result<(), MoveError> {
+        if self.tableau.iter().any(|pile| pile.is_empty()) {
+            return Err(MoveError::MoveSrcNotExist);
         }
-        let dst_card = *dst_card.unwrap();
-
-        self.stock.insert(self.current_stock_pos, dst_card);
-        self.current_stock_pos += 1;
 
-        if dst.card < dst_pile.len() {
-            let n = dst_pile.len();
-            for _ in dst.card..n {
-                dst_pile.pop();
-            }
+        for pile in self.tableau.iter_mut().rev() {
+            let mut card = pile.pop().unwrap();
+            card.is_up = false;
+            self.stock.push(card);
         }
 
         Ok(())
@@ -256,12 +1104,10 @@ impl Game {
             return Err(MoveError::MoveSrcNotExist);
         }
 
-        if let Some(before_visible) = before_visible {
-            if src.card > 0 {
-                let card = self.tableau[src.pile - 1].get_mut(src.card - 1);
-                if let Some(card) = card {
-                    card.is_up = !before_visible;
-                }
+        // only turn down the card the move turned up
+        if before_visible == Some(true) && src.card > 0 {
+            if let Some(card) = self.tableau[src.pile - 1].get_mut(src.card - 1) {
+                card.is_up = false;
             }
         }
 
@@ -284,93 +1130,195 @@ impl Game {
         Ok(())
     }
 
-    /// do a move
-    pub fn do_move(&mut self, game_move: GameMove) -> Result<(), MoveError> {
-        let res = match game_move {
-            GameMove::DrawStock => self.do_move_draw_stock(),
-            GameMove::RecycleStock => self.do_move_recycle_stock(),
-            GameMove::MoveCard {
-                src,
-                dst,
-                before_visible: _,
-            } => self.do_move_card(src, dst),
-        };
-
-        if res.is_ok() {
-            self.history_moves.push(game_move);
+    /// a hash of the cards of the tableau, stock and foundations
+    ///
+    /// made with the std hasher, so only comparable within a build
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for pile in self.tableau.iter().chain(std::iter::once(&self.stock)) {
+            pile.len().hash(&mut hasher);
+            for card in pile {
+                card.card.hash(&mut hasher);
+                card.is_up.hash(&mut hasher);
+            }
         }
+        self.foundations.hash(&mut hasher);
 
-        res
+        hasher.finish()
     }
 
-    /// move a card
-    fn do_move_card(&mut self, src: CardPosition, dst: CardPosition) -> Result<(), MoveError> {
-        if src.pile == 0 {
-            self.do_move_card_stock_to_tableau(src, dst)
-        } else {
-            self.do_move_card_tableau_to_tableau(src, dst)
-        }
-    }
+    /// the index of the first history move not matching a replay from the deal,
+    /// the length of the history if only the final state differs,
+    /// none if the history is sound
+    pub fn history_discrepancy(&self) -> Option<usize> {
+        let mut replay = self.initial_deal();
+
+        for (i, annotated) in self.history_moves.iter().enumerate() {
+            // the completed runs are made again by the move before them
+            if replay.history_moves.len() <= i
+                && (replay.state_hash() != annotated.pre_state_hash
+                    || replay.do_move(annotated.game_move).is_err())
+            {
+                return Some(i);
+            }
 
-    /// move card from stock to tableau
-    fn do_move_card_stock_to_tableau(
-        &mut self,
-        src: CardPosition,
-        dst: CardPosition,
-    ) -> Result<(), MoveError> {
-        if src.card != self.current_stock_pos - 1 {
-            return Err(MoveError::MoveInvalidStockCard);
+            let matches = replay.history_moves.get(i).is_some_and(|replayed| {
+                replayed.game_move == annotated.game_move
+                    && replayed.post_state_hash == annotated.post_state_hash
+            });
+            if !matches {
+                return Some(i);
+            }
         }
 
-        let src_card = self.stock.get(src.card);
-        if src_card.is_none() {
-            return Err(MoveError::MoveSrcNotExist);
-        }
-        let src_card = *src_card.unwrap();
-        if !src_card.is_up {
-            return Err(MoveError::MoveSrcNotExist);
+        let n = self.history_moves.len();
+        if replay.history_moves.len() != n || replay.state_hash() != self.state_hash() {
+            return Some(n);
         }
 
-        let dst_pile = self.tableau.get_mut(dst.pile - 1);
-        if dst_pile.is_none() {
-            return Err(MoveError::MoveDstNotValid);
-        }
-        let dst_pile: &mut Vec<GameCard> = dst_pile.unwrap();
+        None
+    }
 
-        if dst_pile.len() > dst.card {
-            return Err(MoveError::MoveDstNotValid);
-        }
+    /// the moves made so far in words, the newest first
+    ///
+    /// the history is replayed from the deal to know the cards moved
+    pub fn history_descriptions(&self) -> Vec<String> {
+        let mut replay = self.initial_deal();
+        replay.annotate_moves = false;
+
+        let mut lines: Vec<String> = self
+            .history_moves
+            .iter()
+            .enumerate()
+            .map(|(i, annotated)| {
+                let line = annotated.game_move.describe(&replay);
+                // the completed runs are made again by the move before them
+                if replay.history_moves.len() <= i {
+                    let _ = replay.do_move(annotated.game_move);
+                }
+                line
+            })
+            .collect();
+        lines.reverse();
 
-        if src_card.card.rank == Rank::King && dst_pile.is_empty() {
-            dst_pile.push(src_card);
+        lines
+    }
 
-            self.stock.remove(src.card);
-            self.current_stock_pos -= 1;
+    /// test if replaying the history from the deal gives the same states
+    pub fn verify_history_integrity(&self) -> bool {
+        self.history_discrepancy().is_none()
+    }
 
-            return Ok(());
-        }
+    /// do a move
+    pub fn do_move(&mut self, game_move: GameMove) -> Result<(), MoveError> {
+        let state_hash = |game: &Game| {
+            if game.annotate_moves {
+                game.state_hash()
+            } else {
+                0
+            }
+        };
+        let pre_state_hash = state_hash(self);
+        let mut game_move = game_move;
+        let res = match game_move {
+            GameMove::DealRow => self.do_move_deal_row(),
+            GameMove::MoveCard { src, dst, .. } => self.do_move_card(src, dst).map(|flipped| {
+                // the flip made is recorded, not the one the caller expected
+                game_move = GameMove::MoveCard {
+                    src,
+                    dst,
+                    before_visible: (src.card > 0).then_some(flipped),
+                };
+            }),
+            GameMove::CompleteRun { pile, suit, .. } => self.do_move_complete_run(pile, suit),
+        };
 
-        let dst_before = dst_pile.last();
-        if dst_before.is_none() {
-            return Err(MoveError::MoveDstNotValid);
+        if res.is_ok() {
+            self.mark_dirty(game_move);
+            self.history_moves.push(AnnotatedMove {
+                game_move,
+                pre_state_hash,
+                post_state_hash: state_hash(self),
+                timestamp_ms: now_millis(),
+            });
+
+            if let GameMove::CompleteRun { pile, suit, .. } = game_move {
+                self.set_status(format!(
+                    "Run of {} completed, {}/{}",
+                    suit,
+                    self.completed_run_count(),
+                    RUNS_TO_WIN
+                ));
+                self.event_callbacks
+                    .emit(&GameEvent::SequenceCompleted { pile, suit });
+            } else {
+                if self.start_time.is_none() {
+                    self.start_time = Some(now_millis());
+                }
+                self.move_count += 1;
+                self.redo_moves.clear();
+                self.update_best_run(game_move);
+
+                self.event_callbacks.emit(&GameEvent::MoveMade(game_move));
+                self.remove_completed_runs();
+
+                if self.test_win() {
+                    // the win message is shown instead
+                    self.status_message = None;
+                    self.stop_timer();
+                    if !self.config.no_animation {
+                        self.win_animation = Some(std::time::Instant::now());
+                    }
+                    self.event_callbacks.emit(&GameEvent::GameWon {
+                        score: self.score,
+                        elapsed: self.elapsed(),
+                    });
+                }
+            }
         }
-        let dst_before = *dst_before.unwrap();
 
-        if !verify_under(self.game_suit, dst_before.card, src_card.card) {
-            return Err(MoveError::MoveDstNotValid);
+        res
+    }
+
+    /// remove a completed run from the top of a pile
+    fn do_move_complete_run(&mut self, pile: usize, suit: Suit) -> Result<(), MoveError> {
+        if self.completed_run_suit(pile) != Some(suit) {
+            return Err(MoveError::NoCompletedRun);
         }
 
-        dst_pile.push(src_card);
+        let pile = &mut self.tableau[pile - 1];
+        pile.truncate(pile.len() - 13);
+
+        // auto turn the last card to up
+        if let Some(last) = pile.last_mut() {
+            last.is_up = true;
+        }
 
-        self.stock.remove(src.card);
-        self.current_stock_pos -= 1;
+        self.foundations.push(suit);
+        self.score += COMPLETE_RUN_SCORE;
+        self.record_score();
 
         Ok(())
     }
 
-    /// move card from tableau to tableau
-    fn do_move_card_tableau_to_tableau(
-        &mut self,
+    /// move a card
+    ///
+    /// true if the card left on the top of the source pile was turned up
+    fn do_move_card(&mut self, src: CardPosition, dst: CardPosition) -> Result<bool, MoveError> {
+        if src.pile == 0 {
+            return Err(MoveError::MoveSrcNotExist);
+        }
+        if dst.pile == 0 {
+            return Err(MoveError::MoveDstNotValid);
+        }
+
+        self.do_move_card_tableau_to_tableau(src, dst)
+    }
+
+    /// check a card can be moved from tableau to tableau,
+    /// without moving it
+    fn check_move_card_tableau_to_tableau(
+        &self,
         src: CardPosition,
         dst: CardPosition,
     ) -> Result<(), MoveError> {
@@ -378,18 +1326,21 @@ impl Game {
         if src_pile.is_none() {
             return Err(MoveError::MoveSrcNotExist);
         }
-        let src_pile = src_pile.unwrap().clone();
+        let src_pile = src_pile.unwrap();
 
         let src_card = src_pile.get(src.card);
         if src_card.is_none() {
             return Err(MoveError::MoveSrcNotExist);
         }
         let src_card = *src_card.unwrap();
-        if !src_card.is_up {
+        if !src_card.is_movable() {
             return Err(MoveError::MoveSrcNotExist);
         }
+        if !is_sequential_group(self.game_suit, &src_pile[src.card..]) {
+            return Err(MoveError::MoveGroupNotSequential);
+        }
 
-        let dst_pile = self.tableau.get_mut(dst.pile - 1);
+        let dst_pile = self.tableau.get(dst.pile - 1);
         if dst_pile.is_none() {
             return Err(MoveError::MoveDstNotValid);
         }
@@ -399,34 +1350,31 @@ impl Game {
             return Err(MoveError::MoveDstNotValid);
         }
 
-        if src_card.card.rank == Rank::King && dst_pile.is_empty() {
-            let n = src_pile.len() - src.card;
-            src_pile
-                .into_iter()
-                .skip(src.card)
-                .for_each(|v| dst_pile.push(v));
-            let src_pile = self.tableau.get_mut(src.pile - 1).unwrap();
-            for _ in 0..n {
-                src_pile.pop();
-            }
-
-            // auto turn the last card to up
-            let last = src_pile.last_mut();
-            if let Some(last) = last {
-                last.is_up = true;
+        // any run can be moved onto an empty pile
+        if let Some(dst_before) = dst_pile.last() {
+            if !verify_rank_under(dst_before.card, src_card.card) {
+                return Err(MoveError::MoveCardNotUnder {
+                    card: src_card.card,
+                    onto: dst_before.card,
+                });
             }
-
-            return Ok(());
         }
 
-        let dst_before = dst_pile.last();
-        if dst_before.is_none() {
-            return Err(MoveError::MoveDstNotValid);
-        }
-        let dst_before = *dst_before.unwrap();
+        Ok(())
+    }
 
-        verify_under(self.game_suit, dst_before.card, src_card.card);
+    /// move card from tableau to tableau
+    ///
+    /// true if the card left on the top of the source pile was turned up
+    fn do_move_card_tableau_to_tableau(
+        &mut self,
+        src: CardPosition,
+        dst: CardPosition,
+    ) -> Result<bool, MoveError> {
+        self.check_move_card_tableau_to_tableau(src, dst)?;
 
+        let src_pile = self.tableau[src.pile - 1].clone();
+        let dst_pile = &mut self.tableau[dst.pile - 1];
         let n = src_pile.len() - src.card;
         src_pile
             .into_iter()
@@ -439,538 +1387,943 @@ impl Game {
 
         // auto turn the last card to up
         let last = src_pile.last_mut();
+        let mut flipped = false;
         if let Some(last) = last {
+            flipped = !last.is_up;
             last.is_up = true;
         }
 
-        Ok(())
+        Ok(flipped)
     }
 
-    /// draw one card from stock
-    fn do_move_draw_stock(&mut self) -> Result<(), MoveError> {
-        if self.current_stock_pos >= self.stock.len() {
-            return Err(MoveError::DrawEmptyStock);
+    /// deal one face up card from the stock on each tableau pile
+    fn do_move_deal_row(&mut self) -> Result<(), MoveError> {
+        if self.stock_exhausted() {
+            return Err(MoveError::DealEmptyStock);
         }
-
-        if self.current_stock_pos == 0 {
-            self.current_stock_pos = 1;
-            return Ok(());
+        if !self.config.relaxed_deal && self.tableau.iter().any(|pile| pile.is_empty()) {
+            return Err(MoveError::DealWithEmptyPile);
         }
 
-        self.current_stock_pos += 1;
+        for pile in self.tableau.iter_mut() {
+            let mut card = self.stock.pop().unwrap();
+            card.is_up = true;
+            pile.push(card);
+        }
 
         Ok(())
     }
 
-    /// recycle the stock
-    fn do_move_recycle_stock(&mut self) -> Result<(), MoveError> {
-        if self.current_stock_pos < self.stock.len() {
-            return Err(MoveError::RecycleNoneEmptyStock);
+    /// show a message to the player in the status bar
+    pub fn set_status(&mut self, message: String) {
+        self.status_message = Some((message, std::time::Instant::now()));
+    }
+
+    /// deal a row from the stock,
+    /// telling the player why if it is not possible
+    fn deal_row(&mut self) {
+        if let Err(err) = self.do_move(GameMove::DealRow) {
+            self.set_status(err.to_string());
         }
+    }
 
-        self.current_stock_pos = 0;
+    /// the number of rows left to deal from the stock
+    pub fn deals_remaining(&self) -> usize {
+        self.stock_cards_remaining() / DEAL_SIZE
+    }
 
-        Ok(())
+    /// the number of cards left in the stock
+    pub fn stock_cards_remaining(&self) -> usize {
+        self.stock.len()
     }
 
-    /// find possible move for a given card
-    ///
-    /// if no, return none
-    fn find_possible_move(&self, src: CardPosition) -> Option<GameMove> {
-        let card = if src.pile == 0 {
-            self.stock.get(src.card)
-        } else {
-            let pile = self.tableau.get(src.pile - 1);
-            if let Some(pile) = pile {
-                pile.get(src.card)
-            } else {
-                return None;
-            }
-        }?;
+    /// test if there is no full row left to deal
+    pub fn stock_exhausted(&self) -> bool {
+        self.stock_cards_remaining() < DEAL_SIZE
+    }
 
-        let before_visible = if src.pile == 0 {
-            None
-        } else if src.card < 1 {
-            Some(false)
-        } else {
-            let pile = self.tableau.get(src.pile - 1);
+    /// the next card to be dealt from the stock
+    pub fn stock_current_card(&self) -> Option<&GameCard> {
+        self.stock.last()
+    }
 
-            if let Some(pile) = pile {
-                let card = pile.get(src.card - 1);
-                if let Some(card) = card {
-                    Some(!card.is_up)
-                } else {
-                    Some(false)
-                }
+    /// the number of cards in the stock at the start of a game
+    pub fn stock_total_count(&self) -> usize {
+        STOCK_SIZE
+    }
+
+    /// the `before_visible` of a move starting from a card
+    fn before_visible(&self, src: CardPosition) -> Option<bool> {
+        if src.card < 1 {
+            return None;
+        }
+
+        let pile = self.tableau.get(src.pile - 1);
+        if let Some(pile) = pile {
+            let card = pile.get(src.card - 1);
+            if let Some(card) = card {
+                Some(!card.is_up)
             } else {
                 Some(false)
             }
-        };
+        } else {
+            Some(false)
+        }
+    }
 
-        for i in 0..10 {
-            if i + 1 == src.pile {
-                continue;
-            }
+    /// every move that can be made in the current state
+    pub fn legal_moves(&self) -> Vec<GameMove> {
+        let mut moves = Vec::new();
 
-            let pile = self.tableau.get(i);
-            if pile.is_none() {
-                continue;
+        for (i, pile) in self.tableau.iter().enumerate() {
+            for card in 0..pile.len() {
+                let src = CardPosition { pile: i + 1, card };
+
+                for (j, dst_pile) in self.tableau.iter().enumerate() {
+                    // moving a whole pile to an empty one changes nothing
+                    if i == j || (card == 0 && dst_pile.is_empty()) {
+                        continue;
+                    }
+                    let dst = CardPosition {
+                        pile: j + 1,
+                        card: dst_pile.len(),
+                    };
+                    if self.check_move_card_tableau_to_tableau(src, dst).is_err() {
+                        continue;
+                    }
+
+                    moves.push(GameMove::MoveCard {
+                        src,
+                        dst,
+                        before_visible: self.before_visible(src),
+                    });
+                }
             }
-            let pile = pile.unwrap();
+        }
 
-            if pile.is_empty() && card.card.rank == Rank::King {
-                return Some(GameMove::MoveCard {
-                    src,
-                    dst: CardPosition {
+        if !self.stock_exhausted()
+            && (self.config.relaxed_deal || self.tableau.iter().all(|pile| !pile.is_empty()))
+        {
+            moves.push(GameMove::DealRow);
+        }
+
+        moves
+    }
+
+    /// a copy of the game state,
+    /// without the ui state or the event callbacks
+    pub fn clone_for_analysis(&self) -> Game {
+        Game {
+            start_time: self.start_time,
+            end_time: self.end_time,
+            move_count: self.move_count,
+            tableau: self.tableau.clone(),
+            stock: self.stock.clone(),
+            score: self.score,
+            // not needed to look for moves
+            score_history: Vec::new(),
+            game_suit: self.game_suit,
+            seed: self.seed,
+            daily: self.daily,
+            initial_state: self.initial_state.clone(),
+            history_moves: self.history_moves.clone(),
+            annotate_moves: false,
+            redo_moves: self.redo_moves.clone(),
+            foundations: self.foundations.clone(),
+            selected: None,
+            source: None,
+            destinations: None,
+            last_click: None,
+            mouse_pos: None,
+            hint: None,
+            deadlocked: self.deadlocked,
+            timed_out: self.timed_out,
+            undo_count: self.undo_count,
+            best_run: self.best_run,
+            win_animation: None,
+            help_opened: None,
+            history_view: None,
+            autoplay: None,
+            confirm: None,
+            stats: None,
+            stats_recorded: true,
+            config: self.config.clone(),
+            status_message: None,
+            event_callbacks: EventCallbacks::default(),
+            layout: ScreenLayout::default(),
+            dirty_piles: HashSet::new(),
+            force_full_redraw: true,
+        }
+    }
+
+    /// the game state after a move,
+    /// none if the move is not valid
+    pub fn simulate_move(&self, game_move: GameMove) -> Option<Game> {
+        let mut game = self.clone_for_analysis();
+        game.do_move(game_move).ok()?;
+
+        Some(game)
+    }
+
+    /// find the best run to move from a pile,
+    /// starting at the given card or above it
+    ///
+    /// the given card is used when its run can move,
+    /// otherwise the start of the run above it,
+    /// the source of the returned move is the run actually moved
+    ///
+    /// if no, return none
+    fn find_possible_move(&self, src: CardPosition) -> Option<GameMove> {
+        let pile = self.tableau.get(src.pile.checked_sub(1)?)?;
+
+        let (_, _, src, dst) = (src.card..pile.len())
+            .filter(|&card| {
+                // splitting a run in its middle gains nothing
+                card == src.card
+                    || !verify_under(self.game_suit, pile[card - 1].card, pile[card].card)
+                    || !pile[card - 1].is_movable()
+            })
+            .map(|card| CardPosition {
+                pile: src.pile,
+                card,
+            })
+            .filter_map(|head| {
+                let (rank, dst) = self.best_destination(head)?;
+                Some((rank, std::cmp::Reverse(head.card), head, dst))
+            })
+            .max_by_key(|(rank, longest, _, _)| (*rank, *longest))?;
+
+        Some(GameMove::MoveCard {
+            src,
+            dst,
+            before_visible: self.before_visible(src),
+        })
+    }
+
+    /// find the best place to move a run to
+    ///
+    /// building on the same suit is preferred to building on another suit,
+    /// and an empty pile is the last resort,
+    /// the leftmost pile wins a tie
+    ///
+    /// if no, return none
+    fn best_destination(&self, src: CardPosition) -> Option<(DestinationRank, CardPosition)> {
+        let pile = self.tableau.get(src.pile.checked_sub(1)?)?;
+        let card = pile.get(src.card)?;
+        if !card.is_movable() || !is_sequential_group(self.game_suit, &pile[src.card..]) {
+            return None;
+        }
+
+        self.tableau
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i + 1 != src.pile)
+            .filter_map(|(i, pile)| {
+                let rank = match pile.last() {
+                    // a run already at the bottom of its pile
+                    // gains nothing from an empty pile
+                    None if src.card > 0 => DestinationRank::EmptyPile,
+                    None => return None,
+                    Some(last) if !verify_rank_under(last.card, card.card) => return None,
+                    Some(last) if last.card.suit == card.card.suit => DestinationRank::SameSuit,
+                    Some(_) => DestinationRank::OtherSuit,
+                };
+
+                Some((
+                    rank,
+                    CardPosition {
                         pile: i + 1,
-                        card: 0,
+                        card: pile.len(),
                     },
-                    before_visible,
-                });
-            }
+                ))
+            })
+            .max_by_key(|(rank, dst)| (*rank, std::cmp::Reverse(dst.pile)))
+    }
 
-            let last_card = pile.last();
-            if last_card.is_none() {
-                continue;
-            }
-            let last_card = last_card.unwrap();
+    /// the other piles the cards from a position can be moved onto,
+    /// checked as a move would be
+    pub fn legal_destinations(&self, src: CardPosition) -> Vec<usize> {
+        (1..=self.tableau.len())
+            .filter(|&pile| pile != src.pile)
+            .filter(|&pile| {
+                let dst = CardPosition {
+                    pile,
+                    card: self.tableau[pile - 1].len(),
+                };
+                self.check_move_card_tableau_to_tableau(src, dst).is_ok()
+            })
+            .collect()
+    }
 
-            if !verify_under(self.game_suit, last_card.card, card.card) {
-                continue;
-            }
+    /// search the piles the picked cards can go to,
+    /// only again when the picked card or the tableau changes
+    pub fn refresh_destinations(&mut self) {
+        let src = match self.source {
+            Some(src) => src,
+            None => return,
+        };
 
-            return Some(GameMove::MoveCard {
-                src,
-                dst: CardPosition {
-                    pile: i + 1,
-                    card: pile.len(),
-                },
-                before_visible,
-            });
+        let history_len = self.history_moves.len();
+        let is_stale = !matches!(
+            &self.destinations,
+            Some((cached, len, _)) if *cached == src && *len == history_len
+        );
+        if is_stale {
+            let destinations = self.legal_destinations(src);
+            self.destinations = Some((src, history_len, destinations));
         }
+    }
 
-        None
+    /// the piles the picked cards can go to,
+    /// as last searched by `refresh_destinations`
+    pub fn source_destinations(&self) -> &[usize] {
+        match (&self.destinations, self.source) {
+            (Some((cached, len, destinations)), Some(src))
+                if *cached == src && *len == self.history_moves.len() =>
+            {
+                destinations
+            }
+            _ => &[],
+        }
+    }
+
+    /// the tableau pile at a point of the screen
+    fn pile_at(&self, x: u16, y: u16) -> Option<usize> {
+        (0..self.layout.piles.len().min(10))
+            .find(|&i| test_point_in_rect(x, y, self.layout.piles[i]))
+            .map(|i| i + 1)
+    }
+
+    /// the card a click at a point of the screen would pick,
+    /// as drawn by the last render
+    pub fn card_at(&self, x: u16, y: u16) -> Option<CardPosition> {
+        let pile = self.pile_at(x, y)?;
+        let card = (0..self.tableau[pile - 1].len()).position(|card| {
+            self.tableau[pile - 1][card].is_movable()
+                && self
+                    .layout
+                    .card_area(CardPosition { pile, card })
+                    .is_some_and(|area| test_point_in_rect(x, y, area))
+        })?;
+
+        Some(CardPosition { pile, card })
+    }
+
+    /// follow the mouse,
+    /// true if the card under it changed and the ui must be drawn again
+    fn handle_mouse_move(&mut self, event: crossterm::event::MouseEvent) -> bool {
+        let hovered = self.mouse_pos.and_then(|(x, y)| self.card_at(x, y));
+        self.mouse_pos = Some((event.column, event.row));
+
+        hovered != self.card_at(event.column, event.row)
     }
 
     /// the function to handle crossterm click event
+    ///
+    /// a left click picks a card, a second one drops it on a pile,
+    /// a double or right click moves a card to any possible place
     fn handle_click(&mut self, event: crossterm::event::MouseEvent) -> crossterm::Result<()> {
         let button = match event.kind {
             MouseEventKind::Down(button) => button,
             _ => return Ok(()),
         };
 
-        match button {
-            crossterm::event::MouseButton::Left => {}
-            _ => return Ok(()),
-        }
-
         let x = event.column;
         let y = event.row;
 
-        if test_point_in_rect(x, y, self.stock_chunks[0]) {
-            let card = self.stock.get(self.current_stock_pos - 1);
-            if card.is_none() {
-                return Ok(());
-            }
-            let card = card.unwrap();
+        if let Some(stock) = self.layout.stock {
+            if test_point_in_rect(x, y, stock) {
+                self.source = None;
+                self.deal_row();
 
-            if card.pos.is_none() {
                 return Ok(());
             }
+        }
 
-            if !test_point_in_rect(x, y, card.pos.unwrap()) {
+        let pile = match self.pile_at(x, y) {
+            Some(pile) => pile,
+            None => {
+                // clicking elsewhere clears the picked card
+                self.source = None;
                 return Ok(());
             }
+        };
+        let card = self.card_at(x, y);
 
-            let game_move = self.find_possible_move(CardPosition {
-                pile: 0,
-                card: self.current_stock_pos - 1,
-            });
-            if let Some(game_move) = game_move {
-                let _ = self.do_move(game_move);
+        let now = std::time::Instant::now();
+        let last_click = self.last_click.take();
+        let is_double_click = match (last_click, card) {
+            (Some((time, last)), Some(card)) => {
+                last == card && now.duration_since(time) < DOUBLE_CLICK_INTERVAL
             }
+            _ => false,
+        };
 
-            return Ok(());
-        }
-
-        if test_point_in_rect(x, y, self.stock_ui_pos.unwrap()) {
-            if self.current_stock_pos >= self.stock.len() {
-                let _ = self.do_move(GameMove::RecycleStock);
-            } else {
-                let _ = self.do_move(GameMove::DrawStock);
+        match button {
+            crossterm::event::MouseButton::Left if is_double_click => {
+                self.source = None;
+                self.auto_move(card.unwrap());
             }
-
-            return Ok(());
-        }
-
-        for i in 0..10 {
-            if test_point_in_rect(x, y, self.tableau_chunks[i]) {
-                for j in 0..self.tableau[i].len() {
-                    let c = self.tableau[i][j];
-                    if let Some(pos) = c.pos {
-                        if c.is_up && test_point_in_rect(x, y, pos) {
-                            let game_move = self.find_possible_move(CardPosition {
-                                pile: i + 1,
-                                card: j,
-                            });
-                            if let Some(game_move) = game_move {
-                                let _ = self.do_move(game_move);
-                            }
-                            return Ok(());
-                        }
+            crossterm::event::MouseButton::Left => match (self.source, card) {
+                (Some(source), _) if source.pile == pile => {
+                    self.source = None;
+                    if let Some(card) = card {
+                        self.last_click = Some((now, card));
                     }
                 }
-
-                return Ok(());
+                (Some(_), _) => self.move_source_to(pile),
+                (None, Some(card)) => {
+                    self.selected = Some(card);
+                    self.pick_source();
+                    self.last_click = Some((now, card));
+                }
+                (None, None) => {}
+            },
+            crossterm::event::MouseButton::Right => {
+                self.source = None;
+                if let Some(card) = card {
+                    self.auto_move(card);
+                }
             }
+            _ => {}
         }
 
         Ok(())
     }
 
-    /// create a new game, with a given game suit
-    pub fn new(game_suit: GameSuitNumber) -> Self {
-        let mut rng = rand::thread_rng();
-
-        let mut all_cards = Vec::with_capacity(104);
-        for i in 1..14 {
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Clubs,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Clubs,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
-        }
-        for i in 1..14 {
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Diamonds,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Diamonds,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
+    /// highlight the best move,
+    /// or tell the player there is none
+    fn show_hint(&mut self) {
+        match hint::best_move(self) {
+            Some(game_move) => self.hint = Some((game_move, std::time::Instant::now())),
+            None => {
+                self.set_status(String::from("No moves, deal from stock or undo"));
+            }
         }
-        for i in 1..14 {
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Hearts,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Hearts,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
+    }
+
+    /// make the next autoplay move once it is time to
+    ///
+    /// the autoplay stops once the game is won,
+    /// there is no move left or a state comes back
+    fn autoplay_step(&mut self) {
+        let autoplay = match &self.autoplay {
+            Some(autoplay) => autoplay,
+            None => return,
+        };
+        if autoplay.last_move.elapsed() < AUTOPLAY_INTERVAL {
+            return;
         }
-        for i in 1..14 {
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Spades,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
-            all_cards.push(GameCard {
-                card: Card {
-                    suit: Suit::Spades,
-                    rank: Rank::from(i),
-                },
-                is_up: false,
-                pos: None,
-            });
+        if self.test_win() {
+            self.autoplay = None;
+            return;
         }
 
-        let mut tableau = Vec::with_capacity(10);
-        for _ in 0..4 {
-            let mut pile = Vec::with_capacity(6);
+        let game_move = match hint::autoplay_move(self) {
+            Some(game_move) => game_move,
+            None => {
+                self.autoplay = None;
+                self.set_status(String::from("Autoplay stopped, no move left"));
+                return;
+            }
+        };
+        if self.do_move(game_move).is_err() {
+            self.autoplay = None;
+            return;
+        }
 
-            for _ in 0..5 {
-                let num = rng.gen_range(0..all_cards.len());
-                let card = all_cards.swap_remove(num);
-                pile.push(card);
+        let state_hash = self.state_hash();
+        if let Some(autoplay) = &mut self.autoplay {
+            autoplay.last_move = std::time::Instant::now();
+            if !autoplay.seen.insert(state_hash) {
+                self.autoplay = None;
+                self.set_status(String::from("Autoplay stopped, no progress"));
             }
-            let num = rng.gen_range(0..all_cards.len());
-            let mut card = all_cards.swap_remove(num);
-            card.is_up = true;
-            pile.push(card);
-            tableau.push(pile);
-        }
-        for _ in 0..6 {
-            let mut pile = Vec::with_capacity(6);
-
-            for _ in 0..4 {
-                let num = rng.gen_range(0..all_cards.len());
-                let card = all_cards.swap_remove(num);
-                pile.push(card);
-            }
-            let num = rng.gen_range(0..all_cards.len());
-            let mut card = all_cards.swap_remove(num);
-            card.is_up = true;
-            pile.push(card);
-            tableau.push(pile);
-        }
-
-        let mut stock = Vec::with_capacity(50);
-        for _ in 0..50 {
-            let num = rng.gen_range(0..all_cards.len());
-            let mut card = all_cards.swap_remove(num);
-            card.is_up = true;
-            stock.push(card);
         }
+    }
 
-        Game {
-            start_time: None,
-            tableau,
-            stock,
-            current_stock_pos: 0,
-            score: 0,
-            game_suit,
-            history_moves: Vec::new(),
-            stock_ui_pos: None,
-            stock_chunks: Vec::new(),
-            tableau_chunks: Vec::new(),
+    /// move a card to a possible place, if any
+    fn auto_move(&mut self, src: CardPosition) {
+        let game_move = self.find_possible_move(src);
+        if let Some(game_move) = game_move {
+            let _ = self.do_move(game_move);
         }
     }
 
-    /// reset all the ui pos of card before render
-    fn render_reset_ui_pos(&mut self) {
-        self.stock_ui_pos = None;
+    /// move the keyboard cursor
+    ///
+    /// left and right jump to the top card of the neighbour pile,
+    /// up and down walk through the face up cards of the current pile
+    pub fn move_cursor(&mut self, cursor_move: CursorMove) {
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => {
+                self.selected = Some(self.top_card_position(1));
+                return;
+            }
+        };
+        // the pile might have shrunk since the cursor was placed
+        let selected = if selected.card < self.tableau[selected.pile - 1].len() {
+            selected
+        } else {
+            self.top_card_position(selected.pile)
+        };
 
-        self.stock.iter_mut().for_each(|c| {
-            c.pos = None;
+        self.selected = Some(match cursor_move {
+            CursorMove::Left => {
+                let pile = if selected.pile <= 1 {
+                    10
+                } else {
+                    selected.pile - 1
+                };
+                self.top_card_position(pile)
+            }
+            CursorMove::Right => {
+                let pile = if selected.pile >= 10 {
+                    1
+                } else {
+                    selected.pile + 1
+                };
+                self.top_card_position(pile)
+            }
+            CursorMove::Up => {
+                let pile = &self.tableau[selected.pile - 1];
+                if selected.card > 0 && pile[selected.card - 1].is_movable() {
+                    CardPosition {
+                        pile: selected.pile,
+                        card: selected.card - 1,
+                    }
+                } else {
+                    selected
+                }
+            }
+            CursorMove::Down => {
+                let pile = &self.tableau[selected.pile - 1];
+                if selected.card + 1 < pile.len() {
+                    CardPosition {
+                        pile: selected.pile,
+                        card: selected.card + 1,
+                    }
+                } else {
+                    selected
+                }
+            }
         });
+    }
 
-        self.tableau.iter_mut().for_each(|pile| {
-            pile.iter_mut().for_each(|c| {
-                c.pos = None;
-            })
-        });
+    /// the position of the top card of a tableau pile
+    ///
+    /// card 0 for an empty pile
+    fn top_card_position(&self, pile: usize) -> CardPosition {
+        CardPosition {
+            pile,
+            card: self.tableau[pile - 1].len().saturating_sub(1),
+        }
     }
 
-    /// Render the game ui
-    fn render_all(&mut self) -> io::Result<()> {
-        self.render_reset_ui_pos();
+    /// move the card under the keyboard cursor to a possible place
+    fn move_selected(&mut self) {
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => return,
+        };
 
-        let mut terminal = TERMINAL.lock().unwrap();
+        self.auto_move(selected);
 
-        let mut stock_chunks = Vec::new();
-        let mut tableau_chunks = Vec::new();
+        // the selected card might have moved away
+        self.selected = Some(self.top_card_position(selected.pile));
+    }
 
-        terminal.draw(|f| {
-            let size = f.size();
+    /// put the keyboard cursor on the top card of a pile
+    fn select_pile(&mut self, pile: usize) {
+        self.selected = Some(self.top_card_position(pile));
+    }
 
-            let outer_block = Block::default().title("Spider").borders(Borders::ALL);
-            let new_size = outer_block.inner(size);
-            f.render_widget(outer_block, size);
-            let size = new_size;
+    /// pick the card under the keyboard cursor to be moved
+    fn pick_source(&mut self) {
+        let selected = match self.selected {
+            Some(selected) => selected,
+            None => return,
+        };
 
-            let stock_tableau_chunks = Layout::default()
-                .direction(tui::layout::Direction::Vertical)
-                .margin(1)
-                .constraints([Constraint::Length(10), Constraint::Length(50)].as_ref())
-                .split(size);
+        let is_up = self.tableau[selected.pile - 1]
+            .get(selected.card)
+            .is_some_and(GameCard::is_movable);
+        if !is_up {
+            return;
+        }
 
-            stock_chunks = Layout::default()
-                .direction(tui::layout::Direction::Horizontal)
-                .margin(1)
-                .constraints([Constraint::Length(50), Constraint::Length(10)].as_ref())
-                .split(stock_tableau_chunks[0]);
+        self.source = Some(selected);
+        self.set_status(String::from("Pick a pile to move to, Esc to cancel"));
+    }
 
-            let mut tableau_constraint = Vec::new();
-            for _ in 0..10 {
-                tableau_constraint.push(Constraint::Length(10));
-            }
-            tableau_chunks = Layout::default()
-                .direction(tui::layout::Direction::Horizontal)
-                .margin(1)
-                .constraints(tableau_constraint.clone())
-                .split(stock_tableau_chunks[1]);
+    /// move the picked cards onto a pile,
+    /// telling the player why if it is not possible
+    fn move_source_to(&mut self, pile: usize) {
+        let src = match self.source.take() {
+            Some(src) => src,
+            None => return,
+        };
 
-            self.render_left_stock(stock_chunks[1], f);
-            self.render_visible_stock(stock_chunks[0], f);
-            #[allow(clippy::needless_range_loop)]
-            for i in 0..10 {
-                self.render_pile(i, tableau_chunks[i], f);
-            }
-        })?;
-        drop(terminal);
+        let game_move = GameMove::MoveCard {
+            src,
+            dst: CardPosition {
+                pile,
+                card: self.tableau[pile - 1].len(),
+            },
+            before_visible: self.before_visible(src),
+        };
+        match self.do_move(game_move) {
+            Ok(_) => self.select_pile(pile),
+            Err(err) => self.set_status(err.to_string()),
+        }
+    }
 
-        self.stock_chunks = stock_chunks;
-        self.tableau_chunks = tableau_chunks;
+    /// create a new game, with a given game suit
+    pub fn new(game_suit: GameSuitNumber, config: GameConfig) -> Self {
+        // a random seed is shown to the player,
+        // so any deal could be played again
+        let daily = config.daily.then(daily::today);
+        let seed = match daily {
+            Some(day) => daily::seed(day, game_suit),
+            None => config.seed.unwrap_or_else(rand::random),
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
 
-        Ok(())
-    }
+        let mut deck = Deck::spider(game_suit);
+        deck.shuffle(&mut rng);
+        let mut draw = || GameCard {
+            card: deck.draw().unwrap(),
+            is_up: false,
+        };
 
-    /// render the left stock ui
-    fn render_left_stock(&mut self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
-        let mut area = area;
-        if area.height > 8 {
-            area.height = 8;
+        // the first 4 piles get 6 cards, the others 5,
+        // only the top card of each pile is face up
+        let mut tableau = Vec::with_capacity(10);
+        for i in 0..10 {
+            let size = if i < 4 { 6 } else { 5 };
+            let mut pile: Vec<GameCard> = (0..size).map(|_| draw()).collect();
+            pile.last_mut().unwrap().is_up = true;
+            tableau.push(pile);
         }
-        if area.width > 8 {
-            area.width = 8;
+
+        let mut stock: Vec<GameCard> = (0..STOCK_SIZE).map(|_| draw()).collect();
+
+        // the shuffled deal is kept if no winnable one is found
+        if config.winnable {
+            if let Some(deal) = winnable::deal(game_suit, seed) {
+                tableau = deal.tableau;
+                stock = deal.stock;
+            }
         }
 
-        let stock_block = Block::default().title("Stock").borders(Borders::ALL);
-        let inner = stock_block.inner(area);
-        let chunks = Layout::default()
-            .direction(tui::layout::Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(50),
-                    Constraint::Percentage(25),
-                ]
-                .as_ref(),
-            )
-            .margin(0)
-            .split(inner);
-        let left_block = Block::default()
-            .title(format!("{}", self.stock.len() - self.current_stock_pos))
-            .borders(Borders::empty());
+        debug_assert!(game_suit.verify_deal_is_valid(&tableau, &stock));
 
-        f.render_widget(stock_block, area);
-        f.render_widget(left_block, chunks[1]);
+        let mut game = Game::from_deal(game_suit, seed, Deal { tableau, stock }, config);
+        game.daily = daily;
 
-        self.stock_ui_pos = Some(area);
+        game
     }
 
-    /// render the tableau
-    fn render_pile(&mut self, pile: usize, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
-        let pile = self.tableau.get_mut(pile).unwrap();
+    /// create a game from the cards of a deal,
+    /// the seed only shown to the player
+    pub fn from_deal(game_suit: GameSuitNumber, seed: u64, deal: Deal, config: GameConfig) -> Self {
+        let best_run = deal
+            .tableau
+            .iter()
+            .map(|pile| longest_run(pile))
+            .max()
+            .unwrap_or(0);
 
-        let n = pile.len();
+        Game {
+            start_time: None,
+            end_time: None,
+            move_count: 0,
+            tableau: deal.tableau.clone(),
+            stock: deal.stock.clone(),
+            score: 0,
+            score_history: vec![(0, 0)],
+            game_suit,
+            seed,
+            daily: None,
+            initial_state: deal,
+            history_moves: Vec::new(),
+            annotate_moves: true,
+            redo_moves: Vec::new(),
+            foundations: Vec::new(),
+            selected: None,
+            source: None,
+            destinations: None,
+            last_click: None,
+            mouse_pos: None,
+            hint: None,
+            deadlocked: false,
+            timed_out: false,
+            undo_count: 0,
+            best_run,
+            win_animation: None,
+            help_opened: None,
+            history_view: None,
+            autoplay: None,
+            confirm: None,
+            stats: None,
+            stats_recorded: false,
+            config,
+            status_message: None,
+            event_callbacks: EventCallbacks::default(),
+            layout: ScreenLayout::default(),
+            dirty_piles: HashSet::new(),
+            force_full_redraw: true,
+        }
+    }
 
-        if n == 0 {
-            let area = Rect::new(area.x, area.y, 8, 8);
-            let card_block = Block::default().title("Empty").borders(Borders::ALL);
+    /// a text representation of the game
+    ///
+    /// face up cards are shown in the card notation, e.g. `KS`,
+    /// face down cards are shown as `##`
+    pub fn render_to_string(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str(&format!("Seed: {}\n", self.seed));
+        output.push_str(&format!(
+            "Stock: {} cards, {} deals left\n",
+            self.stock_cards_remaining(),
+            self.deals_remaining()
+        ));
+
+        output.push_str(&format!(
+            "Runs: {}/{}\n",
+            self.completed_run_count(),
+            RUNS_TO_WIN
+        ));
+
+        for (i, pile) in self.tableau.iter().enumerate() {
+            output.push_str(&format!("{:>2}:", i + 1));
+            for card in pile {
+                if card.is_up {
+                    output.push_str(&format!(" {}", card.card.to_notation()));
+                } else {
+                    output.push_str(" ##");
+                }
+            }
+            output.push('\n');
+        }
 
-            f.render_widget(card_block, area);
+        output
+    }
 
-            return;
+    /// every card of the tableau, one pile per line
+    ///
+    /// face up cards are shown in the card notation, e.g. `KS`,
+    /// face down cards in lower case, e.g. `ks`
+    pub fn debug_tableau_string(&self) -> String {
+        let mut output = String::new();
+
+        for (i, pile) in self.tableau.iter().enumerate() {
+            output.push_str(&format!("{:>2}:", i + 1));
+            for card in pile {
+                let notation = card.card.to_notation();
+                if card.is_up {
+                    output.push_str(&format!(" {}", notation));
+                } else {
+                    output.push_str(&format!(" {}", notation.to_lowercase()));
+                }
+            }
+            output.push('\n');
         }
 
-        let mut area = Rect::new(area.x, area.y + (2 * (n - 1)) as u16, 8, 8);
-        for i in 0..n {
-            let card = n - i - 1;
-            let card = pile.get_mut(card);
-            if card.is_none() {
-                continue;
-            }
-            let card = card.unwrap();
+        output
+    }
 
-            let title = if card.is_up {
-                card.pos = Some(area);
-                card.card.to_string()
-            } else {
-                String::from("")
-            };
+    /// test if the game can be left at once,
+    /// otherwise the player is asked to confirm first
+    ///
+    /// a game without any move made has nothing to lose
+    fn confirm_quit(&mut self) -> bool {
+        if self.move_count == 0 {
+            self.stop_timer();
+            return true;
+        }
 
-            let mut card_block = if i == 0 {
-                Block::default().title(title).borders(Borders::ALL)
-            } else {
-                Block::default()
-                    .title(title)
-                    .borders(Borders::LEFT)
-                    .borders(Borders::RIGHT)
-                    .borders(Borders::TOP)
-            };
+        self.confirm = Some(Confirm::Quit);
+        false
+    }
 
-            if card.is_up {
-                card_block = card_block.style(Style::default().fg(card.card.suit.color()));
-            }
+    /// draw the game, keeping where the cards were drawn for the clicks
+    fn draw(&mut self, renderer: &mut dyn Renderer) -> std::io::Result<()> {
+        self.refresh_destinations();
+        self.layout = renderer.render(self)?;
+        self.clear_dirty();
 
-            f.render_widget(card_block, area);
+        Ok(())
+    }
 
-            if i == 0 {
-                area.height = 2;
+    /// cascade the completed runs until the animation is over
+    /// or any key is pressed, the key is not used otherwise
+    fn play_win_animation(&mut self, renderer: &mut dyn Renderer) -> crossterm::Result<()> {
+        while self
+            .win_animation
+            .is_some_and(|start| start.elapsed() < WIN_ANIMATION_DURATION)
+        {
+            self.draw(renderer)?;
+            if crossterm::event::poll(WIN_ANIMATION_FRAME)? {
+                if let crossterm::event::Event::Key(_) = crossterm::event::read()? {
+                    break;
+                }
             }
-            area.y -= 2;
         }
-    }
+        self.win_animation = None;
 
-    /// render the stock
-    fn render_visible_stock(&mut self, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
-        let mut n = self.current_stock_pos;
-        if n > 4 {
-            n = 4;
-        }
+        Ok(())
+    }
 
-        let mut area = Rect::new(area.x + area.width - 10, area.y, 8, 8);
+    /// run the game
+    pub fn run_game(&mut self, renderer: &mut dyn Renderer) -> crossterm::Result<()> {
+        let mut redraw = true;
+        loop {
+            self.check_time_out();
+            self.check_deadlock();
+            if self.test_win() {
+                self.record_result();
+                self.play_win_animation(renderer)?;
+            }
+            if redraw {
+                self.draw(renderer)?;
+            }
+            redraw = true;
+
+            // wake up every so often to tick the clock
+            if !crossterm::event::poll(TICK_INTERVAL)? {
+                self.autoplay_step();
+                if self
+                    .hint
+                    .is_some_and(|(_, time)| time.elapsed() >= HINT_DURATION)
+                {
+                    self.hint = None;
+                }
+                if self
+                    .status_message
+                    .as_ref()
+                    .is_some_and(|(_, time)| time.elapsed() >= STATUS_DURATION)
+                {
+                    self.status_message = None;
+                }
+                continue;
+            }
+            let event = crossterm::event::read()?;
+            // the layout follows the new size on the next render,
+            // without closing any popup
+            if let crossterm::event::Event::Resize(..) = event {
+                self.force_full_redraw = true;
+                continue;
+            }
+            // moving the mouse only draws again when the hovered card changes
+            if let crossterm::event::Event::Mouse(mouse) = event {
+                if matches!(mouse.kind, MouseEventKind::Moved | MouseEventKind::Drag(_)) {
+                    redraw = self.handle_mouse_move(mouse);
+                    continue;
+                }
+            }
+            self.status_message = None;
+            self.hint = None;
 
-        for i in 0..n {
-            let card = self.current_stock_pos - i - 1;
-            let card = self.stock.get_mut(card);
-            if card.is_none() {
+            // any key stops the autoplay
+            if self.autoplay.take().is_some() {
+                self.set_status(String::from("Autoplay stopped"));
                 continue;
             }
-            let card = card.unwrap();
 
-            if i == 0 {
-                card.pos = Some(area);
+            // only the choices of the time up popup are available
+            if self.timed_out {
+                if let crossterm::event::Event::Key(key) = event {
+                    match key.code {
+                        event::KeyCode::Char('r') => self.restart(),
+                        event::KeyCode::Char('n') => self.new_game(),
+                        event::KeyCode::Esc | event::KeyCode::Char('q') => return Ok(()),
+                        _ => {}
+                    }
+                }
+                continue;
             }
 
-            let card_block = if i == 0 {
-                Block::default()
-                    .title(card.card.to_string())
-                    .borders(Borders::all())
-            } else {
-                Block::default()
-                    .title(card.card.to_string())
-                    .borders(Borders::TOP)
-                    .borders(Borders::BOTTOM)
-                    .borders(Borders::LEFT)
-            };
-            let card_block = card_block.style(Style::default().fg(card.card.suit.color()));
+            // only the choices of the dead end popup are available
+            if self.deadlocked {
+                if let crossterm::event::Event::Key(key) = event {
+                    match key.code {
+                        event::KeyCode::Char('u') => {
+                            self.undo_by_player();
+                            self.deadlocked = false;
+                        }
+                        event::KeyCode::Char('r') => self.restart(),
+                        event::KeyCode::Char('n') => self.new_game(),
+                        event::KeyCode::Esc | event::KeyCode::Char('q') => {
+                            self.stop_timer();
+                            return Ok(());
+                        }
+                        _ => {}
+                    }
+                }
+                continue;
+            }
 
-            f.render_widget(card_block, area);
+            // any key closes the statistics
+            if self.stats.take().is_some() {
+                continue;
+            }
 
-            if i == 0 {
-                area.width = 6;
+            if let Some(history_view) = &mut self.history_view {
+                if let crossterm::event::Event::Key(key) = event {
+                    let cursor_move = self
+                        .config
+                        .key_binding_mode
+                        .cursor_move_from_keycode(key.code);
+                    match (key.code, cursor_move) {
+                        (event::KeyCode::Esc, _) => self.history_view = None,
+                        (_, Some(CursorMove::Up)) => {
+                            history_view.selected = history_view.selected.saturating_sub(1)
+                        }
+                        (_, Some(CursorMove::Down)) => {
+                            history_view.selected = (history_view.selected + 1)
+                                .min(history_view.lines.len().saturating_sub(1))
+                        }
+                        _ => {}
+                    }
+                }
+                continue;
             }
-            area.x -= 6;
-        }
 
-        let card_block = Block::default()
-            .title(format!("{}", self.current_stock_pos - n))
-            .borders(Borders::TOP)
-            .borders(Borders::BOTTOM)
-            .borders(Borders::LEFT);
-        f.render_widget(card_block, area);
-    }
+            // any key closes the help
+            if self.help_opened.is_some() {
+                self.close_help();
+                continue;
+            }
 
-    /// run the game
-    pub fn run_game(&mut self) -> crossterm::Result<()> {
-        loop {
-            self.render_all()?;
+            // only the choices of the win screen are available
+            if self.test_win() {
+                if let crossterm::event::Event::Key(key) = event {
+                    match key.code {
+                        event::KeyCode::Char('n') => self.do_confirmed(Confirm::NewGame)?,
+                        event::KeyCode::Char('N') => self.new_game(),
+                        event::KeyCode::Char('u') => self.undo_by_player(),
+                        event::KeyCode::Esc | event::KeyCode::Char('q') => return Ok(()),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
 
-            let event = crossterm::event::read()?;
+            // anything but yes cancels the action
+            if let Some(confirm) = self.confirm.take() {
+                if let crossterm::event::Event::Key(key) = event {
+                    if key.code == event::KeyCode::Char('y') {
+                        self.do_confirmed(confirm)?;
+                        if confirm == Confirm::Quit {
+                            return Ok(());
+                        }
+                    }
+                }
+                continue;
+            }
 
             let key = match event {
                 crossterm::event::Event::Key(c) => c,
@@ -981,25 +2334,1717 @@ impl Game {
                 _ => continue,
             };
 
+            let cursor_move = self
+                .config
+                .key_binding_mode
+                .cursor_move_from_keycode(key.code);
+            if let Some(cursor_move) = cursor_move {
+                self.move_cursor(cursor_move);
+                continue;
+            }
+
             let c = match key.code {
-                event::KeyCode::Esc => return Ok(()),
+                // cancel the picked card first
+                event::KeyCode::Esc if self.source.is_some() => {
+                    self.source = None;
+                    continue;
+                }
+                event::KeyCode::Esc => {
+                    if self.confirm_quit() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                event::KeyCode::Enter => {
+                    match (self.source, self.selected) {
+                        (Some(_), Some(selected)) => self.move_source_to(selected.pile),
+                        _ => self.pick_source(),
+                    }
+                    continue;
+                }
                 event::KeyCode::Char(c) => c,
                 _ => continue,
             };
 
-            match c {
-                'q' => return Ok(()),
-                'u' => self.undo_once(),
-                's' => {
-                    let _ = self.do_move(GameMove::DrawStock);
+            // 1 to 9 for the first piles, 0 for the tenth
+            if let Some(digit) = c.to_digit(10) {
+                let pile = if digit == 0 { 10 } else { digit as usize };
+                if self.source.is_some() {
+                    self.move_source_to(pile);
+                } else {
+                    self.select_pile(pile);
                 }
-                'w' => {
-                    if self.test_win() {
+                continue;
+            }
+
+            let action = match key_action(self.config.key_binding_mode, c) {
+                Some(action) => action,
+                None => continue,
+            };
+            match action {
+                KeyAction::Quit => {
+                    if self.confirm_quit() {
                         return Ok(());
                     }
                 }
-                _ => continue,
+                KeyAction::MoveSelected => self.move_selected(),
+                KeyAction::Undo => self.undo_by_player(),
+                KeyAction::Redo => self.redo_once(),
+                KeyAction::Restart => self.confirm = Some(Confirm::Restart),
+                KeyAction::Stats => self.stats = Some(Stats::load()),
+                KeyAction::NewGame => self.confirm = Some(Confirm::NewGame),
+                KeyAction::NewGameSameSuit => self.confirm = Some(Confirm::NewGameSameSuit),
+                KeyAction::Hint => self.show_hint(),
+                KeyAction::DealRow => self.deal_row(),
+                KeyAction::Save => match save::save(self) {
+                    Ok(_) => self.set_status(String::from("Game saved")),
+                    Err(err) => self.set_status(format!("Can't save the game: {}", err)),
+                },
+                KeyAction::Help => self.help_opened = Some(now_millis()),
+                KeyAction::AutoPlay => {
+                    self.autoplay = Some(AutoPlay {
+                        last_move: std::time::Instant::now(),
+                        seen: HashSet::from([self.state_hash()]),
+                    });
+                    self.set_status(String::from("Autoplay on, any key to stop"));
+                }
+                KeyAction::Export => match replay::export(self) {
+                    Ok(path) => self.set_status(format!("Replay exported to {}", path.display())),
+                    Err(err) => self.set_status(format!("Can't export the replay: {}", err)),
+                },
+                KeyAction::History => {
+                    self.history_view = Some(HistoryView {
+                        lines: self.history_descriptions(),
+                        selected: 0,
+                    })
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::test_util::{face_up_flags, game_from, move_to};
+
+    #[test]
+    fn deal_follows_the_game_suit() {
+        for game_suit in [
+            GameSuitNumber::One,
+            GameSuitNumber::Two,
+            GameSuitNumber::Four,
+        ] {
+            let config = GameConfig {
+                seed: Some(7),
+                ..GameConfig::default()
+            };
+            let game = Game::new(game_suit, config);
+            let suits: HashSet<Suit> = game
+                .tableau
+                .iter()
+                .flatten()
+                .chain(&game.stock)
+                .map(|card| card.card.suit)
+                .collect();
+
+            assert_eq!(
+                game.tableau.iter().flatten().count() + game.stock.len(),
+                104
+            );
+            assert_eq!(suits, game_suit.card_suits_allowed().into_iter().collect());
+        }
+    }
+
+    #[test]
+    fn any_suit_goes_on_the_next_higher_rank() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "5H"]);
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        assert_eq!(
+            game.debug_tableau_string().lines().next(),
+            Some(" 1: 6S 5H")
+        );
+    }
+
+    #[test]
+    fn a_card_only_goes_on_the_next_higher_rank() {
+        let mut game = game_from(GameSuitNumber::One, &["7S", "5S"]);
+
+        let res = game.do_move(move_to(&game, 2, 0, 1));
+        assert!(matches!(res, Err(MoveError::MoveCardNotUnder { .. })));
+    }
+
+    #[test]
+    fn a_run_of_mixed_suits_does_not_move_together() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S 5H", "7H"]);
+
+        let res = game.do_move(move_to(&game, 1, 0, 2));
+        assert!(matches!(res, Err(MoveError::MoveGroupNotSequential)));
+        // the top card alone still can
+        let mut game = game_from(GameSuitNumber::Two, &["6S 5H", "6H"]);
+        assert!(game.do_move(move_to(&game, 1, 1, 2)).is_ok());
+    }
+
+    #[test]
+    fn a_five_does_not_go_on_a_nine() {
+        let mut game = game_from(GameSuitNumber::One, &["9S", "ks 5S"]);
+        let before = game.debug_tableau_string();
+
+        let res = game.do_move(move_to(&game, 2, 1, 1));
+
+        assert!(matches!(res, Err(MoveError::MoveCardNotUnder { .. })));
+        assert!(game.history_moves.is_empty());
+        // the king under the five stays face down
+        assert_eq!(game.debug_tableau_string(), before);
+    }
+
+    #[test]
+    fn a_five_goes_on_a_six() {
+        let mut game = game_from(GameSuitNumber::One, &["6S", "ks 5S"]);
+
+        assert!(game.do_move(move_to(&game, 2, 1, 1)).is_ok());
+
+        assert_eq!(game.history_moves.len(), 1);
+        assert!(game
+            .debug_tableau_string()
+            .starts_with(" 1: 6S 5S\n 2: KS\n"));
+    }
+
+    #[test]
+    fn a_mid_rank_run_goes_on_an_empty_pile_and_back() {
+        let mut game = game_from(GameSuitNumber::Two, &["ks 8H 7H 6H"]);
+        let before = game.debug_tableau_string();
+
+        assert!(game.do_move(move_to(&game, 1, 1, 2)).is_ok());
+        assert!(game
+            .debug_tableau_string()
+            .starts_with(" 1: KS\n 2: 8H 7H 6H\n"));
+
+        game.undo_once();
+        assert_eq!(game.debug_tableau_string(), before);
+        assert!(game.history_moves.is_empty());
+    }
+
+    /// the pile the 7 of spades on the top of pile 4 would be moved to
+    fn best_pile(piles: &[&str]) -> Option<(DestinationRank, usize)> {
+        let game = game_from(GameSuitNumber::Four, piles);
+        let (rank, dst) = game.best_destination(CardPosition { pile: 4, card: 1 })?;
+
+        Some((rank, dst.pile))
+    }
+
+    #[test]
+    fn destinations_rank_same_suit_then_other_suit_then_empty_pile() {
+        assert_eq!(
+            best_pile(&["8H", "8D", "8S", "kd 7S"]),
+            Some((DestinationRank::SameSuit, 3))
+        );
+        assert_eq!(
+            best_pile(&["", "8D", "", "kd 7S"]),
+            Some((DestinationRank::OtherSuit, 2))
+        );
+        assert_eq!(
+            best_pile(&["KC", "", "", "kd 7S"]),
+            Some((DestinationRank::EmptyPile, 2))
+        );
+        assert_eq!(
+            best_pile(&["KC", "QC", "JC", "kd 7S", "TC", "9C", "5C", "4C", "3C", "2C"]),
+            None
+        );
+    }
+
+    #[test]
+    fn auto_move_skips_the_first_legal_pile_for_a_better_one() {
+        let mut game = game_from(GameSuitNumber::Four, &["", "8H", "8S", "kd 7S"]);
+        let src = CardPosition { pile: 4, card: 1 };
+
+        let game_move = game.find_possible_move(src);
+        assert!(matches!(
+            game_move,
+            Some(GameMove::MoveCard { dst, .. }) if dst.pile == 3
+        ));
+
+        // the same suit taken, the other suit comes before the empty pile
+        game.tableau[2].clear();
+        let game_move = game.find_possible_move(src);
+        assert!(matches!(
+            game_move,
+            Some(GameMove::MoveCard { dst, .. }) if dst.pile == 2
+        ));
+    }
+
+    #[test]
+    fn auto_move_takes_the_run_above_a_card_that_can_not_move() {
+        // the 7 of hearts can't take the 6 of spades along,
+        // the 6 alone goes on the 7 of spades
+        let game = game_from(GameSuitNumber::Four, &["kd 7H 6S", "8H", "7S"]);
+
+        let game_move = game.find_possible_move(CardPosition { pile: 1, card: 1 });
+        assert!(matches!(
+            game_move,
+            Some(GameMove::MoveCard { src, dst, .. }) if src.card == 2 && dst.pile == 3
+        ));
+    }
+
+    fn seeded(game_suit: GameSuitNumber, seed: u64) -> Game {
+        let config = GameConfig {
+            seed: Some(seed),
+            no_animation: true,
+            ..GameConfig::default()
+        };
+
+        Game::new(game_suit, config)
+    }
+
+    fn stock_string(game: &Game) -> String {
+        game.stock
+            .iter()
+            .map(|card| card.card.to_notation())
+            .collect()
+    }
+
+    /// the cards, the foundations, the score and the history of a game
+    fn full_state(game: &Game) -> String {
+        format!(
+            "{}stock: {}\nfoundations: {:?}\nscore: {}\nhistory: {:?}\n",
+            game.debug_tableau_string(),
+            stock_string(game),
+            game.foundations,
+            game.score,
+            game.history_moves
+                .iter()
+                .map(|annotated| annotated.game_move)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// a game of the given time limit, started some seconds ago
+    /// and stopped now, so its clock stands still
+    fn timed(limit: Option<u64>, played: u64) -> Game {
+        let mut game = game_from(GameSuitNumber::One, &["KS"]);
+        game.config.time_limit = limit.map(Duration::from_secs);
+        game.start_time = Some(0);
+        game.end_time = Some(played as u128 * 1000);
+        // the tests never write the statistics
+        game.stats_recorded = true;
+
+        game
+    }
+
+    #[test]
+    fn untimed_game_has_no_time_remaining() {
+        let game = timed(None, 100);
+
+        assert_eq!(game.time_remaining(), None);
+        assert!(!game.time_pressure_mode());
+    }
+
+    #[test]
+    fn time_remaining_is_the_limit_before_the_first_move() {
+        let mut game = timed(Some(60), 0);
+        game.start_time = None;
+
+        assert_eq!(game.time_remaining(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn time_remaining_counts_down() {
+        assert_eq!(
+            timed(Some(60), 20).time_remaining(),
+            Some(Duration::from_secs(40))
+        );
+        assert!(!timed(Some(60), 30).time_pressure_mode());
+        assert!(timed(Some(60), 31).time_pressure_mode());
+    }
+
+    #[test]
+    fn time_remaining_stops_at_zero() {
+        assert_eq!(timed(Some(60), 60).time_remaining(), Some(Duration::ZERO));
+        assert_eq!(timed(Some(60), 90).time_remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn game_is_lost_when_the_time_is_up() {
+        let mut game = timed(Some(60), 59);
+        game.check_time_out();
+        assert!(!game.timed_out);
+
+        let mut game = timed(Some(60), 60);
+        game.check_time_out();
+        assert!(game.timed_out);
+    }
+
+    /// a game after two moves, each flipping a face down card
+    fn two_moves_made() -> Game {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS", "4s JS", "3s TS"]);
+        assert!(game.do_move(move_to(&game, 2, 1, 1)).is_ok());
+        assert!(game.do_move(move_to(&game, 3, 1, 1)).is_ok());
+
+        game
+    }
+
+    #[test]
+    fn sound_history_has_no_discrepancy() {
+        let game = two_moves_made();
+
+        assert_eq!(game.history_discrepancy(), None);
+        assert!(game.verify_history_integrity());
+    }
+
+    #[test]
+    fn changed_hash_is_found_at_its_move() {
+        let mut game = two_moves_made();
+        game.history_moves[1].post_state_hash ^= 1;
+
+        assert_eq!(game.history_discrepancy(), Some(1));
+        assert!(!game.verify_history_integrity());
+
+        let mut game = two_moves_made();
+        game.history_moves[0].pre_state_hash ^= 1;
+
+        assert_eq!(game.history_discrepancy(), Some(0));
+    }
+
+    #[test]
+    fn changed_move_is_found_at_its_move() {
+        let mut game = two_moves_made();
+        game.history_moves[1].game_move = GameMove::DealRow;
+
+        assert_eq!(game.history_discrepancy(), Some(1));
+    }
+
+    #[test]
+    fn changed_tableau_is_found_after_the_moves() {
+        let mut game = two_moves_made();
+        game.tableau[1][0].is_up = false;
+
+        assert_eq!(game.history_discrepancy(), Some(2));
+        assert!(!game.verify_history_integrity());
+    }
+
+    #[test]
+    fn missing_move_is_found() {
+        let mut game = two_moves_made();
+        game.history_moves.remove(0);
+
+        assert_eq!(game.history_discrepancy(), Some(0));
+    }
+
+    /// a game where the second move completes a run of spades
+    fn two_moves_from_a_run() -> Game {
+        game_from(
+            GameSuitNumber::One,
+            &["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S", "2S", "AS", "9S", "8S"],
+        )
+    }
+
+    #[test]
+    fn no_score_per_move_before_the_first_move() {
+        let game = two_moves_from_a_run();
+
+        assert_eq!(game.score_per_move(), 0.0);
+        assert_eq!(game.peak_score_per_move(), 0.0);
+        assert_eq!(game.score_at_move(0), Some(0));
+        assert_eq!(game.score_at_move(1), None);
+    }
+
+    #[test]
+    fn score_per_move_spreads_the_completed_run() {
+        let mut game = two_moves_from_a_run();
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        assert_eq!(game.score_per_move(), 0.0);
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_ok());
+        assert_eq!(game.score, COMPLETE_RUN_SCORE);
+        assert_eq!(game.score_per_move(), 50.0);
+
+        assert!(game.do_move(move_to(&game, 5, 0, 4)).is_ok());
+        assert!(game.do_move(move_to(&game, 4, 0, 1)).is_ok());
+        assert_eq!(game.score_per_move(), 25.0);
+        assert_eq!(game.peak_score_per_move(), 50.0);
+        assert_eq!(game.score_at_move(1), Some(0));
+        assert_eq!(game.score_at_move(3), Some(COMPLETE_RUN_SCORE));
+        assert_eq!(game.score_at_move(5), None);
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_game() {
+        for game_suit in [
+            GameSuitNumber::One,
+            GameSuitNumber::Two,
+            GameSuitNumber::Four,
+        ] {
+            let (a, b) = (seeded(game_suit, 1234), seeded(game_suit, 1234));
+
+            assert_eq!(a.debug_tableau_string(), b.debug_tableau_string());
+            assert_eq!(stock_string(&a), stock_string(&b));
+            assert_eq!(a.seed, 1234);
+        }
+    }
+
+    #[test]
+    fn other_seed_deals_another_game() {
+        let (a, b) = (
+            seeded(GameSuitNumber::Four, 1),
+            seeded(GameSuitNumber::Four, 2),
+        );
+
+        assert_ne!(
+            (a.debug_tableau_string(), stock_string(&a)),
+            (b.debug_tableau_string(), stock_string(&b))
+        );
+    }
+
+    #[test]
+    fn restart_deals_the_same_layout_again() {
+        let mut game = seeded(GameSuitNumber::Four, 23);
+        let dealt = full_state(&game);
+        play(&mut game, 2);
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+        play(&mut game, 2);
+        // keep the statistics file out of the test
+        game.stats_recorded = true;
+
+        game.restart();
+
+        assert_eq!(full_state(&game), dealt);
+        assert_eq!(full_state(&game.initial_deal()), dealt);
+        assert_eq!(game.move_count, 0);
+        assert!(!game.can_redo());
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip_many_times() {
+        let mut game = seeded(GameSuitNumber::Two, 9);
+        let mut states = vec![full_state(&game)];
+        for n in 0..6 {
+            if n == 3 {
+                assert!(game.do_move(GameMove::DealRow).is_ok());
+            } else {
+                play(&mut game, 1);
+            }
+            states.push(full_state(&game));
+        }
+
+        for _ in 0..3 {
+            for state in states.iter().rev().skip(1) {
+                game.undo_once();
+                assert_eq!(full_state(&game), *state);
+            }
+            assert!(!game.can_undo());
+
+            for state in states.iter().skip(1) {
+                game.redo_once();
+                assert_eq!(full_state(&game), *state);
+            }
+            assert!(!game.can_redo());
+        }
+    }
+
+    #[test]
+    fn seeded_deal_snapshot() {
+        let game = seeded(GameSuitNumber::Two, 42);
+
+        assert_eq!(
+            game.debug_tableau_string(),
+            " 1: 3h ks 4h th qh 8H
+ 2: 4s 2s 8s 6h 5s 7H
+ 3: ks 5s as 9h 5h AH
+ 4: kh jh 8h 2h 3h KS
+ 5: 2s 3s js 8s KH
+ 6: qs 8h 5s 5s 9S
+ 7: 6s 6h ts qs 8H
+ 8: 5h 2s ah jh JH
+ 9: th qs qs kh 9H
+10: 6s 7s 7h 2h 9H
+"
+        );
+    }
+
+    #[test]
+    fn hand_made_tableau_snapshot() {
+        let mut game = game_from(GameSuitNumber::Four, &["ks qd JC", "", "TH 9S"]);
+        assert!(game.do_move(move_to(&game, 1, 2, 2)).is_ok());
+
+        assert_eq!(
+            game.debug_tableau_string(),
+            " 1: ks QD
+ 2: JC
+ 3: TH 9S
+ 4:
+ 5:
+ 6:
+ 7:
+ 8:
+ 9:
+10:
+"
+        );
+    }
+
+    #[test]
+    fn completed_run_undoes_and_redoes_to_the_same_states() {
+        let run = "KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S";
+        let mut game = game_from(GameSuitNumber::One, &[&format!("4s {}", run), "AS", "9S"]);
+        let dealt = full_state(&game);
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        let completed = full_state(&game);
+        assert_eq!(game.completed_run_count(), 1);
+        // the card under the run is turned up
+        assert!(game.tableau[0][0].is_up);
+
+        for _ in 0..3 {
+            game.undo_once();
+            assert_eq!(full_state(&game), dealt);
+            assert!(!game.tableau[0][0].is_up);
+
+            game.redo_once();
+            assert_eq!(full_state(&game), completed);
+        }
+    }
+
+    #[test]
+    fn half_undone_game_redoes_to_the_same_state() {
+        let mut game = seeded(GameSuitNumber::Four, 11);
+        play(&mut game, 4);
+        let end = full_state(&game);
+
+        for _ in 0..3 {
+            game.undo_once();
+            game.undo_once();
+            game.redo_once();
+            game.undo_once();
+            game.redo_once();
+            game.redo_once();
+
+            assert_eq!(full_state(&game), end);
+        }
+    }
+
+    /// play the first legal moves, a deal included
+    fn play(game: &mut Game, moves: usize) {
+        for _ in 0..moves {
+            let game_move = game.legal_moves()[0];
+            assert!(game.do_move(game_move).is_ok());
+        }
+    }
+
+    /// the piles a move of the cards from a position is accepted onto
+    fn accepted_piles(game: &Game, src: CardPosition) -> Vec<usize> {
+        (1..=game.tableau.len())
+            .filter(|&pile| {
+                let mut copy = game.clone_for_analysis();
+                let game_move = move_to(&copy, src.pile, src.card, pile);
+                copy.do_move(game_move).is_ok()
+            })
+            .collect()
+    }
+
+    /// test the destinations of every card against the moves accepted
+    fn assert_destinations_are_accepted(game: &Game) {
+        for (i, pile) in game.tableau.iter().enumerate() {
+            for card in 0..pile.len() {
+                let src = CardPosition { pile: i + 1, card };
+
+                assert_eq!(
+                    game.legal_destinations(src),
+                    accepted_piles(game, src),
+                    "{:?}",
+                    src
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn destinations_match_the_accepted_moves() {
+        let games = [
+            game_from(GameSuitNumber::Four, &["ks 8H", "9H", "9S", "", "kd 7S 6S"]),
+            game_from(GameSuitNumber::Two, &["7S 6H", "8H", "qs 7H 6S", "8S"]),
+            game_from(GameSuitNumber::One, &["KS QS JS", "", "ks TS", "QS"]),
+        ];
+
+        for game in &games {
+            assert_destinations_are_accepted(game);
+        }
+    }
+
+    #[test]
+    fn destinations_match_the_accepted_moves_of_played_deals() {
+        for (game_suit, seed) in [(GameSuitNumber::Two, 5), (GameSuitNumber::Four, 6)] {
+            let mut game = seeded(game_suit, seed);
+            assert_destinations_are_accepted(&game);
+
+            play(&mut game, 3);
+            assert!(game.do_move(GameMove::DealRow).is_ok());
+            assert_destinations_are_accepted(&game);
+        }
+    }
+
+    #[test]
+    fn destinations_are_found_again_after_a_move() {
+        let piles = ["9S", "TS", "TS", "9S", "KS", "KS", "KS", "KS", "KS", "KS"];
+        let mut game = game_from(GameSuitNumber::One, &piles);
+        game.source = Some(CardPosition { pile: 1, card: 0 });
+
+        game.refresh_destinations();
+        assert_eq!(game.destinations.as_ref().unwrap().2, [2, 3]);
+
+        assert!(game.do_move(move_to(&game, 4, 0, 2)).is_ok());
+        game.refresh_destinations();
+        assert_eq!(game.destinations.as_ref().unwrap().2, [3, 4]);
+    }
+
+    #[test]
+    fn undo_all_matches_a_fresh_deal_of_the_same_seed() {
+        let mut game = seeded(GameSuitNumber::Four, 42);
+        play(&mut game, 3);
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+        play(&mut game, 3);
+        game.undo_by_player();
+        assert!(game.move_count > 0);
+        assert_eq!(game.undo_count, 1);
+
+        game.undo_all();
+
+        let fresh = seeded(GameSuitNumber::Four, 42);
+        assert_eq!(game.debug_tableau_string(), fresh.debug_tableau_string());
+        assert_eq!(stock_string(&game), stock_string(&fresh));
+        assert_eq!(game.score, 0);
+        assert!(game.foundations.is_empty());
+        assert!(!game.can_undo());
+        assert!(!game.can_redo());
+        assert_eq!(game.start_time, None);
+        assert_eq!(game.move_count, 0);
+        assert_eq!(game.undo_count, 0);
+        assert_eq!(game.move_count, fresh.move_count);
+    }
+
+    #[test]
+    fn undo_all_restores_the_cards_played_not_the_seed() {
+        let mut game = game_from(GameSuitNumber::Two, &["js 9S", "TH", "kh 8H"]);
+        let dealt = game.debug_tableau_string();
+        assert!(game.do_move(move_to(&game, 1, 1, 2)).is_ok());
+        assert!(game.do_move(move_to(&game, 3, 1, 2)).is_ok());
+
+        game.undo_all();
+
+        assert_eq!(game.debug_tableau_string(), dealt);
+        assert_eq!(game.initial_deal().debug_tableau_string(), dealt);
+    }
+
+    /// ten piles of a single card, none of them next to another in rank
+    const STUCK: [&str; 10] = ["AS", "3S", "5S", "7S", "9S", "JS", "KS", "AH", "3H", "5H"];
+
+    #[test]
+    fn no_move_left_when_no_card_fits() {
+        let game = game_from(GameSuitNumber::Two, &STUCK);
+
+        assert!(game.legal_moves().is_empty());
+        assert!(!game.has_any_move());
+    }
+
+    #[test]
+    fn a_lone_card_is_not_moved_to_an_empty_pile() {
+        let game = game_from(GameSuitNumber::Two, &STUCK[..9]);
+
+        assert!(!game.has_any_move());
+    }
+
+    #[test]
+    fn face_down_cards_do_not_move() {
+        let mut piles = STUCK;
+        piles[0] = "4h AS";
+        let game = game_from(GameSuitNumber::Two, &piles);
+
+        assert!(!game.has_any_move());
+    }
+
+    #[test]
+    fn a_fitting_card_is_a_move() {
+        let mut piles = STUCK;
+        piles[0] = "4H";
+        let game = game_from(GameSuitNumber::Two, &piles);
+
+        // the threes go on it, and it goes on the fives
+        assert_eq!(
+            game.legal_moves(),
+            vec![
+                move_to(&game, 1, 0, 3),
+                move_to(&game, 1, 0, 10),
+                move_to(&game, 2, 0, 1),
+                move_to(&game, 9, 0, 1),
+            ]
+        );
+        assert!(game.has_any_move());
+    }
+
+    #[test]
+    fn only_the_deal_is_left() {
+        let mut game = game_from(GameSuitNumber::Two, &STUCK);
+        game.stock = crate::test_util::cards("as 2s 3s 4s 5s 6s 7s 8s 9s ts");
+
+        assert_eq!(game.legal_moves(), vec![GameMove::DealRow]);
+    }
+
+    #[test]
+    fn h_hints_only_without_the_vim_keys() {
+        assert_eq!(
+            key_action(KeyBindingMode::Arrow, 'h'),
+            Some(KeyAction::Hint)
+        );
+        assert_eq!(key_action(KeyBindingMode::Both, 'h'), None);
+        assert_eq!(key_action(KeyBindingMode::Vim, 'h'), None);
+        for mode in [
+            KeyBindingMode::Arrow,
+            KeyBindingMode::Both,
+            KeyBindingMode::Vim,
+        ] {
+            assert_eq!(key_action(mode, 'H'), Some(KeyAction::Hint));
+        }
+    }
+
+    #[test]
+    fn only_h_is_shared_with_the_cursor() {
+        let mut keys = HashSet::new();
+        for (key, ..) in KEY_BINDINGS {
+            assert!(keys.insert(*key), "{} is bound twice", key);
+            assert_eq!(
+                key_action(KeyBindingMode::Both, *key).is_none(),
+                *key == 'h'
+            );
+        }
+    }
+
+    #[test]
+    fn help_lists_h_only_without_the_vim_keys() {
+        let mut game = game_from(GameSuitNumber::One, &[]);
+        let lists_h = |game: &Game| game.help_text().lines().any(|line| line.starts_with("h "));
+
+        game.config.key_binding_mode = KeyBindingMode::Arrow;
+        assert!(lists_h(&game));
+        game.config.key_binding_mode = KeyBindingMode::Both;
+        assert!(!lists_h(&game));
+    }
+
+    #[test]
+    fn click_finds_the_card_drawn_under_it() {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS JS"]);
+        game.layout = ScreenLayout {
+            stock: None,
+            piles: vec![Rect::new(0, 0, 8, 20)],
+            cards: vec![vec![
+                None,
+                Some(Rect::new(0, 2, 8, 2)),
+                Some(Rect::new(0, 4, 8, 8)),
+            ]],
+        };
+
+        assert_eq!(game.card_at(3, 0), None);
+        assert_eq!(game.card_at(3, 3), Some(CardPosition { pile: 1, card: 1 }));
+        assert_eq!(game.card_at(3, 9), Some(CardPosition { pile: 1, card: 2 }));
+        assert_eq!(game.card_at(3, 15), None);
+        assert_eq!(game.card_at(9, 3), None);
+    }
+
+    #[test]
+    fn rendering_does_not_change_the_game() {
+        let game = game_from(GameSuitNumber::Two, &["ks QS JH", "9D"]);
+        let before = game.render_to_string();
+        let mut renderer = crate::render::StringRenderer::default();
+
+        let layout = renderer.render(&game).unwrap();
+
+        assert_eq!(renderer.output, before);
+        assert_eq!(game.render_to_string(), before);
+        assert!(layout
+            .card_area(CardPosition { pile: 1, card: 0 })
+            .is_none());
+    }
+
+    /// a left click at a point of the screen
+    fn left_click(column: u16, row: u16) -> event::MouseEvent {
+        event::MouseEvent {
+            kind: MouseEventKind::Down(event::MouseButton::Left),
+            column,
+            row,
+            modifiers: event::KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn click_on_an_empty_stock_changes_nothing() {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS", "JS"]);
+        game.layout = ScreenLayout {
+            stock: Some(Rect::new(0, 0, 8, 8)),
+            ..ScreenLayout::default()
+        };
+        let before = game.debug_tableau_string();
+
+        assert!(game.handle_click(left_click(2, 2)).is_ok());
+
+        assert_eq!(game.debug_tableau_string(), before);
+        assert!(game.history_moves.is_empty());
+        assert!(game.status_message.is_some());
+    }
+
+    /// click on the stock of a fresh deal after dealing some rows,
+    /// the game and the pile sizes before the click
+    fn click_stock_after(rows: usize) -> (Game, Vec<usize>) {
+        let mut game = seeded(GameSuitNumber::Four, 3);
+        for _ in 0..rows {
+            assert!(game.do_move(GameMove::DealRow).is_ok());
+        }
+        game.layout = ScreenLayout {
+            stock: Some(Rect::new(0, 0, 8, 8)),
+            ..ScreenLayout::default()
+        };
+        let sizes = game.tableau.iter().map(Vec::len).collect();
+
+        assert!(game.handle_click(left_click(2, 2)).is_ok());
+
+        (game, sizes)
+    }
+
+    #[test]
+    fn click_on_a_full_stock_deals_a_row() {
+        let (game, sizes) = click_stock_after(0);
+
+        assert_eq!(game.stock_cards_remaining(), 40);
+        for (pile, size) in game.tableau.iter().zip(sizes) {
+            assert_eq!(pile.len(), size + 1);
+            assert!(pile.last().unwrap().is_up);
+        }
+        assert_eq!(game.history_moves.len(), 1);
+        assert!(matches!(game.history_moves[0].game_move, GameMove::DealRow));
+    }
+
+    #[test]
+    fn click_on_a_partial_stock_deals_a_row() {
+        let (game, sizes) = click_stock_after(2);
+
+        assert_eq!(game.stock_cards_remaining(), 20);
+        assert_eq!(game.deals_remaining(), 2);
+        for (pile, size) in game.tableau.iter().zip(sizes) {
+            assert_eq!(pile.len(), size + 1);
+        }
+    }
+
+    #[test]
+    fn click_on_the_last_row_empties_the_stock() {
+        let (game, _) = click_stock_after(4);
+
+        assert!(game.stock_exhausted());
+        assert_eq!(game.stock_current_card().map(|card| card.card), None);
+        assert_eq!(game.history_moves.len(), 5);
+    }
+
+    #[test]
+    fn click_before_the_first_render_is_ignored() {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS", "JS"]);
+        let before = game.debug_tableau_string();
+
+        assert!(game.handle_click(left_click(0, 0)).is_ok());
+        assert!(game.handle_click(left_click(3, 3)).is_ok());
+
+        assert_eq!(game.debug_tableau_string(), before);
+        assert_eq!(game.source, None);
+    }
+
+    /// press a key on a game with the cursor on the top card of the first pile,
+    /// the cursor after it
+    fn press(mode: KeyBindingMode, code: event::KeyCode) -> Option<CardPosition> {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS JS", "9S"]);
+        game.config.key_binding_mode = mode;
+        game.selected = Some(CardPosition { pile: 1, card: 2 });
+
+        if let Some(cursor_move) = mode.cursor_move_from_keycode(code) {
+            game.move_cursor(cursor_move);
+        }
+        game.selected
+    }
+
+    #[test]
+    fn each_binding_moves_the_cursor() {
+        use event::KeyCode::{Char, Down, Left, Right, Up};
+
+        let top = Some(CardPosition { pile: 1, card: 2 });
+        let up = Some(CardPosition { pile: 1, card: 1 });
+        let right = Some(CardPosition { pile: 2, card: 0 });
+        // wraps round to the last pile, empty here
+        let left = Some(CardPosition { pile: 10, card: 0 });
+
+        for (mode, arrows, vim) in [
+            (KeyBindingMode::Arrow, true, false),
+            (KeyBindingMode::Vim, false, true),
+            (KeyBindingMode::Both, true, true),
+        ] {
+            for (code, moved, bound) in [
+                (Left, left, arrows),
+                (Right, right, arrows),
+                (Up, up, arrows),
+                (Down, top, arrows),
+                (Char('h'), left, vim),
+                (Char('l'), right, vim),
+                (Char('k'), up, vim),
+                (Char('j'), top, vim),
+            ] {
+                let expected = if bound { moved } else { top };
+                assert_eq!(press(mode, code), expected, "{:?} {:?}", mode, code);
+            }
+        }
+    }
+
+    #[test]
+    fn face_down_card_can_not_be_moved() {
+        let mut game = game_from(GameSuitNumber::One, &["6s 5S", "7S"]);
+
+        assert!(game.do_move(move_to(&game, 1, 0, 2)).is_err());
+        assert!(game.legal_moves().iter().all(|game_move| !matches!(
+            game_move,
+            GameMove::MoveCard { src, .. } if src.card == 0 && src.pile == 1
+        )));
+        assert!(game.history_moves.is_empty());
+    }
+
+    #[test]
+    fn face_up_card_under_a_run_can_be_moved() {
+        let mut game = game_from(GameSuitNumber::One, &["6S 5S", "7S"]);
+
+        assert!(game.do_move(move_to(&game, 1, 0, 2)).is_ok());
+        assert_eq!(game.tableau[1].len(), 3);
+    }
+
+    #[test]
+    fn cursor_stays_on_the_face_up_cards() {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS JS"]);
+        game.selected = Some(CardPosition { pile: 1, card: 2 });
+
+        for _ in 0..3 {
+            game.move_cursor(CursorMove::Up);
+        }
+        assert_eq!(game.selected, Some(CardPosition { pile: 1, card: 1 }));
+        for _ in 0..3 {
+            game.move_cursor(CursorMove::Down);
+        }
+        assert_eq!(game.selected, Some(CardPosition { pile: 1, card: 2 }));
+    }
+
+    #[test]
+    fn first_move_puts_the_cursor_on_the_first_pile() {
+        let mut game = game_from(GameSuitNumber::One, &["ks QS JS"]);
+
+        game.move_cursor(CursorMove::Right);
+
+        assert_eq!(game.selected, Some(CardPosition { pile: 1, card: 2 }));
+    }
+
+    /// the piles a move changes, found by making it
+    fn changed_piles(game: &mut Game, game_move: GameMove) -> Vec<usize> {
+        let before = game.debug_tableau_string();
+        assert!(game.do_move(game_move).is_ok());
+        let after = game.debug_tableau_string();
+
+        before
+            .lines()
+            .zip(after.lines())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+
+    fn affected_piles(game_move: GameMove) -> Vec<usize> {
+        (1..=10)
+            .filter(|&pile| game_move.affects_pile(pile))
+            .collect()
+    }
+
+    #[test]
+    fn a_card_move_affects_its_two_piles() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "ks 5H", "9D"]);
+        let game_move = move_to(&game, 2, 1, 1);
+
+        assert_eq!(affected_piles(game_move), [1, 2]);
+        assert_eq!(changed_piles(&mut game, game_move), [1, 2]);
+    }
+
+    #[test]
+    fn a_deal_affects_every_pile() {
+        let mut game = seeded(GameSuitNumber::Two, 5);
+
+        assert_eq!(
+            affected_piles(GameMove::DealRow),
+            (1..=10).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            changed_piles(&mut game, GameMove::DealRow),
+            (1..=10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn a_completed_run_affects_its_pile() {
+        let mut game = game_from(
+            GameSuitNumber::One,
+            &["9S", "ks KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S AS"],
+        );
+        let game_move = GameMove::CompleteRun {
+            pile: 2,
+            suit: Suit::Spades,
+            flipped: true,
+        };
+
+        assert_eq!(affected_piles(game_move), [2]);
+        assert_eq!(changed_piles(&mut game, game_move), [2]);
+    }
+
+    /// the piles marked to be drawn again, in order
+    fn dirty_piles(game: &Game) -> Vec<usize> {
+        let mut piles: Vec<usize> = game.dirty_piles.iter().copied().collect();
+        piles.sort_unstable();
+
+        piles
+    }
+
+    #[test]
+    fn new_game_is_drawn_whole() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "ks 5H"]);
+        assert!(game.force_full_redraw);
+        assert!(game.dirty_piles.is_empty());
+
+        game.clear_dirty();
+        assert!(!game.force_full_redraw);
+    }
+
+    #[test]
+    fn a_card_move_marks_its_two_piles() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "ks 5H", "9D"]);
+        game.clear_dirty();
+
+        assert!(game.do_move(move_to(&game, 2, 1, 1)).is_ok());
+        assert_eq!(dirty_piles(&game), [1, 2]);
+
+        game.clear_dirty();
+        assert!(game.undo_once());
+        assert_eq!(dirty_piles(&game), [1, 2]);
+
+        game.clear_dirty();
+        game.redo_once();
+        assert_eq!(dirty_piles(&game), [1, 2]);
+        assert!(!game.force_full_redraw);
+    }
+
+    #[test]
+    fn a_deal_marks_every_pile() {
+        let mut game = seeded(GameSuitNumber::Two, 5);
+        game.clear_dirty();
+
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+        assert_eq!(dirty_piles(&game), (1..=10).collect::<Vec<_>>());
+
+        game.clear_dirty();
+        assert!(game.undo_once());
+        assert_eq!(dirty_piles(&game), (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_completed_run_marks_its_pile() {
+        let mut game = game_from(
+            GameSuitNumber::One,
+            &["9S", "ks KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S AS"],
+        );
+        game.clear_dirty();
+
+        assert!(game
+            .do_move(GameMove::CompleteRun {
+                pile: 2,
+                suit: Suit::Spades,
+                flipped: true,
+            })
+            .is_ok());
+        assert_eq!(dirty_piles(&game), [2]);
+    }
+
+    #[test]
+    fn a_move_completing_a_run_marks_both_piles() {
+        let run = "KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S";
+        let mut game = game_from(GameSuitNumber::One, &["9S", run, "AS"]);
+        game.clear_dirty();
+
+        assert!(game.do_move(move_to(&game, 3, 0, 2)).is_ok());
+        assert_eq!(game.completed_run_count(), 1);
+        assert_eq!(dirty_piles(&game), [2, 3]);
+
+        game.clear_dirty();
+        assert!(game.undo_once());
+        assert_eq!(dirty_piles(&game), [2, 3]);
+    }
+
+    #[test]
+    fn a_failed_move_marks_nothing() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "ks 5H", "9D"]);
+        game.clear_dirty();
+
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_err());
+        assert!(game.dirty_piles.is_empty());
+    }
+
+    #[test]
+    fn undo_all_draws_the_whole_tableau() {
+        let mut game = seeded(GameSuitNumber::Two, 5);
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+        game.clear_dirty();
+
+        game.undo_all();
+
+        assert!(game.force_full_redraw);
+    }
+
+    fn round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let src = CardPosition { pile: 3, card: 4 };
+        let dst = CardPosition { pile: 10, card: 0 };
+        let moves = [
+            GameMove::DealRow,
+            GameMove::MoveCard {
+                src,
+                dst,
+                before_visible: None,
+            },
+            GameMove::MoveCard {
+                src,
+                dst,
+                before_visible: Some(true),
+            },
+            GameMove::CompleteRun {
+                pile: 7,
+                suit: Suit::Diamonds,
+                flipped: false,
+            },
+        ];
+
+        assert_eq!(round_trip(&src), src);
+        assert_eq!(round_trip(&dst), dst);
+        for game_move in moves {
+            assert_eq!(round_trip(&game_move), game_move);
+            let annotated = AnnotatedMove {
+                game_move,
+                pre_state_hash: u64::MAX,
+                post_state_hash: 1,
+                timestamp_ms: 1_700_000_000_000,
+            };
+            assert_eq!(round_trip(&annotated), annotated);
+        }
+    }
+
+    #[test]
+    fn can_undo_follows_the_history() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "7H", "5S"]);
+        assert!(!game.can_undo());
+
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_ok());
+        assert!(game.can_undo());
+        assert!(game.do_move(move_to(&game, 1, 0, 2)).is_ok());
+        assert!(game.can_undo());
+
+        game.undo_once();
+        assert!(game.can_undo());
+        game.undo_once();
+        assert!(!game.can_undo());
+        // nothing left to undo
+        game.undo_once();
+        assert!(!game.can_undo());
+    }
+
+    #[test]
+    fn failed_move_leaves_nothing_to_undo() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "9H"]);
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_err());
+
+        assert!(!game.can_undo());
+    }
+
+    /// a game with a row left in the stock and the last pile empty
+    fn empty_last_pile(relaxed_deal: bool) -> Game {
+        let mut game = game_from(GameSuitNumber::One, &["KS"; 9]);
+        game.stock = crate::test_util::cards("as 2s 3s 4s 5s 6s 7s 8s 9s ts");
+        game.config.relaxed_deal = relaxed_deal;
+
+        game
+    }
+
+    #[test]
+    fn strict_deal_needs_every_pile_filled() {
+        let mut game = empty_last_pile(false);
+
+        assert!(matches!(
+            game.do_move(GameMove::DealRow),
+            Err(MoveError::DealWithEmptyPile)
+        ));
+        assert_eq!(game.stock.len(), 10);
+        assert!(game.tableau[9].is_empty());
+        assert!(!game.legal_moves().contains(&GameMove::DealRow));
+    }
+
+    #[test]
+    fn relaxed_deal_fills_the_empty_pile() {
+        let mut game = empty_last_pile(true);
+        assert!(game.legal_moves().contains(&GameMove::DealRow));
+
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+
+        assert!(game.stock.is_empty());
+        assert!(game.tableau.iter().all(|pile| pile.last().unwrap().is_up));
+        assert_eq!(game.tableau[9].len(), 1);
+    }
+
+    #[test]
+    fn both_deals_need_a_stock() {
+        for relaxed_deal in [false, true] {
+            let mut game = empty_last_pile(relaxed_deal);
+            game.stock.clear();
+
+            assert!(matches!(
+                game.do_move(GameMove::DealRow),
+                Err(MoveError::DealEmptyStock)
+            ));
+        }
+    }
+
+    /// collect every event of a game from now on, written out short
+    fn observe(game: &mut Game) -> Rc<RefCell<Vec<String>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let observed = Rc::clone(&events);
+        game.on_event(move |event| {
+            let describe = |game_move: &GameMove| match game_move {
+                GameMove::MoveCard { src, dst, .. } => {
+                    format!("{}:{} to {}", src.pile, src.card, dst.pile)
+                }
+                other => format!("{:?}", other),
+            };
+            observed.borrow_mut().push(match event {
+                GameEvent::MoveMade(game_move) => format!("move {}", describe(game_move)),
+                GameEvent::MoveUndone(game_move) => format!("undo {}", describe(game_move)),
+                GameEvent::SequenceCompleted { pile, suit } => format!("run {} {:?}", pile, suit),
+                GameEvent::GameWon { .. } => String::from("won"),
+                GameEvent::Deadlocked => String::from("deadlocked"),
+            });
+        });
+
+        events
+    }
+
+    #[test]
+    fn events_follow_a_scripted_game() {
+        let mut game = game_from(
+            GameSuitNumber::Two,
+            &["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S", "AS", "6H", "5H"],
+        );
+        game.foundations = vec![Suit::Spades; RUNS_TO_WIN - 1];
+        let events = observe(&mut game);
+
+        assert!(game.do_move(move_to(&game, 4, 0, 3)).is_ok());
+        game.undo_once();
+        // a failed move is not reported
+        assert!(game.do_move(move_to(&game, 4, 0, 2)).is_err());
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        game.check_deadlock();
+
+        assert_eq!(
+            *events.borrow(),
+            [
+                "move 4:0 to 3",
+                "undo 4:0 to 3",
+                "move 2:0 to 1",
+                "run 1 Spades",
+                "won",
+            ]
+        );
+    }
+
+    #[test]
+    fn deadlock_is_reported_once() {
+        let mut game = game_from(GameSuitNumber::Four, &STUCK);
+        let events = observe(&mut game);
+
+        game.check_deadlock();
+        game.check_deadlock();
+
+        assert_eq!(*events.borrow(), ["deadlocked"]);
+    }
+
+    /// the stock helpers of a fresh deal after dealing some rows
+    fn stock_after(rows: usize) -> (usize, usize, bool, Option<String>, usize) {
+        let mut game = seeded(GameSuitNumber::Four, 3);
+        for _ in 0..rows {
+            assert!(game.do_move(GameMove::DealRow).is_ok());
+        }
+
+        (
+            game.stock_cards_remaining(),
+            game.deals_remaining(),
+            game.stock_exhausted(),
+            game.stock_current_card()
+                .map(|card| card.card.to_notation()),
+            game.stock_total_count(),
+        )
+    }
+
+    #[test]
+    fn full_stock() {
+        let game = seeded(GameSuitNumber::Four, 3);
+        let top = game.stock.last().unwrap().card.to_notation();
+
+        assert_eq!(stock_after(0), (50, 5, false, Some(top), 50));
+    }
+
+    #[test]
+    fn stock_in_the_middle() {
+        let game = seeded(GameSuitNumber::Four, 3);
+        let top = game.stock[29].card.to_notation();
+
+        assert_eq!(stock_after(2), (30, 3, false, Some(top), 50));
+    }
+
+    #[test]
+    fn stock_with_the_last_row() {
+        let game = seeded(GameSuitNumber::Four, 3);
+        let top = game.stock[9].card.to_notation();
+
+        assert_eq!(stock_after(4), (10, 1, false, Some(top), 50));
+    }
+
+    #[test]
+    fn empty_stock() {
+        assert_eq!(stock_after(5), (0, 0, true, None, 50));
+    }
+
+    /// a game with two runs an Ace short, the Aces on the third and fourth piles
+    fn two_runs_to_complete() -> Game {
+        let run = "KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S";
+
+        game_from(GameSuitNumber::One, &[run, run, "AS", "AS"])
+    }
+
+    #[test]
+    fn score_follows_do_undo_and_redo() {
+        let run = "KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S";
+        let mut game = game_from(GameSuitNumber::One, &[run, "AS", "9S", "8S"]);
+        let mut scores = Vec::new();
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        scores.push(game.score);
+        assert!(game.do_move(move_to(&game, 4, 0, 3)).is_ok());
+        scores.push(game.score);
+        // the undo of a move costs the penalty
+        game.undo_by_player();
+        scores.push(game.score);
+        // the redo does not give it back
+        game.redo_once();
+        scores.push(game.score);
+        // the undo of a completed run takes its points back too
+        game.undo_by_player();
+        game.undo_by_player();
+        scores.push(game.score);
+        game.redo_once();
+        scores.push(game.score);
+
+        assert_eq!(scores, [100, 100, 99, 99, 0, 100]);
+        assert_eq!(game.undo_count, 3);
+    }
+
+    #[test]
+    fn undo_penalty_comes_from_the_config() {
+        let mut game = two_runs_to_complete();
+        game.config.undo_penalty = Some(30);
+
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_ok());
+        assert!(game.do_move(move_to(&game, 4, 0, 2)).is_ok());
+        assert_eq!(game.score, 200);
+
+        game.undo_by_player();
+        assert_eq!(game.score, 70);
+        game.redo_once();
+        assert_eq!(game.score, 170);
+    }
+
+    #[test]
+    fn move_and_undo_counts_follow_the_player() {
+        let run = "KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S";
+        let mut game = game_from(GameSuitNumber::One, &[run, "AS", "9S", "8S"]);
+        assert_eq!((game.num_moves_made(), game.num_undos_made()), (0, 0));
+
+        // the completed run is part of the move
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        assert_eq!(game.num_moves_made(), 1);
+        assert!(game.do_move(move_to(&game, 4, 0, 3)).is_ok());
+        assert_eq!(game.num_moves_made(), 2);
+
+        game.undo_by_player();
+        assert_eq!((game.num_moves_made(), game.num_undos_made()), (1, 1));
+        game.undo_by_player();
+        assert_eq!((game.num_moves_made(), game.num_undos_made()), (0, 2));
+        assert_eq!(game.net_moves(), -2);
+
+        game.redo_once();
+        assert_eq!((game.num_moves_made(), game.num_undos_made()), (1, 2));
+    }
+
+    #[test]
+    fn undo_with_nothing_to_undo_is_not_counted() {
+        let mut game = game_from(GameSuitNumber::One, &["9S", "8S"]);
+
+        game.undo_by_player();
+        assert_eq!(game.num_undos_made(), 0);
+
+        game.config.undo_limit = Some(1);
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        game.undo_by_player();
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        game.undo_by_player();
+        assert_eq!(game.num_undos_made(), 1);
+        assert_eq!(game.num_moves_made(), 1);
+    }
+
+    #[test]
+    fn undo_once_tells_if_a_move_was_undone() {
+        let mut game = game_from(GameSuitNumber::One, &["9S", "8S"]);
+        assert!(!game.undo_once());
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        assert!(game.undo_once());
+        assert!(!game.undo_once());
+    }
+
+    #[test]
+    fn failed_undo_is_not_charged() {
+        let mut game = game_from(GameSuitNumber::One, &["9S", "8S"]);
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        game.score = 10;
+        // the moved card is gone, so the move can't be undone
+        game.tableau[0].clear();
+
+        game.undo_by_player();
+
+        assert_eq!(game.num_undos_made(), 0);
+        assert_eq!(game.score, 10);
+        assert_eq!(game.history_moves.len(), 1);
+    }
+
+    #[test]
+    fn best_run_is_kept_after_breaking_the_run() {
+        let mut game = game_from(GameSuitNumber::One, &["9S", "8S 7S 6S 5S 4S", "5S", "8S"]);
+        assert_eq!(game.best_run, 5);
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        assert_eq!(longest_run(&game.tableau[0]), 6);
+        assert_eq!(game.best_run, 6);
+
+        // 4 then 7 6 5 moved off the run
+        assert!(game.do_move(move_to(&game, 1, 5, 3)).is_ok());
+        assert!(game.do_move(move_to(&game, 1, 2, 4)).is_ok());
+        assert_eq!(longest_run(&game.tableau[0]), 2);
+        assert_eq!(game.best_run, 6);
+
+        game.undo_once();
+        game.undo_once();
+        game.undo_once();
+        assert_eq!(game.best_run, 6);
+    }
+
+    #[test]
+    fn run_count_follows_each_completed_run() {
+        let mut game = two_runs_to_complete();
+        assert_eq!(game.completed_run_count(), 0);
+
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_ok());
+        assert_eq!(game.completed_run_count(), 1);
+        assert!(game.tableau[0].is_empty());
+
+        assert!(game.do_move(move_to(&game, 4, 0, 2)).is_ok());
+        assert_eq!(game.completed_run_count(), 2);
+        assert!(!game.test_win());
+    }
+
+    #[test]
+    fn undo_takes_the_completed_run_back() {
+        let mut game = two_runs_to_complete();
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_ok());
+
+        game.undo_once();
+        assert_eq!(game.completed_run_count(), 0);
+        assert_eq!(game.tableau[0].len(), 12);
+
+        game.redo_once();
+        assert_eq!(game.completed_run_count(), 1);
+    }
+
+    #[test]
+    fn win_agrees_with_the_run_count() {
+        let mut game = two_runs_to_complete();
+        game.foundations = vec![Suit::Spades; RUNS_TO_WIN - 2];
+
+        assert!(game.do_move(move_to(&game, 3, 0, 1)).is_ok());
+        assert_eq!(game.completed_run_count(), RUNS_TO_WIN - 1);
+        assert!(!game.test_win());
+
+        assert!(game.do_move(move_to(&game, 4, 0, 2)).is_ok());
+        assert_eq!(game.completed_run_count(), RUNS_TO_WIN);
+        assert!(game.test_win());
+
+        game.undo_once();
+        assert!(!game.test_win());
+    }
+
+    /// a game one run from the win, the last pile on the first pile
+    fn last_run(game_suit: GameSuitNumber, pile: &str) -> Game {
+        let mut game = game_from(game_suit, &[pile]);
+        game.foundations = vec![Suit::Spades; RUNS_TO_WIN - 1];
+
+        game
+    }
+
+    #[test]
+    fn ordered_run_of_a_suit_wins() {
+        let mut game = last_run(
+            GameSuitNumber::Four,
+            "KH QH JH TH 9H 8H 7H 6H 5H 4H 3H 2H AH",
+        );
+
+        assert!(game.check_and_remove_completed_pile(1));
+        assert!(game.test_win());
+    }
+
+    #[test]
+    fn mis_ordered_pile_of_thirteen_does_not_win() {
+        let mut game = last_run(
+            GameSuitNumber::One,
+            "KS JS QS TS 9S 8S 7S 6S 5S 4S 3S 2S AS",
+        );
+
+        assert!(!game.check_and_remove_completed_pile(1));
+        assert!(!game.test_win());
+        assert_eq!(game.tableau[0].len(), 13);
+    }
+
+    #[test]
+    fn ordered_pile_of_mixed_suits_does_not_win() {
+        let mut game = last_run(
+            GameSuitNumber::Four,
+            "KS QH JS TH 9S 8H 7S 6H 5S 4H 3S 2H AS",
+        );
+
+        assert!(!game.check_and_remove_completed_pile(1));
+        assert!(!game.test_win());
+    }
+
+    #[test]
+    fn full_run_of_a_suit_is_removed() {
+        let mut game = game_from(
+            GameSuitNumber::Two,
+            &["KH QH JH TH 9H 8H 7H 6H 5H 4H 3H 2H AH"],
+        );
+
+        assert!(game.check_and_remove_completed_pile(1));
+        assert!(game.tableau[0].is_empty());
+        assert_eq!(game.foundations, [Suit::Hearts]);
+        assert_eq!(game.score, COMPLETE_RUN_SCORE);
+    }
+
+    #[test]
+    fn full_run_on_other_cards_is_removed_from_the_top() {
+        let mut game = game_from(
+            GameSuitNumber::One,
+            &["5s 9S KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S AS"],
+        );
+
+        assert!(game.check_and_remove_completed_pile(1));
+        assert_eq!(
+            game.debug_tableau_string().lines().next(),
+            Some(" 1: 5s 9S")
+        );
+    }
+
+    #[test]
+    fn mixed_suit_run_of_thirteen_is_not_removed() {
+        let piles = ["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S AH"];
+        let mut game = game_from(GameSuitNumber::Two, &piles);
+
+        assert!(!game.check_and_remove_completed_pile(1));
+        assert_eq!(game.tableau[0].len(), 13);
+        assert!(game.foundations.is_empty());
+    }
+
+    #[test]
+    fn run_short_of_thirteen_is_not_removed() {
+        for pile in ["QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S AS", "KS QS JS", "AS", ""] {
+            let mut game = game_from(GameSuitNumber::One, &[pile]);
+
+            assert!(!game.check_and_remove_completed_pile(1), "{:?}", pile);
+            assert!(game.history_moves.is_empty());
+        }
+    }
+
+    #[test]
+    fn run_with_a_face_down_card_is_not_removed() {
+        let piles = ["ks QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S AS"];
+        let mut game = game_from(GameSuitNumber::One, &piles);
+
+        assert!(!game.check_and_remove_completed_pile(1));
+        assert_eq!(game.tableau[0].len(), 13);
+    }
+
+    #[test]
+    fn repeated_move_and_undo_keep_the_face_up_flags() {
+        let mut game = game_from(GameSuitNumber::Two, &["3s ks 8H", "9S", "5h 7S 6S"]);
+        let flags = face_up_flags(&game);
+
+        for _ in 0..5 {
+            assert!(game.do_move(move_to(&game, 1, 2, 2)).is_ok());
+            assert!(game.tableau[0][1].is_up);
+            game.undo_once();
+            assert_eq!(face_up_flags(&game), flags);
+
+            assert!(game.do_move(move_to(&game, 3, 1, 1)).is_ok());
+            assert!(game.tableau[2][0].is_up);
+            game.undo_once();
+            assert_eq!(face_up_flags(&game), flags);
+        }
+    }
+
+    #[test]
+    fn wrong_before_visible_flag_does_not_change_the_undo() {
+        let mut game = game_from(GameSuitNumber::Two, &["3s ks 8H", "9S", "5h 7S 6S"]);
+        let flags = face_up_flags(&game);
+
+        for before_visible in [None, Some(true), Some(false)] {
+            let game_move = GameMove::MoveCard {
+                src: CardPosition { pile: 1, card: 2 },
+                dst: CardPosition { pile: 2, card: 1 },
+                before_visible,
+            };
+            assert!(game.do_move(game_move).is_ok());
+            game.undo_once();
+            assert_eq!(face_up_flags(&game), flags);
+        }
+    }
+
+    #[test]
+    fn win_does_not_wait_for_the_stock() {
+        let mut game = seeded(GameSuitNumber::One, 3);
+        game.foundations = vec![Suit::Spades; RUNS_TO_WIN];
+
+        assert!(!game.stock_exhausted());
+        assert!(game.test_win());
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fmt::Debug, time::Duration};
+
+use crate::{card::Suit, game::GameMove};
+
+/// The things happening in a game, reported to the event callbacks.
+#[derive(Debug, Clone, Copy)]
+pub enum GameEvent {
+    /// a move is made by the player
+    MoveMade(GameMove),
+    /// a move is undone
+    MoveUndone(GameMove),
+    /// a completed run is removed from a pile
+    SequenceCompleted { pile: usize, suit: Suit },
+    /// all the runs are completed
+    GameWon { score: u32, elapsed: Duration },
+    /// there is no move left
+    Deadlocked,
+}
+
+/// A callback called for every game event.
+pub type EventCallback = Box<dyn Fn(&GameEvent)>;
+
+/// The callbacks called for every game event.
+#[derive(Default)]
+pub struct EventCallbacks(Vec<EventCallback>);
+
+impl Debug for EventCallbacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EventCallbacks({} callbacks)", self.0.len())
+    }
+}
+
+impl EventCallbacks {
+    /// register a callback
+    pub fn push(&mut self, cb: impl Fn(&GameEvent) + 'static) {
+        self.0.push(Box::new(cb));
+    }
+
+    /// report an event to all the callbacks
+    pub fn emit(&self, event: &GameEvent) {
+        for cb in &self.0 {
+            cb(event);
+        }
+    }
+}
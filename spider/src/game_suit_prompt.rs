@@ -1,7 +1,7 @@
 use tui::{
     layout::{Constraint, Direction, Layout},
     style::Style,
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
 };
 
 use crate::{card::GameSuitNumber, TERMINAL};
@@ -33,6 +33,28 @@ static GAME_SUIT_LIST: once_cell::sync::Lazy<List> = once_cell::sync::Lazy::new(
     )
 });
 
+/// ask a yes or no question,
+/// anything but y is no
+pub fn ask_yes_no(question: &str) -> crossterm::Result<bool> {
+    let mut terminal = TERMINAL.lock().unwrap();
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(1)
+                .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+                .split(f.size());
+            let block = Block::default().title(question).borders(Borders::all());
+            f.render_widget(Paragraph::new("y: Yes  n: No").block(block), chunks[0]);
+        })?;
+
+        if let crossterm::event::Event::Key(e) = crossterm::event::read()? {
+            return Ok(e.code == crossterm::event::KeyCode::Char('y'));
+        }
+    }
+}
+
 /// ask for a game suit
 ///
 /// none means user press esc or q
@@ -0,0 +1,177 @@
+use crate::game::{verify_rank_under, Game, GameMove};
+
+/// the best move to suggest to the player,
+/// none if there is no tableau move worth making
+pub fn best_move(game: &Game) -> Option<GameMove> {
+    game.legal_moves()
+        .into_iter()
+        .filter_map(|game_move| rank_move(game, game_move).map(|rank| (rank, game_move)))
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, game_move)| game_move)
+}
+
+/// the move the autoplay makes next,
+/// the best move or a deal when there is none
+pub fn autoplay_move(game: &Game) -> Option<GameMove> {
+    best_move(game).or_else(|| {
+        game.legal_moves()
+            .into_iter()
+            .find(|game_move| *game_move == GameMove::DealRow)
+    })
+}
+
+/// how good a move is, higher is better
+///
+/// none for a deal, or for a move only shuffling a run
+/// between two equally good places
+pub fn rank_move(game: &Game, game_move: GameMove) -> Option<i32> {
+    let (src, dst, before_visible) = match game_move {
+        GameMove::MoveCard {
+            src,
+            dst,
+            before_visible,
+        } => (src, dst, before_visible),
+        _ => return None,
+    };
+    let src_pile = game.tableau.get(src.pile.checked_sub(1)?)?;
+    let dst_pile = game.tableau.get(dst.pile.checked_sub(1)?)?;
+    let card = src_pile.get(src.card)?.card;
+
+    let same_suit_build = dst_pile
+        .last()
+        .is_some_and(|last| last.card.suit == card.suit);
+
+    // the run already sits on a valid card
+    let below = src.card.checked_sub(1).and_then(|i| src_pile.get(i));
+    if let Some(below) = below {
+        if below.is_up && verify_rank_under(below.card, card) {
+            let was_same_suit = below.card.suit == card.suit;
+            if was_same_suit || !same_suit_build {
+                return None;
+            }
+        }
+    }
+
+    let mut rank = 0;
+    if before_visible == Some(true) {
+        rank += 10;
+    }
+    if same_suit_build {
+        rank += 5;
+    }
+    // keep the empty piles for when they are needed
+    if dst_pile.is_empty() {
+        rank -= 5;
+    }
+
+    Some(rank)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::GameSuitNumber,
+        test_util::{cards, game_from, move_to},
+    };
+
+    /// the legal move of the cards from a card of a pile onto another pile,
+    /// as the game lists it
+    fn legal(game: &Game, pile: usize, card: usize, dst: usize) -> GameMove {
+        let wanted = move_to(game, pile, card, dst);
+        game.legal_moves()
+            .into_iter()
+            .find(|game_move| match (game_move, wanted) {
+                (
+                    GameMove::MoveCard { src, dst, .. },
+                    GameMove::MoveCard {
+                        src: wanted_src,
+                        dst: wanted_dst,
+                        ..
+                    },
+                ) => *src == wanted_src && *dst == wanted_dst,
+                _ => false,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn a_deal_is_not_ranked() {
+        let game = game_from(GameSuitNumber::Two, &["6S"]);
+
+        assert_eq!(rank_move(&game, GameMove::DealRow), None);
+    }
+
+    #[test]
+    fn turning_a_card_up_ranks_highest() {
+        let game = game_from(GameSuitNumber::Two, &["ks 5H", "6S", "6H", "7S 5S"]);
+
+        assert_eq!(rank_move(&game, legal(&game, 1, 1, 2)), Some(10));
+        assert_eq!(rank_move(&game, legal(&game, 1, 1, 3)), Some(15));
+        assert_eq!(rank_move(&game, legal(&game, 4, 1, 2)), Some(5));
+    }
+
+    #[test]
+    fn same_suit_ranks_above_other_suit() {
+        let game = game_from(GameSuitNumber::Two, &["5H", "6S", "6H"]);
+
+        assert_eq!(rank_move(&game, legal(&game, 1, 0, 2)), Some(0));
+        assert_eq!(rank_move(&game, legal(&game, 1, 0, 3)), Some(5));
+        assert_eq!(best_move(&game), Some(legal(&game, 1, 0, 3)));
+    }
+
+    #[test]
+    fn an_empty_pile_costs_rank() {
+        let game = game_from(GameSuitNumber::Two, &["ks 5H"]);
+
+        assert_eq!(rank_move(&game, legal(&game, 1, 1, 2)), Some(5));
+    }
+
+    #[test]
+    fn a_run_is_not_moved_to_an_equally_good_place() {
+        let game = game_from(GameSuitNumber::Two, &["7S 6S", "7H", "7D 6H"]);
+
+        assert_eq!(rank_move(&game, legal(&game, 1, 1, 2)), None);
+        // unless the new place is of its suit
+        assert_eq!(rank_move(&game, legal(&game, 3, 1, 2)), Some(5));
+    }
+
+    #[test]
+    fn autoplay_turns_a_card_up_first() {
+        let game = game_from(GameSuitNumber::Two, &["ks 5H", "6S", "6H", "7S 5S"]);
+
+        assert_eq!(autoplay_move(&game), Some(legal(&game, 1, 1, 3)));
+    }
+
+    #[test]
+    fn autoplay_moves_before_dealing() {
+        let mut game = game_from(GameSuitNumber::Two, &["5H", "6H", "9S", "9S", "9S"]);
+        game.stock = cards("as 2s 3s 4s 5s 6s 7s 8s 9s ts");
+
+        assert_eq!(autoplay_move(&game), Some(legal(&game, 1, 0, 2)));
+    }
+
+    #[test]
+    fn autoplay_builds_on_the_suit_of_the_run() {
+        let game = game_from(GameSuitNumber::Two, &["6S 5S", "6H", "7S", "7H"]);
+
+        assert_eq!(autoplay_move(&game), Some(legal(&game, 2, 0, 4)));
+    }
+
+    #[test]
+    fn autoplay_has_nothing_to_do_on_a_won_game() {
+        let game = game_from(GameSuitNumber::One, &[]);
+
+        assert_eq!(autoplay_move(&game), None);
+    }
+
+    #[test]
+    fn autoplay_deals_when_no_move_is_worth_it() {
+        let mut game = game_from(GameSuitNumber::One, &["7S 6S"; 10]);
+        assert_eq!(best_move(&game), None);
+        assert_eq!(autoplay_move(&game), None);
+
+        game.stock = cards("as 2s 3s 4s 5s 6s 7s 8s 9s ts");
+        assert_eq!(autoplay_move(&game), Some(GameMove::DealRow));
+    }
+}
@@ -0,0 +1,31 @@
+use std::{
+    io::{self, Stdout},
+    sync::Mutex,
+};
+
+use tui::{backend::CrosstermBackend, Terminal};
+
+pub mod card;
+pub mod config;
+pub mod daily;
+pub mod game;
+pub mod game_event;
+pub mod game_suit_prompt;
+pub mod hint;
+pub mod render;
+pub mod replay;
+pub mod save;
+pub mod solver;
+pub mod stats;
+#[cfg(test)]
+mod test_util;
+pub mod winnable;
+
+pub static TERMINAL: once_cell::sync::Lazy<Mutex<Terminal<CrosstermBackend<Stdout>>>> =
+    once_cell::sync::Lazy::new(|| {
+        let stdout = io::stdout();
+        let backend = CrosstermBackend::new(stdout);
+        let terminal = Terminal::new(backend).unwrap();
+
+        Mutex::new(terminal)
+    });
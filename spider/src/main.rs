@@ -1,48 +1,175 @@
-use std::{
-    io::{self, Stdout},
-    sync::Mutex,
-};
+use std::io;
 
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
-use game::Game;
-use game_suit_prompt::ask_for_game_suit_loop;
-use tui::{backend::CrosstermBackend, Terminal};
+use spider::{
+    card::set_ascii_suits,
+    config::GameConfig,
+    game::Game,
+    game_suit_prompt::{ask_for_game_suit_loop, ask_yes_no},
+    render::TuiRenderer,
+    replay, save,
+    solver::{solve, DEFAULT_MAX_DEPTH},
+    stats::{GameResult, Stats},
+    TERMINAL,
+};
 
-mod card;
-mod game;
-mod game_suit_prompt;
+fn main() -> Result<(), io::Error> {
+    let config = GameConfig::from_args();
+    set_ascii_suits(config.ascii);
 
-static TERMINAL: once_cell::sync::Lazy<Mutex<Terminal<CrosstermBackend<Stdout>>>> =
-    once_cell::sync::Lazy::new(|| {
-        let stdout = io::stdout();
-        let backend = CrosstermBackend::new(stdout);
-        let terminal = Terminal::new(backend).unwrap();
+    if config.write_config {
+        match config.write_config_file() {
+            Ok(path) => println!("Config written to {}", path.display()),
+            Err(err) => eprintln!("Can't write the config: {}", err),
+        }
 
-        Mutex::new(terminal)
-    });
+        return Ok(());
+    }
+
+    if config.stats {
+        print!("{}", Stats::load().to_table());
+
+        return Ok(());
+    }
+
+    if config.verify_history {
+        let game = match save::load() {
+            Some(saved) => saved.into_game(config),
+            None => {
+                println!("No saved game to verify");
+                return Ok(());
+            }
+        };
+        match game.history_discrepancy() {
+            None => println!("History of {} moves verified", game.moves_count()),
+            Some(i) if i == game.moves_count() => {
+                println!("The saved state does not match the replayed history")
+            }
+            Some(i) => {
+                let annotated = game.history_moves[i];
+                println!(
+                    "History diverges at move {}: {:?}, made at {} ms",
+                    i + 1,
+                    annotated.game_move,
+                    annotated.timestamp_ms
+                );
+            }
+        }
+
+        return Ok(());
+    }
+
+    // headless, no terminal setup needed
+    if config.solve {
+        let game = Game::new(config.game_suit.unwrap_or_default(), config);
+        print!("{}", game.render_to_string());
+        match solve(&game, DEFAULT_MAX_DEPTH) {
+            Some(moves) => {
+                for game_move in moves {
+                    println!("{:?}", game_move);
+                }
+            }
+            None => println!("No solution within {} moves", DEFAULT_MAX_DEPTH),
+        }
+
+        return Ok(());
+    }
+
+    // checked before the terminal setup, so the error could be seen
+    let replay = match &config.replay {
+        Some(path) => {
+            let replay = match replay::load(path) {
+                Ok(replay) => replay,
+                Err(err) => {
+                    eprintln!("Can't read the replay {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            };
+            if let Err((i, err)) = replay.verify(config.clone()) {
+                eprintln!("The replay move {} is not legal: {}", i + 1, err);
+                std::process::exit(1);
+            }
+            Some(replay)
+        }
+        None => None,
+    };
+
+    // read before the terminal setup, so a warning could be seen
+    let saved = match replay {
+        Some(_) => None,
+        None => save::load(),
+    };
 
-fn main() -> Result<(), io::Error> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     stdout.execute(EnterAlternateScreen)?;
     stdout.execute(EnableMouseCapture)?;
 
-    let game_suit = ask_for_game_suit_loop()?;
+    if let Some(replay) = replay {
+        let res = replay.run(config, &mut TuiRenderer::new());
+        if let Err(err) = res {
+            println!("{}", err)
+        }
 
-    if let Some(game_suit) = game_suit {
-        let mut game = Game::new(game_suit);
-        let res = game.run_game();
+        return restore_terminal();
+    }
+
+    let resume = match &saved {
+        Some(_) => ask_yes_no("Resume saved game?")?,
+        None => false,
+    };
+    let game = match saved {
+        Some(saved) if resume => Some(saved.into_game(config)),
+        saved => {
+            // the saved game is abandoned
+            if let Some(saved) = saved {
+                let mut stats = Stats::load();
+                stats.record(&GameResult::from(&saved));
+                let _ = stats.save();
+            }
+
+            let game_suit = match config.game_suit {
+                Some(game_suit) => Some(game_suit),
+                None => ask_for_game_suit_loop()?,
+            };
+            game_suit.map(|game_suit| Game::new(game_suit, config))
+        }
+    };
+
+    let mut save_err = None;
+    if let Some(mut game) = game {
+        let mut renderer = TuiRenderer::new();
+        let res = game.run_game(&mut renderer);
         if let Err(err) = res {
             println!("{}", err)
         }
+
+        // keep an unfinished game for the next launch
+        save_err = if game.test_win() || game.timed_out {
+            save::remove().err()
+        } else if game.move_count > 0 {
+            save::save(&game).err()
+        } else {
+            save::remove().err()
+        };
+    }
+
+    restore_terminal()?;
+
+    if let Some(err) = save_err {
+        eprintln!("warning: can't save the game: {}", err);
     }
 
-    // restore terminal
+    Ok(())
+}
+
+/// leave the alternate screen and the raw mode
+fn restore_terminal() -> Result<(), io::Error> {
     let mut terminal = TERMINAL.lock().unwrap();
     disable_raw_mode()?;
     terminal.backend_mut().execute(LeaveAlternateScreen)?;
@@ -0,0 +1,1002 @@
+use std::{
+    io::{self, Stdout},
+    sync::Mutex,
+    time::Duration,
+};
+
+use tui::{
+    backend::CrosstermBackend,
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Sparkline, Widget,
+    },
+    Frame, Terminal,
+};
+
+use crate::{
+    card::{ascii_suits, Card, Rank},
+    config::{CardBack, GameConfig},
+    daily,
+    game::{
+        test_point_in_rect, CardPosition, Game, GameMove, HistoryView, RUNS_TO_WIN, TIME_CRITICAL,
+        WIN_ANIMATION_DURATION, WIN_ANIMATION_FRAME,
+    },
+    stats::Stats,
+    TERMINAL,
+};
+
+/// the width and height of a card block, when there is room for it
+const CARD_SIZE: u16 = 8;
+/// below this card width the titles are shortened
+const NARROW_CARD_WIDTH: u16 = 6;
+/// the smallest terminal the board is drawn in
+const MIN_WIDTH: u16 = 60;
+const MIN_HEIGHT: u16 = 24;
+
+/// Render a game to some output.
+pub trait Renderer {
+    /// render the current state of the game
+    ///
+    /// returns where the stock, piles and cards were drawn,
+    /// which are used to decide what has been clicked
+    fn render(&mut self, game: &Game) -> io::Result<ScreenLayout>;
+}
+
+/// Where a render drew the parts of the game that can be clicked.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenLayout {
+    /// the stock, none when not drawn
+    pub stock: Option<Rect>,
+    /// the area of each tableau pile
+    pub piles: Vec<Rect>,
+    /// the drawn part of each card by pile, none for a face down card
+    pub cards: Vec<Vec<Option<Rect>>>,
+}
+
+impl ScreenLayout {
+    /// the drawn part of a card, none if it was not drawn face up
+    pub fn card_area(&self, pos: CardPosition) -> Option<Rect> {
+        *self.cards.get(pos.pile - 1)?.get(pos.card)?
+    }
+}
+
+/// Renderer drawing the game in the terminal with tui.
+pub struct TuiRenderer {
+    terminal: &'static Mutex<Terminal<CrosstermBackend<Stdout>>>,
+    /// each pile as last drawn, copied again while it is clean
+    piles: Vec<Option<DrawnPile>>,
+}
+
+/// A pile as last drawn.
+struct DrawnPile {
+    look: PileLook,
+    buffer: Buffer,
+    cards: Vec<Option<Rect>>,
+}
+
+/// What the drawing of a pile depends on besides its cards,
+/// the pile is drawn again when any of it changes.
+#[derive(Debug, Clone, PartialEq)]
+struct PileLook {
+    area: Rect,
+    /// the card under the keyboard cursor in the pile
+    selected: Option<usize>,
+    /// the first card picked to be moved in the pile
+    source: Option<usize>,
+    is_destination: bool,
+    /// the hinted move, if it is from or to the pile
+    hint: Option<GameMove>,
+    /// the mouse, if it is over the pile
+    mouse_pos: Option<(u16, u16)>,
+    ascii_suits: bool,
+}
+
+impl PileLook {
+    /// the look of a pile, 0-9, drawn in an area
+    fn of(game: &Game, pile: usize, area: Rect) -> Self {
+        let in_pile = |pos: Option<CardPosition>| pos.filter(|pos| pos.pile == pile + 1);
+
+        PileLook {
+            area,
+            selected: in_pile(game.selected).map(|pos| pos.card),
+            source: in_pile(game.source).map(|pos| pos.card),
+            is_destination: game.source_destinations().contains(&(pile + 1)),
+            hint: game
+                .hint
+                .map(|(game_move, _)| game_move)
+                .filter(|game_move| game_move.affects_pile(pile + 1)),
+            mouse_pos: game
+                .mouse_pos
+                .filter(|&(x, y)| test_point_in_rect(x, y, area)),
+            ascii_suits: ascii_suits(),
+        }
+    }
+}
+
+/// Widget copying the cells of a pile drawn before.
+struct CachedPile<'a>(&'a Buffer);
+
+impl Widget for CachedPile<'_> {
+    fn render(self, _area: Rect, buf: &mut Buffer) {
+        buf.merge(self.0);
+    }
+}
+
+/// Renderer building a text representation of the game,
+/// for inspecting a game without a terminal.
+#[derive(Default)]
+pub struct StringRenderer {
+    /// the output of the last render
+    pub output: String,
+}
+
+impl Renderer for StringRenderer {
+    fn render(&mut self, game: &Game) -> io::Result<ScreenLayout> {
+        self.output = game.render_to_string();
+
+        Ok(ScreenLayout::default())
+    }
+}
+
+impl Default for TuiRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TuiRenderer {
+    pub fn new() -> Self {
+        TuiRenderer {
+            terminal: &TERMINAL,
+            piles: Vec::new(),
+        }
+    }
+
+    /// render the stock ui,
+    /// a face down pile showing the number of deals left
+    ///
+    /// returns the area drawn
+    fn render_stock(game: &Game, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) -> Rect {
+        let mut area = area;
+        if area.height > 8 {
+            area.height = 8;
+        }
+        if area.width > 8 {
+            area.width = 8;
+        }
+
+        // greyed out once there is nothing left to deal
+        let style = if game.stock_exhausted() {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default()
+        };
+        let stock_block = Block::default()
+            .title("Stock")
+            .borders(Borders::ALL)
+            .style(style);
+        let inner = stock_block.inner(area);
+        let chunks = Layout::default()
+            .direction(tui::layout::Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(50),
+                    Constraint::Percentage(25),
+                ]
+                .as_ref(),
+            )
+            .margin(0)
+            .split(inner);
+        let left_block = Block::default()
+            .title(format!("{} left", game.deals_remaining()))
+            .borders(Borders::empty())
+            .style(style);
+
+        f.render_widget(stock_block, area);
+        if !game.stock_exhausted() {
+            if let Some(back) = Self::card_back(&game.config, inner) {
+                f.render_widget(back, inner);
+            }
+        }
+        f.render_widget(left_block, chunks[1]);
+
+        area
+    }
+
+    /// the back filling the inside of a face down card,
+    /// none for the plain backs
+    fn card_back(config: &GameConfig, inner: Rect) -> Option<Paragraph<'static>> {
+        if config.card_back == CardBack::Plain {
+            return None;
+        }
+
+        let row = if config.ascii { ":" } else { "░" }.repeat(inner.width as usize);
+        let pattern = vec![row.as_str(); inner.height as usize].join("\n");
+        let color = config.back_color();
+
+        Some(Paragraph::new(pattern).style(Style::default().fg(color)))
+    }
+
+    /// render the completed runs
+    ///
+    /// one small block per run,
+    /// only the count if the area is too small to hold them
+    fn render_foundations(game: &Game, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let mut area = area;
+        if area.height > 8 {
+            area.height = 8;
+        }
+
+        let foundation_block = Block::default()
+            .title(format!(
+                "Runs: {}/{}",
+                game.completed_run_count(),
+                RUNS_TO_WIN
+            ))
+            .borders(Borders::ALL);
+        let inner = foundation_block.inner(area);
+        f.render_widget(foundation_block, area);
+
+        let columns = (inner.width / 5) as usize;
+        let rows = (inner.height / 3) as usize;
+        if columns * rows < game.foundations.len() {
+            return;
+        }
+
+        for (i, suit) in game.foundations.iter().enumerate() {
+            let x = inner.x + (i % columns) as u16 * 5;
+            let y = inner.y + (i / columns) as u16 * 3;
+            let run_block = Block::default()
+                .title(suit.to_string())
+                .borders(Borders::ALL)
+                .style(Style::default().fg(game.config.suit_color(*suit)));
+            f.render_widget(run_block, Rect::new(x, y, 5, 3));
+        }
+    }
+
+    /// render a popup in the middle of the area
+    fn render_popup(title: &str, text: &str, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let width = (text.len().max(title.len()) as u16 + 4).min(area.width);
+        let height = 3.min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick);
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(text)
+                .block(block)
+                .alignment(Alignment::Center),
+            popup,
+        );
+    }
+
+    /// render the win screen in the middle of the area,
+    /// with the score evolution and what to do next
+    fn render_win(game: &Game, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let width = 48.min(area.width);
+        let height = 14.min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = Block::default()
+            .title(format!("You win! {}", deal_label(game)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick);
+        let inner = block.inner(popup);
+        f.render_widget(Clear, popup);
+        f.render_widget(block, popup);
+
+        let chunks = Layout::default()
+            .direction(tui::layout::Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(2),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(inner);
+
+        let summary = format!(
+            "Time: {}  Moves: {}  Undos: {}  Best run: {}\nScore: {}  Suits: {}",
+            format_elapsed(game.elapsed()),
+            game.move_count,
+            game.num_undos_made(),
+            game.best_run,
+            game.score,
+            game.game_suit
+        );
+        f.render_widget(Paragraph::new(summary), chunks[0]);
+        f.render_widget(
+            Paragraph::new("n: New game  N: Same suits  u: Undo  q: Quit"),
+            chunks[2],
+        );
+
+        let graph_block = Block::default()
+            .title(format!(
+                "Score {:.1}/move, peak {:.1}/move",
+                game.score_per_move(),
+                game.peak_score_per_move()
+            ))
+            .borders(Borders::TOP);
+
+        // one sample per column, spread over all the moves
+        let moves = game.move_count as usize + 1;
+        let samples = (graph_block.inner(chunks[1]).width as usize).clamp(1, moves);
+        let data: Vec<u64> = (0..samples)
+            .filter_map(|i| game.score_at_move(i * (moves - 1) / (samples - 1).max(1)))
+            .map(u64::from)
+            .collect();
+
+        f.render_widget(
+            Sparkline::default()
+                .block(graph_block)
+                .data(&data)
+                .style(Style::default().fg(Color::Yellow)),
+            chunks[1],
+        );
+    }
+
+    /// render the completed runs bouncing across the area,
+    /// each king leaving a trail of cards behind it
+    ///
+    /// the runs leave one after the other,
+    /// every frame is drawn from the elapsed time alone
+    fn render_cascade(
+        game: &Game,
+        elapsed: Duration,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) {
+        if area.width < 8 || area.height < 8 {
+            return;
+        }
+
+        let frames = (elapsed.as_millis() / WIN_ANIMATION_FRAME.as_millis()) as i32;
+        let runs = game.foundations.len().max(1) as i32;
+        let spread = (WIN_ANIMATION_DURATION.as_millis() / WIN_ANIMATION_FRAME.as_millis()) as i32
+            / 2
+            / runs;
+        let floor = (area.height - 8) as i32;
+        let right = (area.width - 8) as i32;
+
+        for (i, suit) in game.foundations.iter().enumerate() {
+            let i = i as i32;
+            let steps = frames - i * spread;
+            if steps < 0 {
+                continue;
+            }
+
+            let card = Card {
+                suit: *suit,
+                rank: Rank::King,
+            };
+            let style = Style::default().fg(game.config.suit_color(*suit));
+            // the runs leave from where they are shown, to both sides
+            let (mut x, mut y) = ((i * 5).min(right), 0);
+            let dx = if i % 2 == 0 { 2 } else { -2 };
+            let mut dy = 0;
+
+            for _ in 0..=steps {
+                let rect = Rect::new(area.x + x as u16, area.y + y as u16, 8, 8);
+                f.render_widget(Clear, rect);
+                f.render_widget(
+                    Block::default()
+                        .title(card.display_compact())
+                        .borders(Borders::ALL)
+                        .style(style),
+                    rect,
+                );
+
+                x += dx;
+                if !(0..=right).contains(&x) {
+                    break;
+                }
+                dy += 1;
+                y = (y + dy).max(0);
+                // bounce on the bottom, losing some height
+                if y > floor {
+                    y = floor;
+                    dy = -dy * 3 / 4;
+                }
+            }
+        }
+    }
+
+    /// render the keys and rules in the middle of the area
+    fn render_help(game: &Game, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let text = game.help_text();
+        let text_width = text.lines().map(|line| line.len()).max().unwrap_or(0);
+        let width = (text_width as u16 + 2).min(area.width);
+        let height = (text.lines().count() as u16 + 2).min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = Block::default()
+            .title("Help, any key to close")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick);
+        f.render_widget(Clear, popup);
+        f.render_widget(Paragraph::new(text).block(block), popup);
+    }
+
+    /// render the move history in the middle of the area,
+    /// scrolled to the line under the cursor
+    fn render_history(
+        history_view: &HistoryView,
+        area: Rect,
+        f: &mut Frame<CrosstermBackend<Stdout>>,
+    ) {
+        let title = "Moves, newest first, Esc to close";
+        let text_width = history_view
+            .lines
+            .iter()
+            .map(|line| line.chars().count())
+            .chain([title.len()])
+            .max()
+            .unwrap_or(0);
+        let width = (text_width as u16 + 4).min(area.width);
+        let height = (history_view.lines.len().max(1) as u16 + 2).min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick);
+        f.render_widget(Clear, popup);
+
+        if history_view.lines.is_empty() {
+            f.render_widget(Paragraph::new("No move yet").block(block), popup);
+            return;
+        }
+
+        let items: Vec<ListItem> = history_view
+            .lines
+            .iter()
+            .map(|line| ListItem::new(line.as_str()))
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        state.select(Some(history_view.selected));
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    /// render the statistics table in the middle of the area
+    fn render_stats(stats: &Stats, area: Rect, f: &mut Frame<CrosstermBackend<Stdout>>) {
+        let table = stats.to_table();
+        let text_width = table.lines().map(|line| line.len()).max().unwrap_or(0);
+        let width = (text_width as u16 + 2).min(area.width);
+        let height = (table.lines().count() as u16 + 2).min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = Block::default()
+            .title("Statistics, any key to close")
+            .borders(Borders::ALL);
+        f.render_widget(Clear, popup);
+        f.render_widget(Paragraph::new(table).block(block), popup);
+    }
+
+    /// render the tableau
+    ///
+    /// returns the drawn part of each face up card of the pile
+    fn render_pile(game: &Game, pile: usize, area: Rect, buf: &mut Buffer) -> Vec<Option<Rect>> {
+        // the card index under the keyboard cursor in this pile
+        let selected = match game.selected {
+            Some(selected) if selected.pile == pile + 1 => Some(selected.card),
+            _ => None,
+        };
+        // the first card picked to be moved in this pile
+        let source = match game.source {
+            Some(source) if source.pile == pile + 1 => Some(source.card),
+            _ => None,
+        };
+        // the pile the picked cards could go to
+        let is_destination = game.source_destinations().contains(&(pile + 1));
+        // the cards of the hinted move in this pile,
+        // the moved run or the card it goes onto
+        let hinted = |index: usize| match game.hint {
+            Some((GameMove::MoveCard { src, dst, .. }, _)) => {
+                (src.pile == pile + 1 && index >= src.card)
+                    || (dst.pile == pile + 1 && index + 1 == dst.card)
+            }
+            _ => false,
+        };
+        let hint_empty = matches!(
+            game.hint,
+            Some((GameMove::MoveCard { dst, .. }, _)) if dst.pile == pile + 1 && dst.card == 0
+        );
+        let hint_style = Style::default().fg(Color::Yellow);
+        let destination_style = Style::default().fg(Color::Green);
+        let mouse_pos = game.mouse_pos;
+        let pile = &game.tableau[pile];
+
+        // a column of space between the piles, when there is enough
+        let width = if area.width > CARD_SIZE {
+            CARD_SIZE
+        } else {
+            area.width
+                .saturating_sub(1)
+                .max(NARROW_CARD_WIDTH.min(area.width))
+        };
+
+        let n = pile.len();
+        let mut areas = vec![None; n];
+        // the cards past the bottom of the pile area are cut
+        let pile_area = area;
+
+        if n == 0 {
+            let area = match clip(Rect::new(area.x, area.y, width, CARD_SIZE), pile_area) {
+                Some(area) => area,
+                None => return areas,
+            };
+            let mut card_block = Block::default().title("Empty").borders(Borders::ALL);
+            if selected.is_some() {
+                card_block = card_block.border_type(BorderType::Thick);
+            }
+            if is_destination {
+                card_block = card_block.border_style(destination_style);
+            }
+            if hint_empty {
+                card_block = card_block.border_style(hint_style);
+            }
+
+            card_block.render(area, buf);
+
+            return areas;
+        }
+
+        // the top card is drawn whole, the others as a strip above it,
+        // two rows each or one row if the pile would not fit
+        let top_height = CARD_SIZE.min(pile_area.height);
+        let rows = (pile_area.height - top_height) as usize;
+        // the face down cards are folded into a single block with their count,
+        // so are the deepest face up cards when there is still no room
+        let face_down = pile.iter().take_while(|card| !card.is_up).count();
+        let folded = face_down.max(n.saturating_sub(rows)).min(n - 1);
+        let strips = n - 1 - folded + usize::from(folded > 0);
+        let offset = if strips * 2 <= rows { 2 } else { 1 };
+
+        let strip_borders = Borders::LEFT | Borders::RIGHT | Borders::TOP;
+        let mut y = pile_area.y;
+
+        if folded > 0 && rows > 0 {
+            let mark = if game.config.ascii { "##" } else { "▒▒" };
+            let title = if folded > face_down {
+                format!("{} {}+{}", mark, face_down, folded - face_down)
+            } else {
+                format!("{} {}", mark, folded)
+            };
+            let mut block = Block::default()
+                .title(title)
+                .borders(strip_borders)
+                .style(Style::default().fg(Color::DarkGray));
+            if selected.is_some_and(|selected| selected < folded) {
+                block = block.border_type(BorderType::Thick);
+            } else if source.is_some_and(|source| source < folded) {
+                block = block.border_type(BorderType::Double);
+            }
+            if (0..folded).any(hinted) {
+                block = block.border_style(hint_style);
+            }
+
+            let rect = Rect::new(area.x, y, width, offset);
+            let inner = block.inner(rect);
+            block.render(rect, buf);
+            if let Some(back) = Self::card_back(&game.config, inner) {
+                back.render(inner, buf);
+            }
+            y += offset;
+        }
+
+        for (index, card) in pile.iter().enumerate().skip(folded) {
+            let is_top = index == n - 1;
+            let area = Rect::new(area.x, y, width, if is_top { top_height } else { offset });
+            y += offset;
+
+            // the click area is what is drawn of the card
+            let title = if card.is_up {
+                areas[index] = Some(area);
+                if width < NARROW_CARD_WIDTH {
+                    card.card.display_short()
+                } else {
+                    card.card.display_compact()
+                }
+            } else {
+                String::from("")
+            };
+
+            // the card a click would pick, the same test as the click
+            let hovered =
+                card.is_movable() && mouse_pos.is_some_and(|(x, y)| test_point_in_rect(x, y, area));
+
+            // the title of the card under the cursor is reversed,
+            // so it stands out whatever the colors
+            let title = if selected == Some(index) {
+                Span::styled(title, Style::default().add_modifier(Modifier::REVERSED))
+            } else if hovered {
+                Span::styled(title, Style::default().add_modifier(Modifier::BOLD))
+            } else {
+                Span::raw(title)
+            };
+            let mut card_block = Block::default().title(title).borders(if is_top {
+                Borders::ALL
+            } else {
+                strip_borders
+            });
+
+            if card.is_up {
+                card_block =
+                    card_block.style(Style::default().fg(game.config.suit_color(card.card.suit)));
+            }
+            if selected == Some(index) {
+                card_block = card_block.border_type(BorderType::Thick);
+            } else if source.is_some_and(|source| index >= source) {
+                card_block = card_block.border_type(BorderType::Double);
+            }
+            if hovered {
+                card_block = card_block.border_style(Style::default().fg(Color::Cyan));
+            }
+            if is_top && is_destination {
+                card_block = card_block.border_style(destination_style);
+            }
+            if hinted(index) {
+                card_block = card_block.border_style(hint_style);
+            }
+
+            card_block.render(area, buf);
+        }
+
+        areas
+    }
+}
+
+impl Renderer for TuiRenderer {
+    /// Render the game ui
+    fn render(&mut self, game: &Game) -> io::Result<ScreenLayout> {
+        let mut terminal = self.terminal.lock().unwrap();
+        let piles = &mut self.piles;
+        piles.resize_with(10, || None);
+
+        let mut layout = ScreenLayout::default();
+
+        terminal.draw(|f| {
+            let size = f.size();
+
+            // nothing is drawn, so nothing can be clicked either
+            if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+                let text = format!(
+                    "Terminal too small, {}x{} needed, {}x{} now",
+                    MIN_WIDTH, MIN_HEIGHT, size.width, size.height
+                );
+                f.render_widget(Paragraph::new(text).alignment(Alignment::Center), size);
+                return;
+            }
+
+            // the undo and redo keys are greyed out when there is nothing to do
+            let key_style = |enabled: bool| {
+                Style::default().fg(if enabled {
+                    Color::White
+                } else {
+                    Color::DarkGray
+                })
+            };
+            let title = Spans::from(vec![
+                Span::raw(format!(
+                    "Spider  {}  Moves: {}  Undos: {}  Best run: {}  Deals left: {}  Time: {}  ",
+                    deal_label(game),
+                    game.num_moves_made(),
+                    game.num_undos_made(),
+                    game.best_run,
+                    game.deals_remaining(),
+                    format_elapsed(game.elapsed())
+                )),
+                Span::styled(
+                    "[U]ndo",
+                    key_style(game.can_undo() && game.undos_remaining() != Some(0)),
+                ),
+                Span::raw(" "),
+                Span::styled("[R]edo", key_style(game.can_redo())),
+            ]);
+            let outer_block = Block::default().title(title).borders(Borders::ALL);
+            let new_size = outer_block.inner(size);
+            f.render_widget(outer_block, size);
+
+            // the countdown in the top right corner, on the border
+            if let Some(remaining) = game.time_remaining() {
+                let mut style = Style::default();
+                if remaining < TIME_CRITICAL {
+                    style = style.fg(Color::Red);
+                    // pulse once a second
+                    if remaining.as_secs() % 2 == 0 {
+                        style = style.bg(Color::DarkGray);
+                    }
+                } else if game.time_pressure_mode() {
+                    style = style.fg(Color::Yellow);
+                }
+
+                let text = format!(" Left: {} ", format_elapsed(remaining));
+                let width = (text.chars().count() as u16).min(size.width.saturating_sub(2));
+                let area = Rect::new(size.x + size.width - 1 - width, size.y, width, 1);
+                f.render_widget(Paragraph::new(Span::styled(text, style)), area);
+            }
+            let size = new_size;
+
+            // the status message on the last line
+            if let Some((status_message, _)) = game.status_message.clone() {
+                if size.height > 0 {
+                    let status_area = Rect::new(size.x, size.y + size.height - 1, size.width, 1);
+                    f.render_widget(Paragraph::new(status_message), status_area);
+                }
+            }
+
+            let stock_tableau_chunks = Layout::default()
+                .direction(tui::layout::Direction::Vertical)
+                .margin(1)
+                .constraints([Constraint::Length(10), Constraint::Min(0)].as_ref())
+                .split(size);
+
+            let stock_chunks = Layout::default()
+                .direction(tui::layout::Direction::Horizontal)
+                .margin(1)
+                .constraints([Constraint::Min(0), Constraint::Length(10)].as_ref())
+                .split(stock_tableau_chunks[0]);
+
+            // the piles share the width, up to a card and a gap each
+            let pile_width =
+                (stock_tableau_chunks[1].width.saturating_sub(2) / 10).min(CARD_SIZE + 2);
+            let mut tableau_constraint = Vec::new();
+            for _ in 0..10 {
+                tableau_constraint.push(Constraint::Length(pile_width));
+            }
+            let tableau_chunks = Layout::default()
+                .direction(tui::layout::Direction::Horizontal)
+                .margin(1)
+                .constraints(tableau_constraint.clone())
+                .split(stock_tableau_chunks[1]);
+
+            layout.stock = Some(Self::render_stock(game, stock_chunks[1], f));
+            Self::render_foundations(game, stock_chunks[0], f);
+            // the clean piles are copied from the last render
+            layout.cards = (0..10)
+                .map(|i| {
+                    let area = tableau_chunks[i];
+                    let look = PileLook::of(game, i, area);
+                    let is_clean = !game.force_full_redraw
+                        && !game.dirty_piles.contains(&(i + 1))
+                        && piles[i].as_ref().is_some_and(|drawn| drawn.look == look);
+                    if !is_clean {
+                        let mut buffer = Buffer::empty(area);
+                        let cards = Self::render_pile(game, i, area, &mut buffer);
+                        piles[i] = Some(DrawnPile {
+                            look,
+                            buffer,
+                            cards,
+                        });
+                    }
+
+                    let drawn = piles[i].as_ref().unwrap();
+                    f.render_widget(CachedPile(&drawn.buffer), area);
+                    drawn.cards.clone()
+                })
+                .collect();
+            layout.piles = tableau_chunks;
+
+            if game.timed_out {
+                Self::render_popup(
+                    "Time's Up!",
+                    "r: Restart this deal  n: New game  q: Quit",
+                    size,
+                    f,
+                );
+            } else if game.deadlocked {
+                Self::render_popup(
+                    "No moves left",
+                    "u: Undo  r: Restart this deal  n: New game",
+                    size,
+                    f,
+                );
+            } else if let Some(confirm) = game.confirm {
+                Self::render_popup(confirm.question(), "y: Yes  any other key: No", size, f);
+            } else if game.help_opened.is_some() {
+                Self::render_help(game, size, f);
+            } else if let Some(history_view) = &game.history_view {
+                Self::render_history(history_view, size, f);
+            } else if let Some(stats) = &game.stats {
+                Self::render_stats(stats, size, f);
+            } else if let Some(start) = game.win_animation {
+                Self::render_cascade(game, start.elapsed(), size, f);
+            } else if game.test_win() {
+                Self::render_win(game, size, f);
+            }
+        })?;
+        drop(terminal);
+
+        Ok(layout)
+    }
+}
+
+/// the part of a rect inside the bounds, none if it is all outside
+fn clip(rect: Rect, bounds: Rect) -> Option<Rect> {
+    if rect.intersects(bounds) {
+        Some(rect.intersection(bounds))
+    } else {
+        None
+    }
+}
+
+/// the daily deal date, or the seed to play the deal again
+fn deal_label(game: &Game) -> String {
+    match game.daily {
+        Some(day) => format!("Daily {}", daily::date_string(day)),
+        None if game.config.winnable => format!("Seed: {} winnable", game.seed),
+        None => format!("Seed: {}", game.seed),
+    }
+}
+
+/// format a duration as mm:ss
+pub(crate) fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::GameSuitNumber,
+        test_util::{game_from, move_to},
+    };
+
+    fn rendered(game: &Game) -> String {
+        let mut renderer = StringRenderer::default();
+        renderer.render(game).unwrap();
+
+        renderer.output
+    }
+
+    /// the line of a tableau pile, 1-10
+    fn pile_line(output: &str, pile: usize) -> String {
+        output.lines().nth(2 + pile).unwrap().to_string()
+    }
+
+    fn fresh_game(seed: u64) -> Game {
+        let config = GameConfig {
+            seed: Some(seed),
+            no_animation: true,
+            ..GameConfig::default()
+        };
+
+        Game::new(GameSuitNumber::One, config)
+    }
+
+    #[test]
+    fn empty_tableau_has_ten_empty_piles() {
+        let output = rendered(&game_from(GameSuitNumber::One, &[]));
+
+        for pile in 1..=10 {
+            assert_eq!(pile_line(&output, pile), format!("{:>2}:", pile));
+        }
+    }
+
+    #[test]
+    fn face_down_cards_are_hidden() {
+        let output = rendered(&game_from(GameSuitNumber::Two, &["ks qh JS"]));
+
+        assert_eq!(pile_line(&output, 1), " 1: ## ## JS");
+    }
+
+    #[test]
+    fn fresh_deal_shows_a_full_stock() {
+        let output = rendered(&fresh_game(0));
+
+        assert!(output.contains("Stock: 50 cards, 5 deals left\n"));
+    }
+
+    #[test]
+    fn fresh_deal_shows_only_the_top_cards() {
+        let output = rendered(&fresh_game(0));
+
+        for pile in 1..=10 {
+            let line = pile_line(&output, pile);
+            let hidden = if pile <= 4 { 5 } else { 4 };
+            assert_eq!(line.matches("##").count(), hidden, "{}", line);
+            assert_eq!(line.split_whitespace().count(), hidden + 2, "{}", line);
+        }
+    }
+
+    #[test]
+    fn dealing_a_row_empties_the_stock_by_ten() {
+        let mut game = fresh_game(0);
+
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+        let output = rendered(&game);
+
+        assert!(output.contains("Stock: 40 cards, 4 deals left\n"));
+        assert_eq!(pile_line(&output, 10).split_whitespace().count(), 7);
+    }
+
+    #[test]
+    fn hand_made_game_has_an_empty_stock() {
+        let output = rendered(&game_from(GameSuitNumber::One, &["KS"]));
+
+        assert!(output.contains("Stock: 0 cards, 0 deals left\n"));
+    }
+
+    #[test]
+    fn seed_is_shown_first() {
+        let output = rendered(&fresh_game(42));
+
+        assert_eq!(output.lines().next(), Some("Seed: 42"));
+    }
+
+    #[test]
+    fn moved_card_shows_on_its_new_pile() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "ks 5H"]);
+
+        assert!(game.do_move(move_to(&game, 2, 1, 1)).is_ok());
+        let output = rendered(&game);
+
+        assert_eq!(pile_line(&output, 1), " 1: 6S 5H");
+        assert_eq!(pile_line(&output, 2), " 2: KS");
+    }
+
+    #[test]
+    fn undone_move_shows_the_piles_as_before() {
+        let mut game = game_from(GameSuitNumber::Two, &["6S", "ks 5H"]);
+        let before = rendered(&game);
+
+        assert!(game.do_move(move_to(&game, 2, 1, 1)).is_ok());
+        game.undo_once();
+
+        assert_eq!(rendered(&game), before);
+    }
+
+    #[test]
+    fn completed_run_is_counted_and_removed() {
+        let mut game = game_from(
+            GameSuitNumber::One,
+            &["ks KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S", "AS"],
+        );
+        assert!(rendered(&game).contains("Runs: 0/8\n"));
+
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        let output = rendered(&game);
+
+        assert!(output.contains("Runs: 1/8\n"));
+        assert_eq!(pile_line(&output, 1), " 1: KS");
+        assert_eq!(pile_line(&output, 2), " 2:");
+    }
+}
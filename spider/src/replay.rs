@@ -0,0 +1,247 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::{self, Event, KeyCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    card::{GameCard, GameSuitNumber},
+    config::GameConfig,
+    game::{Deal, Game, GameMove, MoveError},
+    render::Renderer,
+    save::data_path,
+};
+
+/// the version of the replay format,
+/// a replay of another version can't be played
+const REPLAY_VERSION: u32 = 1;
+
+/// A game as written to a replay file,
+/// the deal and the moves made on it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub game_suit: GameSuitNumber,
+    pub seed: u64,
+    pub daily: Option<u64>,
+    /// the dealt tableau, kept along the seed
+    /// so the replay does not depend on the shuffle
+    pub tableau: Vec<Vec<GameCard>>,
+    pub stock: Vec<GameCard>,
+    /// the moves of the player,
+    /// the completed runs are made again by the move before them
+    pub moves: Vec<GameMove>,
+}
+
+impl From<&Game> for Replay {
+    fn from(game: &Game) -> Self {
+        let deal = game.initial_state.clone();
+
+        Replay {
+            version: REPLAY_VERSION,
+            game_suit: game.game_suit,
+            seed: game.seed,
+            daily: game.daily,
+            tableau: deal.tableau,
+            stock: deal.stock,
+            moves: game
+                .history_moves
+                .iter()
+                .map(|annotated| annotated.game_move)
+                .filter(|game_move| !matches!(game_move, GameMove::CompleteRun { .. }))
+                .collect(),
+        }
+    }
+}
+
+impl Replay {
+    /// the game as it was dealt, before any move
+    pub fn to_game(&self, config: GameConfig) -> Game {
+        let deal = Deal {
+            tableau: self.tableau.clone(),
+            stock: self.stock.clone(),
+        };
+        let mut game = Game::from_deal(self.game_suit, self.seed, deal, config);
+        game.daily = self.daily;
+
+        game
+    }
+
+    /// make every move again from the deal,
+    /// the game at the end or the first move that is not legal
+    pub fn verify(&self, config: GameConfig) -> Result<Game, (usize, MoveError)> {
+        let mut game = self.to_game(config);
+        for (i, game_move) in self.moves.iter().enumerate() {
+            game.do_move(*game_move).map_err(|err| (i, err))?;
+        }
+
+        Ok(game)
+    }
+
+    /// step through the moves in the terminal,
+    /// Space or Right for the next one, Left for the one before
+    pub fn run(&self, config: GameConfig, renderer: &mut dyn Renderer) -> crossterm::Result<()> {
+        let mut game = self.to_game(config);
+        let mut step = 0;
+
+        loop {
+            game.set_status(format!(
+                "Replay move {}/{}  Space, Right: next  Left: back  q: quit",
+                step,
+                self.moves.len()
+            ));
+            renderer.render(&game)?;
+            game.clear_dirty();
+
+            let key = match event::read()? {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Char(' ') | KeyCode::Right if step < self.moves.len() => {
+                    // checked to be legal before the replay starts
+                    let _ = game.do_move(self.moves[step]);
+                    step += 1;
+                }
+                KeyCode::Left if step > 0 => {
+                    game.undo_once();
+                    step -= 1;
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// `~/.local/share/rgames/spider_replay.json`
+pub fn replay_path() -> Option<PathBuf> {
+    data_path("spider_replay.json")
+}
+
+/// write the game to the replay file
+pub fn export(game: &Game) -> io::Result<PathBuf> {
+    let path = replay_path().ok_or_else(|| io::Error::other("unknown home directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let json = serde_json::to_string(&Replay::from(game))?;
+    std::fs::write(&path, json)?;
+
+    Ok(path)
+}
+
+/// read a replay file
+pub fn load(path: &Path) -> io::Result<Replay> {
+    let content = std::fs::read_to_string(path)?;
+    let replay: Replay = serde_json::from_str(&content)?;
+    if replay.version != REPLAY_VERSION {
+        return Err(io::Error::other(format!(
+            "unsupported replay version {}",
+            replay.version
+        )));
+    }
+
+    Ok(replay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{game_from, move_to};
+
+    /// a seeded game played for some moves, the first legal one each time
+    fn scripted_game() -> Game {
+        let config = GameConfig {
+            seed: Some(11),
+            no_animation: true,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(GameSuitNumber::One, config);
+        for _ in 0..40 {
+            let game_move = match game.legal_moves().first() {
+                Some(&game_move) => game_move,
+                None => break,
+            };
+            assert!(game.do_move(game_move).is_ok());
+        }
+
+        game
+    }
+
+    /// the replay of a game written and read back as json
+    fn exported(game: &Game) -> Replay {
+        let json = serde_json::to_string(&Replay::from(game)).unwrap();
+
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn replay_reproduces_the_final_tableau() {
+        let game = scripted_game();
+        assert!(!game.history_moves.is_empty());
+
+        let replayed = exported(&game).verify(game.config.clone()).ok().unwrap();
+
+        assert_eq!(replayed.debug_tableau_string(), game.debug_tableau_string());
+        assert_eq!(replayed.history_moves.len(), game.history_moves.len());
+        assert_eq!(replayed.state_hash(), game.state_hash());
+    }
+
+    #[test]
+    fn replay_makes_the_completed_runs_again() {
+        let run = "KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S";
+        let mut game = game_from(GameSuitNumber::One, &[run, "AS", "9S"]);
+        assert!(game.do_move(move_to(&game, 2, 0, 1)).is_ok());
+        assert_eq!(game.completed_run_count(), 1);
+
+        let replay = exported(&game);
+        assert_eq!(replay.moves.len(), 1);
+
+        let replayed = replay.verify(game.config.clone()).ok().unwrap();
+        assert_eq!(replayed.completed_run_count(), 1);
+        assert_eq!(replayed.debug_tableau_string(), game.debug_tableau_string());
+    }
+
+    #[test]
+    fn replay_starts_from_the_deal() {
+        let game = scripted_game();
+
+        let dealt = exported(&game).to_game(game.config.clone());
+
+        assert_eq!(
+            dealt.debug_tableau_string(),
+            game.initial_deal().debug_tableau_string()
+        );
+        assert!(dealt.history_moves.is_empty());
+    }
+
+    #[test]
+    fn illegal_move_is_reported_with_its_index() {
+        let game = scripted_game();
+        let mut replay = exported(&game);
+        let illegal = move_to(&game, 1, 0, 2);
+        replay.moves.insert(1, illegal);
+
+        assert!(matches!(replay.verify(game.config.clone()), Err((1, _))));
+    }
+
+    #[test]
+    fn other_version_is_not_loaded() {
+        let game = scripted_game();
+        let mut replay = Replay::from(&game);
+        let path = std::env::temp_dir().join("spider-replay-test-version.json");
+
+        std::fs::write(&path, serde_json::to_string(&replay).unwrap()).unwrap();
+        assert_eq!(load(&path).unwrap().moves, replay.moves);
+
+        replay.version = REPLAY_VERSION + 1;
+        std::fs::write(&path, serde_json::to_string(&replay).unwrap()).unwrap();
+        assert!(load(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    card::{GameCard, GameSuitNumber, Suit},
+    config::GameConfig,
+    game::{now_millis, AnnotatedMove, Deal, Game, GameMove},
+};
+
+/// the version of the save format,
+/// a save of another version can't be resumed
+const SAVE_VERSION: u32 = 4;
+
+/// A game in progress, as written to the save file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub version: u32,
+    pub game_suit: GameSuitNumber,
+    pub seed: u64,
+    pub daily: Option<u64>,
+    /// the cards as dealt, not always the shuffle of the seed
+    pub initial_state: Deal,
+    pub tableau: Vec<Vec<GameCard>>,
+    pub stock: Vec<GameCard>,
+    pub foundations: Vec<Suit>,
+    pub score: u32,
+    pub score_history: Vec<(u32, u32)>,
+    pub move_count: u32,
+    #[serde(default)]
+    pub undo_count: u32,
+    #[serde(default)]
+    pub best_run: u32,
+    /// the undo rules the game was started with,
+    /// kept so a resumed game can't get more undos
+    pub undo_limit: Option<u32>,
+    pub undo_penalty: Option<u32>,
+    pub history_moves: Vec<AnnotatedMove>,
+    pub redo_moves: Vec<GameMove>,
+    /// the time played, none if no move has been made
+    pub elapsed_millis: Option<u64>,
+}
+
+impl From<&Game> for SavedGame {
+    fn from(game: &Game) -> Self {
+        SavedGame {
+            version: SAVE_VERSION,
+            game_suit: game.game_suit,
+            seed: game.seed,
+            daily: game.daily,
+            initial_state: game.initial_state.clone(),
+            tableau: game.tableau.clone(),
+            stock: game.stock.clone(),
+            foundations: game.foundations.clone(),
+            score: game.score,
+            score_history: game.score_history.clone(),
+            move_count: game.move_count,
+            undo_count: game.undo_count,
+            best_run: game.best_run,
+            undo_limit: game.config.undo_limit,
+            undo_penalty: game.config.undo_penalty,
+            history_moves: game.history_moves.clone(),
+            redo_moves: game.redo_moves.clone(),
+            elapsed_millis: game.start_time.map(|_| game.elapsed().as_millis() as u64),
+        }
+    }
+}
+
+impl SavedGame {
+    /// the saved game, with the clock running again from the saved time
+    ///
+    /// the undo rules are the saved ones, not those of the config
+    pub fn into_game(self, mut config: GameConfig) -> Game {
+        config.undo_limit = self.undo_limit;
+        config.undo_penalty = self.undo_penalty;
+        let mut game = Game::from_deal(self.game_suit, self.seed, self.initial_state, config);
+
+        game.daily = self.daily;
+        game.tableau = self.tableau;
+        game.stock = self.stock;
+        game.foundations = self.foundations;
+        game.score = self.score;
+        game.score_history = self.score_history;
+        game.move_count = self.move_count;
+        game.undo_count = self.undo_count;
+        game.best_run = game.best_run.max(self.best_run);
+        game.history_moves = self.history_moves;
+        game.redo_moves = self.redo_moves;
+        game.start_time = self
+            .elapsed_millis
+            .map(|elapsed| now_millis() - elapsed as u128);
+
+        game
+    }
+}
+
+/// a file in `~/.local/share/rgames`,
+/// none if the home directory is unknown
+pub fn data_path(name: &str) -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+
+    Some(Path::new(&home).join(".local/share/rgames").join(name))
+}
+
+/// `~/.local/share/rgames/spider_save.json`
+pub fn save_path() -> Option<PathBuf> {
+    data_path("spider_save.json")
+}
+
+/// write the game to the save file
+pub fn save(game: &Game) -> io::Result<()> {
+    let path = save_path().ok_or_else(|| io::Error::other("unknown home directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let json = serde_json::to_string(&SavedGame::from(game))?;
+    std::fs::write(path, json)
+}
+
+/// remove the save file, if any
+pub fn remove() -> io::Result<()> {
+    match save_path() {
+        Some(path) if path.exists() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// read the save file, none if there is no save
+///
+/// a save that can't be read is moved aside with a warning,
+/// so it does not come back on every launch
+pub fn load() -> Option<SavedGame> {
+    let path = save_path()?;
+    let content = std::fs::read_to_string(&path).ok()?;
+
+    match serde_json::from_str::<SavedGame>(&content) {
+        Ok(saved) if saved.version == SAVE_VERSION => Some(saved),
+        _ => {
+            let aside = path.with_extension("json.corrupt");
+            eprintln!(
+                "warning: can't read the saved game, moved to {}",
+                aside.display()
+            );
+            let _ = std::fs::rename(&path, aside);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::stats::GameResult;
+
+    /// the saved game written and read back
+    fn round_trip(game: &Game) -> SavedGame {
+        let json = serde_json::to_string(&SavedGame::from(game)).unwrap();
+
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn winnable_deal_resumes_without_the_flag() {
+        let config = GameConfig {
+            seed: Some(3),
+            winnable: true,
+            no_animation: true,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(GameSuitNumber::Two, config);
+        let dealt = game.debug_tableau_string();
+        for _ in 0..4 {
+            let game_move = game.legal_moves()[0];
+            assert!(game.do_move(game_move).is_ok());
+        }
+
+        let mut resumed = round_trip(&game).into_game(GameConfig::default());
+
+        assert_eq!(resumed.debug_tableau_string(), game.debug_tableau_string());
+        assert_eq!(resumed.initial_deal().debug_tableau_string(), dealt);
+        assert!(resumed.verify_history_integrity());
+        resumed.undo_all();
+        assert_eq!(resumed.debug_tableau_string(), dealt);
+    }
+
+    /// a game of two suits a few moves and a deal in,
+    /// with a move undone
+    fn game_in_progress() -> Game {
+        let config = GameConfig {
+            seed: Some(8),
+            no_animation: true,
+            ..GameConfig::default()
+        };
+        let mut game = Game::new(GameSuitNumber::Two, config);
+        for _ in 0..3 {
+            let game_move = game.legal_moves()[0];
+            assert!(game.do_move(game_move).is_ok());
+        }
+        assert!(game.do_move(GameMove::DealRow).is_ok());
+        let game_move = game.legal_moves()[0];
+        assert!(game.do_move(game_move).is_ok());
+        game.undo_once();
+        game.undo_count = 1;
+
+        game
+    }
+
+    fn stock_string(game: &Game) -> String {
+        game.stock
+            .iter()
+            .map(|card| card.card.to_notation())
+            .collect()
+    }
+
+    #[test]
+    fn game_resumes_as_saved() {
+        let game = game_in_progress();
+
+        let saved = round_trip(&game);
+        let elapsed = Duration::from_millis(saved.elapsed_millis.unwrap());
+        let resumed = saved.into_game(game.config.clone());
+
+        assert_eq!(resumed.debug_tableau_string(), game.debug_tableau_string());
+        assert_eq!(stock_string(&resumed), stock_string(&game));
+        assert_eq!(resumed.foundations, game.foundations);
+        assert_eq!(resumed.score, game.score);
+        assert_eq!(resumed.score_history, game.score_history);
+        assert_eq!(resumed.move_count, game.move_count);
+        assert_eq!(resumed.undo_count, game.undo_count);
+        assert_eq!(resumed.best_run, game.best_run);
+        assert_eq!(resumed.history_moves, game.history_moves);
+        assert_eq!(resumed.redo_moves, game.redo_moves);
+        assert_eq!(resumed.seed, game.seed);
+        // the clock runs on from the saved time
+        assert!(resumed.elapsed() >= elapsed);
+        assert!(resumed.verify_history_integrity());
+    }
+
+    #[test]
+    fn undo_rules_resume_as_saved() {
+        let mut game = game_in_progress();
+        game.config.undo_limit = Some(3);
+        game.config.undo_penalty = Some(7);
+
+        let resumed = round_trip(&game).into_game(GameConfig::default());
+
+        assert_eq!(resumed.config.undo_limit, Some(3));
+        assert_eq!(resumed.config.undo_penalty(), 7);
+        assert_eq!(resumed.undos_remaining(), Some(2));
+    }
+
+    #[test]
+    fn resumed_game_plays_on() {
+        let game = game_in_progress();
+        let mut resumed = round_trip(&game).into_game(game.config.clone());
+
+        let redo = resumed.redo_moves[0];
+        resumed.redo_once();
+
+        assert_eq!(resumed.history_moves.last().unwrap().game_move, redo);
+        assert!(resumed.verify_history_integrity());
+    }
+
+    #[test]
+    fn result_of_an_abandoned_save_matches_the_game() {
+        let game = game_in_progress();
+        let saved = round_trip(&game);
+
+        let from_save = GameResult::from(&saved);
+        let from_game = GameResult::from(&game);
+
+        assert_eq!(from_save.game_suit, from_game.game_suit);
+        assert_eq!(from_save.won, from_game.won);
+        assert_eq!(from_save.moves, from_game.moves);
+        assert_eq!(from_save.undos, from_game.undos);
+        assert_eq!(from_save.best_run, from_game.best_run);
+        assert_eq!(from_save.daily, from_game.daily);
+        assert!(from_save.elapsed <= from_game.elapsed);
+    }
+}
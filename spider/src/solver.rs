@@ -0,0 +1,176 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::game::{Game, GameMove};
+
+/// the default number of moves searched by the solver
+pub const DEFAULT_MAX_DEPTH: usize = 20;
+
+/// the number of states searched before giving up,
+/// a full deal branches too much to search every move
+const MAX_STATES: usize = 1_000_000;
+
+/// the search state shared by every branch
+struct Search {
+    /// the most remaining depth each state has been searched with
+    visited: HashMap<u64, usize>,
+    /// the number of states searched so far
+    states: usize,
+}
+
+/// search a sequence of moves winning the game,
+/// with iterative deepening depth first search
+///
+/// none if there is no win within `max_depth` moves,
+/// or the search gave up
+pub fn solve(game: &Game, max_depth: usize) -> Option<Vec<GameMove>> {
+    let game = game.clone_for_analysis();
+    let mut search = Search {
+        visited: HashMap::new(),
+        states: 0,
+    };
+
+    for depth in 0..=max_depth {
+        search.visited.clear();
+        let mut moves = Vec::new();
+        if search.search(&game, depth, &mut moves) {
+            return Some(moves);
+        }
+        if search.states >= MAX_STATES {
+            return None;
+        }
+    }
+
+    None
+}
+
+impl Search {
+    fn search(&mut self, game: &Game, depth: usize, moves: &mut Vec<GameMove>) -> bool {
+        if game.test_win() {
+            return true;
+        }
+        if depth == 0 || self.states >= MAX_STATES {
+            return false;
+        }
+        self.states += 1;
+
+        // a state already searched at least this deep can't win
+        let hash = state_hash(game);
+        if self.visited.get(&hash).is_some_and(|&d| d >= depth) {
+            return false;
+        }
+        self.visited.insert(hash, depth);
+
+        for game_move in game.legal_moves() {
+            let next = match game.simulate_move(game_move) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            moves.push(game_move);
+            if self.search(&next, depth - 1, moves) {
+                return true;
+            }
+            moves.pop();
+        }
+
+        false
+    }
+}
+
+/// a compact hash of the tableau and the stock
+fn state_hash(game: &Game) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for pile in &game.tableau {
+        pile.len().hash(&mut hasher);
+        for card in pile {
+            card.card.suit.hash(&mut hasher);
+            card.card.rank.hash(&mut hasher);
+            card.is_up.hash(&mut hasher);
+        }
+    }
+    game.stock.len().hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::{GameSuitNumber, Suit},
+        game::RUNS_TO_WIN,
+        test_util::{game_from, move_to},
+    };
+
+    /// a game of two or three piles with all but the last run completed
+    fn last_run(piles: &[&str]) -> Game {
+        let mut game = game_from(GameSuitNumber::One, piles);
+        game.foundations = vec![Suit::Spades; RUNS_TO_WIN - 1];
+
+        game
+    }
+
+    /// test if the moves win the game when played
+    fn wins(game: &Game, moves: &[GameMove]) -> bool {
+        let mut game = game.clone_for_analysis();
+        for game_move in moves {
+            if game.do_move(*game_move).is_err() {
+                return false;
+            }
+        }
+
+        game.test_win()
+    }
+
+    #[test]
+    fn won_game_needs_no_move() {
+        let mut game = game_from(GameSuitNumber::One, &[]);
+        game.foundations = vec![Suit::Spades; RUNS_TO_WIN];
+
+        assert_eq!(solve(&game, 3), Some(vec![]));
+    }
+
+    #[test]
+    fn ace_onto_the_run_is_the_solution() {
+        let game = last_run(&["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S", "AS"]);
+
+        assert_eq!(solve(&game, 3), Some(vec![move_to(&game, 2, 0, 1)]));
+    }
+
+    #[test]
+    fn two_cards_take_two_moves() {
+        let game = last_run(&["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S", "2S", "AS"]);
+
+        let moves = solve(&game, 5).unwrap();
+
+        assert_eq!(moves.len(), 2);
+        assert!(wins(&game, &moves));
+    }
+
+    #[test]
+    fn the_card_in_the_way_is_moved_first() {
+        let game = last_run(&["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S 2S", "AS 9S"]);
+
+        let moves = solve(&game, 5).unwrap();
+
+        assert_eq!(moves.len(), 2);
+        assert!(wins(&game, &moves));
+    }
+
+    #[test]
+    fn no_solution_within_the_depth() {
+        let game = last_run(&["KS QS JS TS 9S 8S 7S 6S 5S 4S 3S", "2S", "AS"]);
+
+        assert_eq!(solve(&game, 1), None);
+    }
+
+    #[test]
+    fn no_solution_without_the_cards() {
+        let game = last_run(&["KS QS JS", "9S"]);
+
+        assert_eq!(solve(&game, 6), None);
+    }
+}
@@ -0,0 +1,417 @@
+use std::{collections::BTreeMap, io, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    card::GameSuitNumber,
+    daily,
+    game::{Game, RUNS_TO_WIN},
+    render::format_elapsed,
+    save::{data_path, SavedGame},
+};
+
+/// The statistics of the games played with one game suit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SuitStats {
+    pub played: u32,
+    pub won: u32,
+    /// the fastest win, in seconds
+    pub best_time_secs: Option<u64>,
+    /// the win with the fewest moves
+    pub fewest_moves: Option<u32>,
+    /// the wins in a row up to the last game
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// the moves undone over all the games
+    #[serde(default)]
+    pub undos: u32,
+    /// the longest run of a single suit built in any game
+    #[serde(default)]
+    pub best_run: u32,
+}
+
+impl SuitStats {
+    /// the part of the games played that were won
+    pub fn win_rate(&self) -> f64 {
+        self.won as f64 / self.played.max(1) as f64
+    }
+
+    /// add a finished game
+    pub fn record(&mut self, result: &GameResult) {
+        self.played += 1;
+        self.undos += result.undos;
+        self.best_run = self.best_run.max(result.best_run);
+
+        if !result.won {
+            self.current_streak = 0;
+            return;
+        }
+
+        self.won += 1;
+        self.current_streak += 1;
+        self.longest_streak = self.longest_streak.max(self.current_streak);
+
+        let secs = result.elapsed.as_secs();
+        self.best_time_secs = Some(self.best_time_secs.map_or(secs, |best| best.min(secs)));
+        self.fewest_moves = Some(
+            self.fewest_moves
+                .map_or(result.moves, |fewest| fewest.min(result.moves)),
+        );
+    }
+}
+
+/// The end of a game, won or abandoned.
+#[derive(Debug, Clone, Copy)]
+pub struct GameResult {
+    pub game_suit: GameSuitNumber,
+    pub won: bool,
+    pub elapsed: Duration,
+    pub moves: u32,
+    /// the moves undone by the player
+    pub undos: u32,
+    /// the longest run of a single suit built
+    pub best_run: u32,
+    /// the day of the daily deal, if it was one
+    pub daily: Option<u64>,
+}
+
+impl From<&Game> for GameResult {
+    fn from(game: &Game) -> Self {
+        GameResult {
+            game_suit: game.game_suit,
+            won: game.test_win(),
+            elapsed: game.elapsed(),
+            moves: game.move_count,
+            undos: game.undo_count,
+            best_run: game.best_run,
+            daily: game.daily,
+        }
+    }
+}
+
+impl From<&SavedGame> for GameResult {
+    fn from(saved: &SavedGame) -> Self {
+        GameResult {
+            game_suit: saved.game_suit,
+            won: saved.foundations.len() == RUNS_TO_WIN,
+            elapsed: Duration::from_millis(saved.elapsed_millis.unwrap_or(0)),
+            moves: saved.move_count,
+            undos: saved.undo_count,
+            best_run: saved.best_run,
+            daily: saved.daily,
+        }
+    }
+}
+
+/// The statistics of all the games, kept in the stats file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub one: SuitStats,
+    pub two: SuitStats,
+    pub four: SuitStats,
+    /// if the daily deal of each day was won,
+    /// only the first daily played that day is kept, whatever its game suit
+    pub daily: BTreeMap<u64, bool>,
+}
+
+impl Stats {
+    /// the statistics of a game suit
+    pub fn suit(&self, game_suit: GameSuitNumber) -> &SuitStats {
+        match game_suit {
+            GameSuitNumber::One => &self.one,
+            GameSuitNumber::Two => &self.two,
+            GameSuitNumber::Four => &self.four,
+        }
+    }
+
+    fn suit_mut(&mut self, game_suit: GameSuitNumber) -> &mut SuitStats {
+        match game_suit {
+            GameSuitNumber::One => &mut self.one,
+            GameSuitNumber::Two => &mut self.two,
+            GameSuitNumber::Four => &mut self.four,
+        }
+    }
+
+    /// merge a finished game into the statistics
+    pub fn record(&mut self, result: &GameResult) {
+        self.suit_mut(result.game_suit).record(result);
+
+        // playing the same daily deal again, or the daily of
+        // another game suit the same day, does not count
+        if let Some(day) = result.daily {
+            self.daily.entry(day).or_insert(result.won);
+        }
+    }
+
+    /// the daily deals won in a row, up to the last one played
+    pub fn daily_streak(&self) -> u32 {
+        let last = match self.daily.keys().next_back() {
+            Some(&last) => last,
+            None => return 0,
+        };
+
+        (0..=last)
+            .rev()
+            .take_while(|day| self.daily.get(day) == Some(&true))
+            .count() as u32
+    }
+
+    /// the most daily deals won on consecutive days
+    pub fn longest_daily_streak(&self) -> u32 {
+        let mut longest = 0;
+        let mut streak = 0;
+        let mut previous = None;
+        for (&day, &won) in &self.daily {
+            streak = match (won, previous) {
+                (false, _) => 0,
+                (true, Some(previous)) if previous + 1 == day => streak + 1,
+                (true, _) => 1,
+            };
+            longest = longest.max(streak);
+            previous = Some(day);
+        }
+
+        longest
+    }
+
+    /// the statistics as a text table
+    pub fn to_table(&self) -> String {
+        let mut output = format!(
+            "{:<6}{:>8}{:>6}{:>10}{:>11}{:>14}{:>8}{:>9}{:>7}{:>10}\n",
+            "Suits",
+            "Played",
+            "Won",
+            "Win rate",
+            "Best time",
+            "Fewest moves",
+            "Streak",
+            "Longest",
+            "Undos",
+            "Best run"
+        );
+
+        let suits = [
+            ("1", GameSuitNumber::One),
+            ("2", GameSuitNumber::Two),
+            ("4", GameSuitNumber::Four),
+        ];
+        for (name, game_suit) in suits {
+            let stats = self.suit(game_suit);
+            output.push_str(&format!(
+                "{:<6}{:>8}{:>6}{:>9.0}%{:>11}{:>14}{:>8}{:>9}{:>7}{:>10}\n",
+                name,
+                stats.played,
+                stats.won,
+                stats.win_rate() * 100.0,
+                stats.best_time_secs.map_or(String::from("-"), |secs| {
+                    format_elapsed(Duration::from_secs(secs))
+                }),
+                stats
+                    .fewest_moves
+                    .map_or(String::from("-"), |moves| moves.to_string()),
+                stats.current_streak,
+                stats.longest_streak,
+                stats.undos,
+                stats.best_run,
+            ));
+        }
+
+        let daily_won = self.daily.values().filter(|&&won| won).count();
+        output.push_str(&format!(
+            "\nDaily: {} played, {} won, streak {}, longest {}",
+            self.daily.len(),
+            daily_won,
+            self.daily_streak(),
+            self.longest_daily_streak()
+        ));
+        if let Some(&last) = self.daily.keys().next_back() {
+            output.push_str(&format!(", last {}", daily::date_string(last)));
+        }
+        output.push('\n');
+
+        output
+    }
+
+    /// read the stats file,
+    /// empty statistics if there is none or it can't be read
+    pub fn load() -> Self {
+        data_path("spider_stats.json")
+            .map(|path| Self::load_from(&path))
+            .unwrap_or_default()
+    }
+
+    /// read a stats file, one that can't be parsed is moved aside
+    /// so the next save does not overwrite it
+    fn load_from(path: &Path) -> Self {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(stats) => stats,
+            Err(_) => {
+                let aside = path.with_extension("json.corrupt");
+                eprintln!(
+                    "warning: can't read the statistics, moved to {}",
+                    aside.display()
+                );
+                let _ = std::fs::rename(path, aside);
+                Self::default()
+            }
+        }
+    }
+
+    /// write the stats file
+    pub fn save(&self) -> io::Result<()> {
+        let path = data_path("spider_stats.json")
+            .ok_or_else(|| io::Error::other("unknown home directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(game_suit: GameSuitNumber, won: bool, secs: u64, moves: u32) -> GameResult {
+        GameResult {
+            game_suit,
+            won,
+            elapsed: Duration::from_secs(secs),
+            moves,
+            undos: 1,
+            best_run: moves / 10,
+            daily: None,
+        }
+    }
+
+    fn daily(day: u64, won: bool) -> GameResult {
+        GameResult {
+            daily: Some(day),
+            ..result(GameSuitNumber::Two, won, 60, 100)
+        }
+    }
+
+    #[test]
+    fn wins_keep_the_best_time_and_fewest_moves() {
+        let mut stats = Stats::default();
+        stats.record(&result(GameSuitNumber::Two, true, 300, 150));
+        stats.record(&result(GameSuitNumber::Two, true, 200, 180));
+        stats.record(&result(GameSuitNumber::Two, false, 10, 5));
+
+        let two = stats.suit(GameSuitNumber::Two);
+        assert_eq!((two.played, two.won), (3, 2));
+        assert_eq!(two.best_time_secs, Some(200));
+        assert_eq!(two.fewest_moves, Some(150));
+        assert_eq!(two.undos, 3);
+        assert_eq!(two.best_run, 18);
+    }
+
+    #[test]
+    fn a_loss_ends_the_streak_not_the_longest() {
+        let mut stats = Stats::default();
+        for won in [true, true, true, false, true] {
+            stats.record(&result(GameSuitNumber::One, won, 100, 100));
+        }
+
+        let one = stats.suit(GameSuitNumber::One);
+        assert_eq!(one.current_streak, 1);
+        assert_eq!(one.longest_streak, 3);
+        assert_eq!(one.win_rate(), 0.8);
+    }
+
+    #[test]
+    fn each_game_suit_is_kept_apart() {
+        let mut stats = Stats::default();
+        stats.record(&result(GameSuitNumber::Four, true, 100, 100));
+
+        assert_eq!(stats.suit(GameSuitNumber::Four).won, 1);
+        assert_eq!(stats.suit(GameSuitNumber::One).played, 0);
+        assert_eq!(stats.suit(GameSuitNumber::Two).played, 0);
+        assert_eq!(stats.suit(GameSuitNumber::Two).win_rate(), 0.0);
+    }
+
+    #[test]
+    fn only_the_first_result_of_a_daily_deal_counts() {
+        let mut stats = Stats::default();
+        stats.record(&daily(10, false));
+        stats.record(&daily(10, true));
+
+        assert_eq!(stats.daily.get(&10), Some(&false));
+        assert_eq!(stats.suit(GameSuitNumber::Two).played, 2);
+    }
+
+    #[test]
+    fn the_daily_of_another_game_suit_the_same_day_does_not_count() {
+        let mut stats = Stats::default();
+        stats.record(&daily(10, false));
+        stats.record(&GameResult {
+            game_suit: GameSuitNumber::Four,
+            ..daily(10, true)
+        });
+
+        assert_eq!(stats.daily.get(&10), Some(&false));
+        assert_eq!(stats.daily.len(), 1);
+        assert_eq!(stats.suit(GameSuitNumber::Four).won, 1);
+    }
+
+    #[test]
+    fn daily_streaks_need_consecutive_days() {
+        let mut stats = Stats::default();
+        for (day, won) in [
+            (1, true),
+            (2, true),
+            (3, true),
+            (4, false),
+            (6, true),
+            (7, true),
+        ] {
+            stats.record(&daily(day, won));
+        }
+
+        assert_eq!(stats.daily_streak(), 2);
+        assert_eq!(stats.longest_daily_streak(), 3);
+        assert_eq!(Stats::default().daily_streak(), 0);
+    }
+
+    #[test]
+    fn stats_file_round_trip() {
+        let mut stats = Stats::default();
+        stats.record(&result(GameSuitNumber::One, true, 90, 120));
+        stats.record(&daily(3, true));
+
+        let content = serde_json::to_string(&stats).unwrap();
+        let parsed: Stats = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(parsed.to_table(), stats.to_table());
+    }
+
+    #[test]
+    fn corrupt_stats_file_is_moved_aside() {
+        let path = std::env::temp_dir().join("spider-stats-test-corrupt.json");
+        let aside = path.with_extension("json.corrupt");
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let stats = Stats::load_from(&path);
+
+        assert_eq!(stats.suit(GameSuitNumber::Two).played, 0);
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(&aside).unwrap(), "{ not json");
+
+        std::fs::remove_file(&aside).unwrap();
+    }
+
+    #[test]
+    fn missing_stats_file_is_empty_statistics() {
+        let path = std::env::temp_dir().join("spider-stats-test-missing.json");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(Stats::load_from(&path).daily.len(), 0);
+        assert!(!path.with_extension("json.corrupt").exists());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{
+    card::{Card, GameCard, GameSuitNumber},
+    config::GameConfig,
+    game::{CardPosition, Deal, Game, GameMove},
+};
+
+/// the cards written in the card notation, separated by spaces,
+/// lower case for a face down card, e.g. `"ks qs JH"`
+pub(crate) fn cards(s: &str) -> Vec<GameCard> {
+    s.split_whitespace()
+        .map(|notation| GameCard {
+            card: Card::from_notation(notation).unwrap(),
+            is_up: notation.chars().any(|c| c.is_ascii_uppercase()),
+        })
+        .collect()
+}
+
+/// a game of the given suits with a hand made tableau,
+/// each pile written bottom first, the missing piles empty
+///
+/// the stock is empty and the seed fixed
+pub(crate) fn game_from(game_suit: GameSuitNumber, piles: &[&str]) -> Game {
+    let config = GameConfig {
+        seed: Some(0),
+        no_animation: true,
+        ..GameConfig::default()
+    };
+    let deal = Deal {
+        tableau: (0..10)
+            .map(|i| piles.get(i).map(|pile| cards(pile)).unwrap_or_default())
+            .collect(),
+        stock: Vec::new(),
+    };
+
+    Game::from_deal(game_suit, 0, deal, config)
+}
+
+/// the face up flag of every card, pile by pile
+pub(crate) fn face_up_flags(game: &Game) -> Vec<Vec<bool>> {
+    game.tableau
+        .iter()
+        .map(|pile| pile.iter().map(|card| card.is_up).collect())
+        .collect()
+}
+
+/// the move of the cards from a card of a pile onto the top of another pile
+pub(crate) fn move_to(game: &Game, pile: usize, card: usize, dst: usize) -> GameMove {
+    GameMove::MoveCard {
+        src: CardPosition { pile, card },
+        dst: CardPosition {
+            pile: dst,
+            card: game.tableau[dst - 1].len(),
+        },
+        before_visible: None,
+    }
+}
@@ -0,0 +1,537 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    card::{Card, GameCard, GameSuitNumber, Rank, Suit},
+    config::GameConfig,
+    game::{verify_under, CardPosition, Deal, Game, GameMove, DEAL_SIZE, RUNS_TO_WIN, STOCK_SIZE},
+};
+
+/// the face down cards of each pile in a deal
+const FACE_DOWN: [usize; 10] = [5, 5, 5, 5, 4, 4, 4, 4, 4, 4];
+
+/// the rows dealt from the stock during a game
+const DEALS: usize = STOCK_SIZE / DEAL_SIZE;
+
+/// the tries made before giving up on a seed
+const MAX_ATTEMPTS: usize = 1000;
+
+/// A deal made backwards from a won game,
+/// with the moves winning it.
+#[derive(Debug, Clone)]
+pub struct WinnableDeal {
+    pub tableau: Vec<Vec<GameCard>>,
+    pub stock: Vec<GameCard>,
+    /// a winning line from the deal, the completed runs left out
+    pub solution: Vec<GameMove>,
+}
+
+impl WinnableDeal {
+    /// play the winning line on the deal
+    pub fn is_won_by_solution(&self, game_suit: GameSuitNumber) -> bool {
+        let deal = Deal {
+            tableau: self.tableau.clone(),
+            stock: self.stock.clone(),
+        };
+        // the seed is only shown to the player
+        let mut game = Game::from_deal(game_suit, 0, deal, GameConfig::default());
+
+        self.solution
+            .iter()
+            .all(|game_move| game.do_move(*game_move).is_ok())
+            && game.test_win()
+    }
+}
+
+/// a deal known to be winnable, made from a seed
+///
+/// the game is played backwards from a won one,
+/// each step undoing a move that would have been legal,
+/// and the winning line is checked by playing it again
+pub fn deal(game_suit: GameSuitNumber, seed: u64) -> Option<WinnableDeal> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    (0..MAX_ATTEMPTS).find_map(|_| {
+        let mut backward = Backward::won(game_suit, &mut rng);
+        backward.unwind(&mut rng)?;
+
+        let mut solution = backward.moves;
+        solution.reverse();
+        let deal = WinnableDeal {
+            tableau: backward.tableau,
+            stock: backward.stock,
+            solution,
+        };
+
+        deal.is_won_by_solution(game_suit).then_some(deal)
+    })
+}
+
+/// The game played backwards, from the won game to the deal.
+///
+/// The face up cards of a pile are kept in sequence,
+/// but for the cards about to go back in the stock,
+/// so they can all be moved away again when folding the piles into a deal.
+#[derive(Clone)]
+struct Backward {
+    game_suit: GameSuitNumber,
+    tableau: Vec<Vec<GameCard>>,
+    stock: Vec<GameCard>,
+    foundations: Vec<Suit>,
+    /// the moves of the winning line, the last one first
+    moves: Vec<GameMove>,
+}
+
+impl Backward {
+    /// the won game, the suits completed in a random order
+    fn won(game_suit: GameSuitNumber, rng: &mut StdRng) -> Self {
+        let suits = game_suit.card_suits_allowed();
+        let mut foundations: Vec<Suit> = (0..RUNS_TO_WIN).map(|i| suits[i % suits.len()]).collect();
+        foundations.shuffle(rng);
+
+        Backward {
+            game_suit,
+            tableau: vec![Vec::new(); 10],
+            stock: Vec::new(),
+            foundations,
+            moves: Vec::new(),
+        }
+    }
+
+    /// go back from the won game to a deal,
+    /// none if the random steps led nowhere
+    fn unwind(&mut self, rng: &mut StdRng) -> Option<()> {
+        // the runs put back before each row,
+        // enough for two cards on every pile
+        let mut schedule = [RUNS_TO_WIN; DEALS];
+        let mut placed = 0;
+        for (i, runs) in schedule.iter_mut().enumerate().take(DEALS - 1) {
+            let needed = (2 * 10 + i * DEAL_SIZE).div_ceil(13);
+            placed = rng.gen_range(needed.max(placed)..=RUNS_TO_WIN);
+            *runs = placed;
+        }
+
+        for runs in schedule {
+            while self.foundations.len() > RUNS_TO_WIN - runs {
+                self.put_back_run(rng)?;
+            }
+            // a few moves mixing the runs
+            for _ in 0..rng.gen_range(0..8) {
+                let movable = self.movable_runs();
+                self.random_move_back(rng, movable, |_, _, _, _| true);
+            }
+            self.fill_empty_piles(rng)?;
+            self.top_up(rng)?;
+            self.deal_back()?;
+        }
+
+        self.fold(rng)
+    }
+
+    /// the number of face down cards of a pile, all at its bottom
+    fn face_down(&self, pile: usize) -> usize {
+        self.tableau[pile]
+            .iter()
+            .take_while(|card| !card.is_up)
+            .count()
+    }
+
+    /// the cards a run can start from to be moved away from a pile,
+    /// each sitting on a card it is valid under
+    fn split_points(&self, pile: usize) -> Vec<usize> {
+        let cards = &self.tableau[pile];
+
+        (1..cards.len())
+            .rev()
+            .take_while(|&card| {
+                cards[card - 1].is_up
+                    && verify_under(self.game_suit, cards[card - 1].card, cards[card].card)
+            })
+            .collect()
+    }
+
+    /// if the cards from a card of a pile could have been moved onto another pile
+    fn joins(&self, from: usize, card: usize, to: usize) -> bool {
+        match self.tableau[to].last() {
+            Some(onto) => verify_under(self.game_suit, onto.card, self.tableau[from][card].card),
+            None => true,
+        }
+    }
+
+    /// if the top cards of a pile are a completed run
+    fn has_completed_run(&self, pile: usize) -> bool {
+        let cards = &self.tableau[pile];
+        if cards.len() < 13 {
+            return false;
+        }
+
+        let run = &cards[cards.len() - 13..];
+        run.iter().enumerate().all(|(i, card)| {
+            card.is_up
+                && card.card.suit == run[0].card.suit
+                && u8::from(card.card.rank) == 13 - i as u8
+        })
+    }
+
+    /// move the cards from a card of a pile back onto another one,
+    /// over the card under them turned down if `flip`
+    ///
+    /// the move made is the other way round,
+    /// not taken if it leaves a completed run on the tableau
+    fn move_back(&mut self, from: usize, card: usize, to: usize, flip: bool) -> bool {
+        let before = (self.tableau[from].clone(), self.tableau[to].clone());
+
+        let run = self.tableau[from].split_off(card);
+        let src = CardPosition {
+            pile: to + 1,
+            card: self.tableau[to].len(),
+        };
+        if flip {
+            if let Some(last) = self.tableau[to].last_mut() {
+                last.is_up = false;
+            }
+        }
+        self.tableau[to].extend(run);
+
+        if self.has_completed_run(from) || self.has_completed_run(to) {
+            (self.tableau[from], self.tableau[to]) = before;
+            return false;
+        }
+
+        self.moves.push(GameMove::MoveCard {
+            src,
+            dst: CardPosition {
+                pile: from + 1,
+                card,
+            },
+            before_visible: Some(flip),
+        });
+
+        true
+    }
+
+    /// if a pile has a single face up card,
+    /// which can be turned down under some cards moved back onto it
+    fn can_turn_down(&self, pile: usize) -> bool {
+        let face_down = self.face_down(pile);
+
+        face_down < FACE_DOWN[pile] && self.tableau[pile].len() == face_down + 1
+    }
+
+    /// the piles the cards from a card of a pile can be moved back onto,
+    /// and if the card under them is turned down
+    fn targets(&self, from: usize, card: usize) -> Vec<(usize, bool)> {
+        let mut targets = Vec::new();
+        for to in (0..10).filter(|&to| to != from) {
+            if self.can_turn_down(to) {
+                targets.push((to, true));
+            }
+            if self.joins(from, card, to) {
+                targets.push((to, false));
+            }
+        }
+
+        targets
+    }
+
+    /// the runs that can be moved away from each pile,
+    /// by the pile and the first card of the run
+    fn movable_runs(&self) -> Vec<(usize, usize)> {
+        (0..10)
+            .flat_map(|from| {
+                let mut cards = self.split_points(from);
+                // a whole pile of face up cards in sequence
+                let len = self.tableau[from].len();
+                if self.face_down(from) == 0 && (len == 1 || cards.contains(&1)) {
+                    cards.push(0);
+                }
+                cards.into_iter().map(move |card| (from, card))
+            })
+            .collect()
+    }
+
+    /// move a random run back onto one of the given places
+    fn random_move_back(
+        &mut self,
+        rng: &mut StdRng,
+        runs: Vec<(usize, usize)>,
+        keep: impl Fn(usize, usize, usize, bool) -> bool,
+    ) -> bool {
+        let mut moves: Vec<(usize, usize, usize, bool)> = runs
+            .into_iter()
+            .flat_map(|(from, card)| {
+                self.targets(from, card)
+                    .into_iter()
+                    .map(move |(to, flip)| (from, card, to, flip))
+            })
+            .filter(|&(from, card, to, flip)| keep(from, card, to, flip))
+            .collect();
+        moves.shuffle(rng);
+
+        moves
+            .into_iter()
+            .any(|(from, card, to, flip)| self.move_back(from, card, to, flip))
+    }
+
+    /// put the last completed run back on an empty pile
+    /// or over a card turned down,
+    /// then split it by the move that completed it
+    fn put_back_run(&mut self, rng: &mut StdRng) -> Option<()> {
+        let mut places: Vec<(usize, bool)> = (0..10)
+            .filter(|&pile| self.tableau[pile].is_empty())
+            .map(|pile| (pile, false))
+            .chain(
+                (0..10)
+                    .filter(|&pile| self.can_turn_down(pile))
+                    .map(|pile| (pile, true)),
+            )
+            .collect();
+        if places.is_empty() {
+            // move a whole pile away to make room
+            let runs = self
+                .movable_runs()
+                .into_iter()
+                .filter(|&(_, card)| card == 0)
+                .collect();
+            if !self.random_move_back(rng, runs, |_, _, _, _| true) {
+                return None;
+            }
+            places = (0..10)
+                .filter(|&pile| self.tableau[pile].is_empty())
+                .map(|pile| (pile, false))
+                .collect();
+        }
+        let &(pile, flip) = places.choose(rng)?;
+
+        let suit = self.foundations.pop()?;
+        let cards = &mut self.tableau[pile];
+        if flip {
+            cards.last_mut()?.is_up = false;
+        }
+        let start = cards.len();
+        cards.extend((1..14).rev().map(|rank| GameCard {
+            card: Card {
+                suit,
+                rank: Rank::try_from(rank).unwrap(),
+            },
+            is_up: true,
+        }));
+
+        let runs = (start + 1..start + 13).map(|card| (pile, card)).collect();
+        self.random_move_back(rng, runs, |_, _, _, _| true)
+            .then_some(())
+    }
+
+    /// split the runs so every pile gets some cards
+    fn fill_empty_piles(&mut self, rng: &mut StdRng) -> Option<()> {
+        while let Some(empty) = (0..10).find(|&pile| self.tableau[pile].is_empty()) {
+            let runs = self
+                .movable_runs()
+                .into_iter()
+                .filter(|&(_, card)| card > 0)
+                .collect();
+            if !self.random_move_back(rng, runs, |_, _, to, _| to == empty) {
+                return None;
+            }
+        }
+
+        Some(())
+    }
+
+    /// put a card from the top of a run on the piles too short to deal from,
+    /// and on a few others, to be dealt back to the stock
+    fn top_up(&mut self, rng: &mut StdRng) -> Option<()> {
+        let face_up = |backward: &Backward, pile: usize| {
+            backward.tableau[pile].len() - backward.face_down(pile)
+        };
+
+        let mut topped = [false; 10];
+        let mut piles: Vec<usize> = (0..10).collect();
+        piles.shuffle(rng);
+
+        for to in piles {
+            if face_up(self, to) >= 2 && !rng.gen_bool(0.3) {
+                continue;
+            }
+
+            // the giving run keeps two cards to deal from
+            let mut givers: Vec<usize> = (0..10)
+                .filter(|&from| from != to && !topped[from] && face_up(self, from) >= 3)
+                .filter(|&from| {
+                    self.split_points(from)
+                        .contains(&(self.tableau[from].len() - 1))
+                })
+                .collect();
+            givers.shuffle(rng);
+
+            let given = givers.into_iter().any(|from| {
+                let card = self.tableau[from].len() - 1;
+                self.move_back(from, card, to, false)
+            });
+            if given {
+                topped[to] = true;
+            } else if face_up(self, to) < 2 {
+                return None;
+            }
+        }
+
+        Some(())
+    }
+
+    /// put the top card of each pile back in the stock
+    fn deal_back(&mut self) -> Option<()> {
+        let can_deal = self
+            .tableau
+            .iter()
+            .all(|pile| pile.len() >= 2 && pile[pile.len() - 2].is_up);
+        if !can_deal {
+            return None;
+        }
+
+        for pile in self.tableau.iter_mut().rev() {
+            let mut card = pile.pop()?;
+            card.is_up = false;
+            self.stock.push(card);
+        }
+        if (0..10).any(|pile| self.has_completed_run(pile)) {
+            return None;
+        }
+        self.moves.push(GameMove::DealRow);
+
+        Some(())
+    }
+
+    /// turn the piles down into a deal,
+    /// moving runs back onto the piles with a single face up card
+    ///
+    /// a pile with cards to give and room for face down cards
+    /// gives all its face up cards but one,
+    /// so there is always a pile left to take them
+    fn fold(&mut self, rng: &mut StdRng) -> Option<()> {
+        loop {
+            let receivers: Vec<usize> = (0..10).filter(|&pile| self.can_turn_down(pile)).collect();
+            let givers: Vec<usize> = (0..10)
+                .filter(|&pile| self.tableau[pile].len() > self.face_down(pile) + 1)
+                .collect();
+            if givers.is_empty() {
+                let is_deal = (0..10).all(|pile| {
+                    self.face_down(pile) == FACE_DOWN[pile]
+                        && self.tableau[pile].len() == FACE_DOWN[pile] + 1
+                });
+                return is_deal.then_some(());
+            }
+
+            let with_room: Vec<usize> = givers
+                .iter()
+                .copied()
+                .filter(|&pile| self.face_down(pile) < FACE_DOWN[pile])
+                .collect();
+            let (from, card) = match with_room.choose(rng) {
+                Some(&from) => (from, self.face_down(from) + 1),
+                None => {
+                    let from = *givers.choose(rng)?;
+                    (from, self.tableau[from].len() - 1)
+                }
+            };
+            let to = *receivers.choose(rng)?;
+            if !self.move_back(from, card, to, true) {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_SUITS: [GameSuitNumber; 3] = [
+        GameSuitNumber::One,
+        GameSuitNumber::Two,
+        GameSuitNumber::Four,
+    ];
+
+    /// the cards of a deal in the card notation,
+    /// the face down ones in lower case
+    fn layout(deal: &WinnableDeal) -> String {
+        let game = Game::from_deal(
+            GameSuitNumber::Four,
+            0,
+            Deal {
+                tableau: deal.tableau.clone(),
+                stock: deal.stock.clone(),
+            },
+            GameConfig::default(),
+        );
+        let stock: String = deal
+            .stock
+            .iter()
+            .map(|card| card.card.to_notation())
+            .collect();
+
+        format!("{}{}", game.debug_tableau_string(), stock)
+    }
+
+    #[test]
+    fn deal_has_the_cards_of_a_fresh_deal() {
+        for game_suit in GAME_SUITS {
+            for seed in 0..5 {
+                let deal = deal(game_suit, seed).unwrap();
+
+                assert!(game_suit.verify_deal_is_valid(&deal.tableau, &deal.stock));
+                assert_eq!(deal.stock.len(), STOCK_SIZE);
+                assert!(deal.stock.iter().all(|card| !card.is_up));
+                for (pile, cards) in deal.tableau.iter().enumerate() {
+                    let face_down = cards.iter().take_while(|card| !card.is_up).count();
+                    assert_eq!(face_down, FACE_DOWN[pile]);
+                    assert_eq!(cards.len(), FACE_DOWN[pile] + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solution_replays_to_a_win() {
+        for game_suit in GAME_SUITS {
+            for seed in 0..5 {
+                let deal = deal(game_suit, seed).unwrap();
+                let config = GameConfig {
+                    seed: Some(seed),
+                    winnable: true,
+                    no_animation: true,
+                    ..GameConfig::default()
+                };
+                let mut game = Game::new(game_suit, config);
+                assert_eq!(
+                    game.debug_tableau_string(),
+                    Game::from_deal(
+                        game_suit,
+                        seed,
+                        Deal {
+                            tableau: deal.tableau.clone(),
+                            stock: deal.stock.clone(),
+                        },
+                        GameConfig::default(),
+                    )
+                    .debug_tableau_string()
+                );
+
+                for game_move in &deal.solution {
+                    assert!(game.do_move(*game_move).is_ok());
+                }
+                assert!(game.test_win());
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_deal() {
+        for game_suit in GAME_SUITS {
+            let first = deal(game_suit, 11).unwrap();
+            let again = deal(game_suit, 11).unwrap();
+
+            assert_eq!(layout(&first), layout(&again));
+            assert_eq!(first.solution, again.solution);
+            assert_ne!(layout(&first), layout(&deal(game_suit, 12).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+};
+
+/// the spider binary, run away from the config and saves of the real home
+fn spider(args: &[&str]) -> Command {
+    let home = std::env::temp_dir().join("spider-cli-test-home");
+    std::fs::create_dir_all(&home).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_spider"));
+    command
+        .args(args)
+        .env("HOME", home)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped());
+
+    command
+}
+
+/// the deal printed by the solver, before the search starts:
+/// the seed, the stock, the runs and the 10 piles
+fn printed_deal(args: &[&str]) -> Vec<String> {
+    let mut child = spider(args).stdout(Stdio::piped()).spawn().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let lines = BufReader::new(stdout)
+        .lines()
+        .take(13)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    // the search of a full deal takes too long to wait for
+    let _ = child.kill();
+    child.wait().unwrap();
+
+    lines
+}
+
+#[test]
+fn suits_and_seed_start_without_the_prompt() {
+    let lines = printed_deal(&["--solve", "--suits", "4", "--seed", "42"]);
+
+    assert_eq!(lines.len(), 13);
+    assert_eq!(lines[0], "Seed: 42");
+    assert_eq!(lines[1], "Stock: 50 cards, 5 deals left");
+    assert!(lines
+        .iter()
+        .all(|line| !line.contains("select a game suit")));
+}
+
+#[test]
+fn same_seed_prints_the_same_deal() {
+    let args = ["--solve", "--suits", "4", "--seed", "42"];
+
+    assert_eq!(printed_deal(&args), printed_deal(&args));
+}
+
+#[test]
+fn one_suit_deals_only_spades() {
+    let lines = printed_deal(&["--solve", "-s", "1", "--seed", "42"]);
+
+    for line in &lines[3..] {
+        let (_, cards) = line.split_once(':').unwrap();
+        assert!(cards
+            .split_whitespace()
+            .all(|card| card == "##" || card.ends_with('S')));
+    }
+}
+
+#[test]
+fn invalid_suits_exit_with_an_error() {
+    let output = spider(&["--suits", "3"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid number of suits 3"));
+}
+
+#[test]
+fn help_lists_the_suits() {
+    let output = spider(&["--help"]).output().unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--suits 1|2|4"));
+}